@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelLayout {
+    Mono,
+    Stereo,
+}
+
+/// What a track carries, which decides the processing it gets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackRole {
+    /// The player's mic: gets voice cleanup and ducks game audio.
+    Voice,
+    /// Teammates, e.g. Discord: ducks game audio.
+    Chat,
+    /// Ducked under voice and chat.
+    Game,
+    #[default]
+    Other,
+}
+
+impl TrackRole {
+    pub fn is_speech(self) -> bool {
+        matches!(self, TrackRole::Voice | TrackRole::Chat)
+    }
+}
+
+/// One audio stream in a clip and how it's mixed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioTrack {
+    pub name: String,
+    /// Index among the clip's audio streams (`0:a:N`).
+    pub stream: usize,
+    #[serde(default = "default_layout")]
+    pub layout: ChannelLayout,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub role: TrackRole,
+    /// Stream title when exported as a stem; defaults to the name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+fn default_layout() -> ChannelLayout {
+    ChannelLayout::Stereo
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl AudioTrack {
+    fn new(name: &str, stream: usize, layout: ChannelLayout) -> Self {
+        Self {
+            name: name.to_string(),
+            stream,
+            layout,
+            weight: default_weight(),
+            enabled: default_enabled(),
+            role: TrackRole::Other,
+            title: None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.clone();
+        }
+        let mut chars = self.name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+}
+
+/// Whether exported clips keep the source tracks next to the mix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioExport {
+    Mixed,
+    /// The mix first, then every source track as a titled stream.
+    Stems,
+}
+
+impl AudioExport {
+    pub const ALL: [AudioExport; 2] = [AudioExport::Mixed, AudioExport::Stems];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AudioExport::Mixed => "mixed",
+            AudioExport::Stems => "stems",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioExport::Mixed => "Mixed only",
+            AudioExport::Stems => "Mixed plus stems",
+        }
+    }
+}
+
+impl FromStr for AudioExport {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        AudioExport::ALL
+            .into_iter()
+            .find(|export| export.as_str().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| anyhow!("unknown audio export '{}', expected 'mixed' or 'stems'", value))
+    }
+}
+
+/// What each audio stream holds, from `~/.config/clips-app/audio_tracks.json`.
+/// Without the file the default `--capture-audio` layout is assumed: mono
+/// mic, Discord, then everything else as game audio.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackMap {
+    pub tracks: Vec<AudioTrack>,
+}
+
+impl Default for TrackMap {
+    fn default() -> Self {
+        Self {
+            tracks: vec![
+                AudioTrack {
+                    role: TrackRole::Voice,
+                    title: Some("Mic".to_string()),
+                    ..AudioTrack::new("voice", 0, ChannelLayout::Mono)
+                },
+                AudioTrack {
+                    role: TrackRole::Chat,
+                    ..AudioTrack::new("discord", 1, ChannelLayout::Stereo)
+                },
+                AudioTrack {
+                    role: TrackRole::Game,
+                    ..AudioTrack::new("game", 2, ChannelLayout::Stereo)
+                },
+            ],
+        }
+    }
+}
+
+impl TrackMap {
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context("failed to read audio track map")?;
+        let map: TrackMap = serde_json::from_str(&contents)
+            .context("failed to parse audio track map")?;
+        map.validate()
+            .with_context(|| format!("invalid audio track map {}", path.display()))?;
+        Ok(map)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        let mut streams = HashSet::new();
+        for track in &self.tracks {
+            if track.name.trim().is_empty() {
+                bail!("the track for stream {} has no name", track.stream);
+            }
+            if !names.insert(track.name.to_lowercase()) {
+                bail!("track name '{}' is used more than once", track.name);
+            }
+            if !streams.insert(track.stream) {
+                bail!("stream {} is mapped more than once", track.stream);
+            }
+            if !track.weight.is_finite() || track.weight < 0.0 {
+                bail!("track '{}' has an invalid weight {}", track.name, track.weight);
+            }
+        }
+        Ok(())
+    }
+
+    /// The map for a clip captured from `sources`, one stream per source.
+    /// Tracks past the last stream are dropped and unmapped streams are added
+    /// under their source name.
+    pub fn for_sources(&self, sources: &[String]) -> Self {
+        let mut tracks = Vec::new();
+        for track in &self.tracks {
+            if track.stream < sources.len() {
+                tracks.push(track.clone());
+            } else {
+                eprintln!(
+                    "[CLIPS_APP] Audio track '{}' maps stream {} but only {} are captured",
+                    track.name,
+                    track.stream,
+                    sources.len()
+                );
+            }
+        }
+        for (stream, source) in sources.iter().enumerate() {
+            if tracks.iter().any(|track| track.stream == stream) {
+                continue;
+            }
+            let name = if tracks.iter().any(|track| track.name.eq_ignore_ascii_case(source)) {
+                format!("track{}", stream + 1)
+            } else {
+                source.clone()
+            };
+            tracks.push(AudioTrack::new(&name, stream, ChannelLayout::Stereo));
+        }
+        tracks.sort_by_key(|track| track.stream);
+        Self { tracks }
+    }
+
+    /// Like [`TrackMap::for_sources`] for a file whose sources aren't known.
+    pub fn for_stream_count(&self, count: usize) -> Self {
+        let sources = (1..=count).map(|n| format!("track{n}")).collect::<Vec<_>>();
+        self.for_sources(&sources)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AudioTrack> {
+        self.tracks
+            .iter()
+            .find(|track| track.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut AudioTrack> {
+        self.tracks
+            .iter_mut()
+            .find(|track| track.name.eq_ignore_ascii_case(name))
+    }
+
+    fn config_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".config/clips-app/audio_tracks.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn names(map: &TrackMap) -> Vec<(usize, &str)> {
+        map.tracks
+            .iter()
+            .map(|track| (track.stream, track.name.as_str()))
+            .collect()
+    }
+
+    fn validation_error(map: &TrackMap) -> String {
+        format!("{:#}", map.validate().unwrap_err())
+    }
+
+    #[test]
+    fn validate_rejects_ambiguous_maps() {
+        assert!(TrackMap::default().validate().is_ok());
+
+        let mut map = TrackMap::default();
+        map.tracks[1].name = "Voice".to_string();
+        assert!(validation_error(&map).contains("'Voice' is used more than once"));
+
+        let mut map = TrackMap::default();
+        map.tracks[2].stream = 0;
+        assert!(validation_error(&map).contains("stream 0 is mapped more than once"));
+
+        let mut map = TrackMap::default();
+        map.tracks[0].name = " ".to_string();
+        assert!(validation_error(&map).contains("stream 0 has no name"));
+
+        for weight in [-0.5, f32::NAN, f32::INFINITY] {
+            let mut map = TrackMap::default();
+            map.tracks[2].weight = weight;
+            assert!(validation_error(&map).contains("invalid weight"), "{weight}");
+        }
+    }
+
+    #[test]
+    fn for_sources_fits_the_map_to_the_captured_streams() {
+        let map = TrackMap::default();
+
+        // Fewer streams than mapped drops the extra tracks
+        let fitted = map.for_sources(&sources(&["default_input", "app:discord"]));
+        assert_eq!(names(&fitted), [(0, "voice"), (1, "discord")]);
+
+        // Unmapped streams are named after their source, unless that clashes
+        let fitted = map.for_sources(&sources(&[
+            "default_input",
+            "app:discord",
+            "default_output",
+            "game",
+            "app:obs",
+        ]));
+        assert_eq!(
+            names(&fitted),
+            [(0, "voice"), (1, "discord"), (2, "game"), (3, "track4"), (4, "app:obs")]
+        );
+        assert_eq!(fitted.get("app:obs").unwrap().role, TrackRole::Other);
+        assert_eq!(fitted.get("Voice").unwrap().layout, ChannelLayout::Mono);
+
+        let fitted = map.for_stream_count(1);
+        assert_eq!(names(&fitted), [(0, "voice")]);
+    }
+}
@@ -1,14 +1,18 @@
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Context, Result};
-use std::convert::TryFrom;
-use nix::sys::signal::{self, Signal};
-use nix::unistd::Pid;
 use serde::{Deserialize, Serialize};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{Child, Command};
-use tokio::time::{sleep, Duration};
+use tokio::time::{Duration, Instant};
+
+use crate::audio::TrackMap;
+use crate::ffmpeg;
+use crate::recorder::{RecorderBackend, RecorderLog, RecorderState};
+use crate::games::DetectedGame;
+use crate::metadata::{ClipKind, ClipMetadata};
+use crate::sessions::SessionLog;
+use crate::settings::CaptureProfile;
+use crate::storage::{self, DiskUsage, StoragePolicy};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ReplayStorage {
@@ -17,7 +21,7 @@ pub enum ReplayStorage {
 }
 
 impl ReplayStorage {
-    fn as_str(self) -> &'static str {
+    pub(crate) fn as_str(self) -> &'static str {
         match self {
             ReplayStorage::Ram => "ram",
             ReplayStorage::Disk => "disk",
@@ -27,18 +31,30 @@ impl ReplayStorage {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplaySettings {
-    #[serde(skip)]
-    pub binary: PathBuf,
     pub target: String,
     pub buffer_seconds: u32,
     pub bitrate: u32,
     pub fps: u32,
     pub audio_tracks: Vec<String>,
+    /// What each captured audio stream holds, recorded with every clip.
+    #[serde(skip)]
+    pub track_map: TrackMap,
     #[serde(skip)]
     pub restore_portal_session: bool,
     pub replay_storage: ReplayStorage,
     #[serde(skip)]
     pub output_dir: PathBuf,
+    /// Durations offered as save buttons in the capture view.
+    pub save_durations: Vec<u32>,
+    /// How long to wait for a saved replay to be fully written.
+    #[serde(skip)]
+    pub save_timeout_secs: u32,
+    /// Consecutive quick crashes tolerated before the supervisor gives up.
+    #[serde(skip)]
+    pub max_restarts: u32,
+    /// Quota and age limits for saved replays and leftover raw files.
+    #[serde(skip)]
+    pub storage: StoragePolicy,
 }
 
 impl ReplaySettings {
@@ -55,11 +71,90 @@ impl ReplaySettings {
         if self.audio_tracks.is_empty() {
             self.audio_tracks.push("default_input".to_string());
         }
+        if self.save_timeout_secs == 0 {
+            self.save_timeout_secs = 30;
+        }
         self.audio_tracks
             .retain(|track| !track.trim().is_empty());
+        self.save_durations.retain(|secs| *secs > 0);
+        self.save_durations.sort_unstable();
+        self.save_durations.dedup();
+        if self.save_durations.is_empty() {
+            self.save_durations = DEFAULT_SAVE_DURATIONS.to_vec();
+        }
+    }
+}
+
+pub const DEFAULT_SAVE_DURATIONS: &[u32] = &[60, 300];
+
+/// Parses durations like `45`, `45s`, `2m`, `1m30s`, `1h` or `7d` into seconds.
+pub fn parse_duration(value: &str) -> Result<u32> {
+    let value = value.trim().to_ascii_lowercase();
+    if value.is_empty() {
+        return Err(anyhow!("empty duration"));
+    }
+    let total = if let Ok(secs) = value.parse::<u32>() {
+        secs
+    } else {
+        parse_duration_units(&value)?
+    };
+    if total == 0 {
+        return Err(anyhow!("duration '{}' must be longer than zero", value));
+    }
+    Ok(total)
+}
+
+fn parse_duration_units(value: &str) -> Result<u32> {
+    let mut total = 0u32;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86_400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(anyhow!("invalid duration '{}'", value)),
+        };
+        let amount = digits
+            .parse::<u32>()
+            .with_context(|| format!("invalid duration '{}'", value))?;
+        total = amount
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| anyhow!("duration '{}' is too long", value))?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(anyhow!("invalid duration '{}' (missing unit after {})", value, digits));
     }
+    Ok(total)
 }
 
+/// Formats seconds compactly, e.g. `45s`, `2m`, `1m30s`.
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{hours}h"));
+    }
+    if minutes > 0 {
+        out.push_str(&format!("{minutes}m"));
+    }
+    if seconds > 0 || out.is_empty() {
+        out.push_str(&format!("{seconds}s"));
+    }
+    out
+}
+
+/// Runs shorter than this count as a rapid failure for backoff purposes.
+const HEALTHY_RUN: Duration = Duration::from_secs(30);
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Clone)]
 pub struct ReplayStatus {
     pub running: bool,
@@ -68,111 +163,182 @@ pub struct ReplayStatus {
     pub fps: u32,
     pub target: String,
     pub audio_tracks: Vec<String>,
+    pub save_durations: Vec<u32>,
     pub last_saved: Option<PathBuf>,
     pub message: Option<String>,
+    pub restart_count: u32,
+    pub last_exit: Option<String>,
+    pub recent_stderr: Vec<String>,
+    /// Seconds into the current full-length recording, if one is running.
+    pub recording_secs: Option<u64>,
+    pub disk_usage: Option<DiskUsage>,
+    /// Game whose capture profile is currently applied.
+    pub active_profile: Option<String>,
+}
+
+/// Restart bookkeeping for the recorder process.
+#[derive(Default)]
+struct Supervisor {
+    /// Whether the recorder should be running (cleared by stop or giving up).
+    wanted: bool,
+    started_at: Option<Instant>,
+    rapid_failures: u32,
+    restart_count: u32,
+    next_restart: Option<Instant>,
+    last_exit: Option<String>,
 }
 
 pub struct ReplayController {
     settings: ReplaySettings,
-    child: Option<Child>,
+    backend: Box<dyn RecorderBackend>,
+    running: bool,
     last_saved: Option<PathBuf>,
+    /// Recordings finished while nothing could process them straight away,
+    /// oldest first.
+    finished_recordings: Vec<PathBuf>,
     last_message: Option<String>,
+    bookmarks: Vec<SystemTime>,
+    supervisor: Supervisor,
+    log: RecorderLog,
+    recording_started: Option<Instant>,
+    /// Start of a recording the recorder cut short, until its file is recovered.
+    interrupted_recording: Option<Instant>,
+    disk_usage: Option<DiskUsage>,
+    /// The game whose profile is applied and the settings to restore after.
+    active_profile: Option<(String, ReplaySettings)>,
+    /// The running game and the session that saved clips are logged against.
+    game: Option<DetectedGame>,
+    session_id: Option<String>,
+    /// Context for the current recording, taken when it started.
+    recording_metadata: Option<ClipMetadata>,
 }
 
 impl ReplayController {
-    pub fn new(settings: ReplaySettings) -> Self {
+    pub fn new(settings: ReplaySettings, backend: Box<dyn RecorderBackend>) -> Self {
         Self {
             settings,
-            child: None,
+            backend,
+            running: false,
             last_saved: None,
+            finished_recordings: Vec::new(),
             last_message: None,
+            bookmarks: Vec::new(),
+            supervisor: Supervisor::default(),
+            log: RecorderLog::default(),
+            recording_started: None,
+            interrupted_recording: None,
+            disk_usage: None,
+            active_profile: None,
+            game: None,
+            session_id: None,
+            recording_metadata: None,
         }
     }
 
-    fn current_pid(&self) -> Option<Pid> {
-        self.child
-            .as_ref()
-            .and_then(|child| child.id())
-            .map(|pid| Pid::from_raw(pid as i32))
-    }
-
-    fn build_command(&self) -> Command {
-        let mut cmd = Command::new(&self.settings.binary);
-        cmd.kill_on_drop(true);
-        cmd.stdin(std::process::Stdio::null());
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::piped());
-
-        cmd.arg("-w").arg(&self.settings.target);
-        cmd.arg("-c").arg("mp4");
-        cmd.arg("-f").arg(self.settings.fps.to_string());
-        cmd.arg("-bm").arg("cbr");
-        cmd.arg("-q").arg(self.settings.bitrate.to_string());
-        cmd.arg("-r").arg(self.settings.buffer_seconds.to_string());
-        cmd.arg("-o").arg(self.settings.output_dir.to_string_lossy().to_string());
-        cmd.arg("-ro").arg(self.settings.output_dir.to_string_lossy().to_string());
-        cmd.arg("-replay-storage").arg(self.settings.replay_storage.as_str());
-        cmd.arg("-v").arg("no");
-
-        if self.settings.restore_portal_session {
-            cmd.arg("-restore-portal-session").arg("yes");
+    fn refresh_state(&mut self) -> Result<()> {
+        if let RecorderState::Exited(status) = self.backend.status()? {
+            self.running = false;
+            self.record_failure(status);
         }
+        Ok(())
+    }
 
-        for track in &self.settings.audio_tracks {
-            if !track.trim().is_empty() {
-                cmd.arg("-a").arg(track);
-            }
+    /// Schedules a restart with exponential backoff, or gives up once the
+    /// recorder has crashed quickly too many times in a row.
+    fn record_failure(&mut self, reason: String) {
+        let sup = &mut self.supervisor;
+        eprintln!("[CLIPS_APP] Replay recorder exited: {}", reason);
+        let reason = if let Some(started) = self.recording_started.take() {
+            self.interrupted_recording = Some(started);
+            format!("{reason}, recording interrupted")
+        } else {
+            reason
+        };
+        sup.last_exit = Some(reason.clone());
+        if !sup.wanted {
+            self.last_message = Some(format!("Replay recorder exited: {}", reason));
+            return;
         }
 
-        if let Ok(val) = std::env::var("WAYLAND_DISPLAY") {
-            cmd.env("WAYLAND_DISPLAY", val);
-        }
-        if let Ok(val) = std::env::var("DISPLAY") {
-            cmd.env("DISPLAY", val);
+        let healthy = sup
+            .started_at
+            .is_some_and(|started| started.elapsed() >= HEALTHY_RUN);
+        if healthy {
+            sup.rapid_failures = 0;
         }
-        if let Ok(val) = std::env::var("XDG_RUNTIME_DIR") {
-            cmd.env("XDG_RUNTIME_DIR", val);
+        sup.rapid_failures += 1;
+        sup.started_at = None;
+
+        if sup.rapid_failures > self.settings.max_restarts {
+            sup.wanted = false;
+            sup.next_restart = None;
+            self.last_message = Some(format!(
+                "Replay recorder failed {} times in a row, giving up: {}",
+                sup.rapid_failures, reason
+            ));
+            return;
         }
 
-        cmd
+        let delay = RESTART_BACKOFF_BASE
+            .saturating_mul(1 << (sup.rapid_failures - 1).min(16))
+            .min(RESTART_BACKOFF_MAX);
+        sup.next_restart = Some(Instant::now() + delay);
+        self.last_message = Some(format!(
+            "Replay recorder exited ({}), restarting in {}s (attempt {}/{})",
+            reason,
+            delay.as_secs(),
+            sup.rapid_failures,
+            self.settings.max_restarts
+        ));
     }
 
-    fn take_child_streams(child: &mut Child) {
-        if let Some(stdout) = child.stdout.take() {
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(stdout).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    eprintln!("[GPU-SR][stdout] {}", line);
-                }
-            });
+    /// Restarts the recorder if it died and its backoff has elapsed. Returns
+    /// true when anything changed so the caller can refresh the status view.
+    pub async fn supervise(&mut self) -> Result<bool> {
+        let was_running = self.running;
+        self.refresh_state()?;
+        self.recover_interrupted_recording().await;
+        let due = !self.running
+            && self.supervisor.wanted
+            && self
+                .supervisor
+                .next_restart
+                .is_some_and(|at| Instant::now() >= at);
+        if !due {
+            return Ok(was_running != self.running);
         }
 
-        if let Some(stderr) = child.stderr.take() {
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(stderr).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    eprintln!("[GPU-SR][stderr] {}", line);
-                }
-            });
-        }
-    }
-
-    fn refresh_child(&mut self) -> Result<()> {
-        if let Some(child) = self.child.as_mut() {
-            if let Some(status) = child.try_wait()? {
-                self.child = None;
-                self.last_message = Some(format!("Replay recorder exited: {:?}", status));
+        self.supervisor.next_restart = None;
+        self.supervisor.restart_count += 1;
+        match self.spawn_recorder() {
+            Ok(()) => {
+                self.last_message = Some(format!(
+                    "Replay recorder restarted (restart #{})",
+                    self.supervisor.restart_count
+                ));
             }
+            Err(err) => self.record_failure(format!("{err:#}")),
         }
-        Ok(())
+        Ok(true)
     }
 
     pub async fn ensure_running(&mut self) -> Result<()> {
-        self.refresh_child()?;
-        if self.child.is_some() {
+        self.refresh_state()?;
+        self.recover_interrupted_recording().await;
+        if self.running {
             return Ok(());
         }
 
+        // An explicit start resets the supervisor's patience
+        self.supervisor.wanted = true;
+        self.supervisor.rapid_failures = 0;
+        self.supervisor.next_restart = None;
+        self.spawn_recorder()?;
+        self.last_message = Some("Replay recorder started".to_string());
+        Ok(())
+    }
+
+    fn spawn_recorder(&mut self) -> Result<()> {
         if !self.settings.output_dir.exists() {
             std::fs::create_dir_all(&self.settings.output_dir)
                 .with_context(|| format!(
@@ -185,29 +351,36 @@ impl ReplayController {
         settings.sanitize();
         self.settings = settings;
 
-        let mut cmd = self.build_command();
-        eprintln!("[CLIPS_APP] Spawning gpu-screen-recorder: {:?}", cmd);
-        let mut child = cmd.spawn().context("failed to spawn gpu-screen-recorder")?;
-        Self::take_child_streams(&mut child);
-        self.child = Some(child);
-        self.last_message = Some("Replay recorder started".to_string());
+        self.backend
+            .spawn(&self.settings, self.log.clone())
+            .with_context(|| format!("failed to start {}", self.backend.name()))?;
+        self.running = true;
+        self.supervisor.started_at = Some(Instant::now());
         Ok(())
     }
 
     pub async fn stop(&mut self) -> Result<()> {
-        if self.child.is_none() {
+        self.supervisor.wanted = false;
+        self.supervisor.next_restart = None;
+        self.recover_interrupted_recording().await;
+        if !self.running {
             return Ok(());
         }
 
-        if let Some(pid) = self.current_pid() {
-            if let Ok(signal) = Signal::try_from(libc::SIGINT) {
-                let _ = signal::kill(pid, signal);
+        // Finish a recording properly rather than cutting it off and queue it
+        // for processing like one stopped by hand
+        if self.recording_started.is_some() {
+            match self.stop_recording().await {
+                Ok(path) => {
+                    self.last_saved = Some(path.clone());
+                    self.finished_recordings.push(path);
+                }
+                Err(err) => eprintln!("[CLIPS_APP] Failed to finish recording: {err:#}"),
             }
         }
 
-        if let Some(mut child) = self.child.take() {
-            let _ = child.wait().await;
-        }
+        self.backend.stop().await?;
+        self.running = false;
         self.last_message = Some("Replay recorder stopped".to_string());
         Ok(())
     }
@@ -216,7 +389,7 @@ impl ReplayController {
         let mut new_settings = settings;
         new_settings.sanitize();
         self.settings = new_settings;
-        if self.child.is_some() {
+        if self.running {
             self.stop().await?;
             self.ensure_running().await?;
         }
@@ -224,53 +397,192 @@ impl ReplayController {
     }
 
     pub async fn save_recent(&mut self, duration_secs: Option<u32>) -> Result<Option<PathBuf>> {
-        self.refresh_child()?;
-        let pid = self
-            .current_pid()
-            .context("replay recorder is not running")?;
-
-        let base_rtmin = libc::SIGRTMIN();
-        let raw_signal = match duration_secs {
-            Some(10) => base_rtmin + 1,
-            Some(30) => base_rtmin + 2,
-            Some(60) => base_rtmin + 3,
-            Some(300) => base_rtmin + 4,
-            Some(600) => base_rtmin + 5,
-            Some(1800) => base_rtmin + 6,
-            None => libc::SIGUSR1,
-            Some(other) => {
-                eprintln!("[CLIPS_APP] Unsupported save duration {}s, using full buffer", other);
-                libc::SIGUSR1
-            }
+        self.refresh_state()?;
+        if !self.running {
+            return Err(anyhow!("replay recorder is not running"));
+        }
+
+        let path = self.backend.save_window(duration_secs).await?;
+        // Backends may hand back more than was asked for
+        if let Some(secs) = duration_secs {
+            cut_to_last(&path, secs).await?;
+        }
+        let mut metadata = self.clip_metadata(ClipKind::Replay);
+        metadata.duration_secs = duration_secs;
+        metadata.bookmarks = self.bookmarks_within(u64::from(
+            duration_secs.unwrap_or(self.settings.buffer_seconds),
+        ));
+        finish_clip(&path, &metadata);
+        self.last_saved = Some(path);
+        self.last_message = Some("Replay saved".to_string());
+        Ok(self.last_saved.clone())
+    }
+
+    /// Starts a full-length recording alongside the replay buffer.
+    pub async fn start_recording(&mut self) -> Result<()> {
+        self.refresh_state()?;
+        if !self.running {
+            return Err(anyhow!("replay recorder is not running"));
+        }
+        if self.recording_started.is_some() {
+            return Err(anyhow!("already recording"));
+        }
+        self.backend.start_recording().await?;
+        self.recording_started = Some(Instant::now());
+        self.recording_metadata = Some(self.clip_metadata(ClipKind::Recording));
+        self.last_message = Some("Recording started".to_string());
+        Ok(())
+    }
+
+    pub async fn stop_recording(&mut self) -> Result<PathBuf> {
+        let started = self
+            .recording_started
+            .take()
+            .context("not recording")?;
+        let path = self.backend.stop_recording().await?;
+        self.finish_recording(&path, started);
+        self.last_message = Some(format!(
+            "Recording saved ({})",
+            format_duration(started.elapsed().as_secs())
+        ));
+        Ok(path)
+    }
+
+    fn finish_recording(&mut self, path: &Path, started: Instant) {
+        let mut metadata = self
+            .recording_metadata
+            .take()
+            .unwrap_or_else(|| self.clip_metadata(ClipKind::Recording));
+        metadata.mark_saved();
+        metadata.bookmarks = self.bookmarks_within(started.elapsed().as_secs());
+        finish_clip(path, &metadata);
+    }
+
+    /// Queues what was written of a recording cut short by a recorder crash
+    /// like any other finished recording.
+    async fn recover_interrupted_recording(&mut self) {
+        let Some(started) = self.interrupted_recording.take() else {
+            return;
         };
+        match self.backend.recover_recording().await {
+            Some(path) => {
+                eprintln!("[CLIPS_APP] Recovered interrupted recording {}", path.display());
+                self.finish_recording(&path, started);
+                self.last_saved = Some(path.clone());
+                self.finished_recordings.push(path);
+            }
+            None => {
+                eprintln!("[CLIPS_APP] Interrupted recording left no file behind");
+                self.recording_metadata = None;
+            }
+        }
+    }
+
+    /// Sets the game clips are attributed to and the session they're logged in.
+    pub fn set_game(&mut self, game: Option<DetectedGame>, session_id: Option<String>) {
+        self.game = game;
+        self.session_id = session_id;
+    }
 
-        // Use libc::kill directly for real-time signals since nix::Signal doesn't support them
-        let result = unsafe { libc::kill(pid.as_raw(), raw_signal) };
-        if result != 0 {
-            return Err(anyhow!("failed to signal gpu-screen-recorder: {}", 
-                std::io::Error::last_os_error()));
+    fn clip_metadata(&self, kind: ClipKind) -> ClipMetadata {
+        ClipMetadata::new(
+            kind,
+            &self.settings,
+            self.backend.name(),
+            self.game.as_ref(),
+            self.session_id.as_deref(),
+        )
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Layers `profile` over the current settings until `revert_profile`.
+    pub async fn apply_profile(&mut self, game: &str, profile: &CaptureProfile) -> Result<()> {
+        // Layered over the base rather than any profile already applied, so
+        // switching games restarts the recorder only once
+        let mut base = self.base_settings().clone();
+        base.save_durations = self.settings.save_durations.clone();
+        let mut settings = base.clone();
+        profile.apply(&mut settings);
+        self.active_profile = Some((game.to_string(), base));
+        self.apply_settings(settings).await
+    }
+
+    /// Restores the settings that were in effect before `apply_profile`.
+    pub async fn revert_profile(&mut self) -> Result<()> {
+        if let Some((_, base)) = self.active_profile.take() {
+            // Save durations aren't part of a profile, so keep any edits
+            let mut base = base;
+            base.save_durations = self.settings.save_durations.clone();
+            self.apply_settings(base).await?;
         }
+        Ok(())
+    }
+
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_ref().map(|(game, _)| game.as_str())
+    }
 
-        // Give encoder a moment to flush file before we look for it.
-        sleep(Duration::from_secs(2)).await;
-        self.last_saved = find_latest_file(&self.settings.output_dir)?;
-        if self.last_saved.is_some() {
-            self.last_message = Some("Replay saved".to_string());
+    /// The settings to persist: those underneath any active profile.
+    pub fn base_settings(&self) -> &ReplaySettings {
+        self.active_profile
+            .as_ref()
+            .map(|(_, base)| base)
+            .unwrap_or(&self.settings)
+    }
+
+    /// Applies the storage policy to the output and processed directories,
+    /// never touching `protected` files, and refreshes the usage figure.
+    pub fn enforce_storage_policy(&mut self, protected: &[PathBuf]) -> Result<()> {
+        let report = storage::prune(&self.settings.output_dir, &self.settings.storage, protected)?;
+        if !report.removed.is_empty() {
+            self.last_message = Some(format!(
+                "Pruned {} old clip file(s), freed {} MiB",
+                report.removed.len(),
+                report.freed_bytes >> 20
+            ));
         }
-        Ok(self.last_saved.clone())
+        self.disk_usage = Some(report.usage);
+        Ok(())
+    }
+
+    /// Takes the oldest recording waiting to be processed, e.g. one finished
+    /// by a settings change, a profile switch or the game exiting.
+    pub fn take_finished_recording(&mut self) -> Option<PathBuf> {
+        (!self.finished_recordings.is_empty()).then(|| self.finished_recordings.remove(0))
+    }
+
+    /// Holds a finished recording until `take_finished_recording`.
+    pub fn queue_finished_recording(&mut self, path: PathBuf) {
+        self.finished_recordings.push(path);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording_started.is_some()
     }
 
     pub fn status(&mut self) -> Result<ReplayStatus> {
-        self.refresh_child()?;
+        self.refresh_state()?;
         Ok(ReplayStatus {
-            running: self.child.is_some(),
+            running: self.running,
             buffer_seconds: self.settings.buffer_seconds,
             bitrate: self.settings.bitrate,
             fps: self.settings.fps,
             target: self.settings.target.clone(),
             audio_tracks: self.settings.audio_tracks.clone(),
+            save_durations: self.settings.save_durations.clone(),
             last_saved: self.last_saved.clone(),
             message: self.last_message.clone(),
+            restart_count: self.supervisor.restart_count,
+            last_exit: self.supervisor.last_exit.clone(),
+            recent_stderr: self.log.lines(),
+            recording_secs: self
+                .recording_started
+                .map(|started| started.elapsed().as_secs()),
+            disk_usage: self.disk_usage,
+            active_profile: self.active_profile().map(str::to_string),
         })
     }
 
@@ -292,29 +604,384 @@ impl ReplayController {
     pub fn clear_last_saved(&mut self) {
         self.last_saved = None;
     }
-}
 
-fn find_latest_file(dir: &Path) -> Result<Option<PathBuf>> {
-    let mut newest: Option<(SystemTime, PathBuf)> = None;
-    for entry in std::fs::read_dir(dir)
-        .with_context(|| format!("reading replay output directory {:?}", dir))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
-        }
-        let metadata = entry
-            .metadata()
-            .with_context(|| format!("metadata for {:?}", path))?;
-        if let Ok(modified) = metadata.modified() {
-            match newest {
-                Some((current_time, _)) if current_time >= modified => {}
-                _ => {
-                    newest = Some((modified, path));
-                }
+    /// Records the current wall-clock time as a moment of interest. It's
+    /// logged in the game session and attached to clips that cover it.
+    pub fn add_bookmark(&mut self) -> SystemTime {
+        let now = SystemTime::now();
+        // Only bookmarks a future save could still cover are kept
+        let keep = self.recording_started.map_or(0, |started| started.elapsed().as_secs())
+            .max(u64::from(self.settings.buffer_seconds));
+        self.bookmarks.retain(|at| {
+            now.duration_since(*at).map_or(true, |age| age.as_secs() <= keep)
+        });
+        self.bookmarks.push(now);
+
+        if let Some(session_id) = self.session_id.as_deref() {
+            let result = SessionLog::load().and_then(|mut log| {
+                log.add_bookmark(session_id, now);
+                log.save()
+            });
+            if let Err(err) = result {
+                eprintln!("[CLIPS_APP] Failed to log bookmark in session {session_id}: {err:#}");
             }
         }
+        self.last_message = Some(format!("Bookmarked moment ({} in the buffer)", self.bookmarks.len()));
+        now
+    }
+
+    /// Unix timestamps of the bookmarks in the last `secs` seconds.
+    fn bookmarks_within(&self, secs: u64) -> Vec<u64> {
+        let now = SystemTime::now();
+        self.bookmarks
+            .iter()
+            .filter(|at| now.duration_since(**at).map_or(true, |age| age.as_secs() <= secs))
+            .filter_map(|at| at.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs())
+            .collect()
+    }
+
+    pub fn bookmarks(&self) -> &[SystemTime] {
+        &self.bookmarks
+    }
+}
+
+/// Writes the clip's sidecar and logs it in its game session.
+fn finish_clip(path: &Path, metadata: &ClipMetadata) {
+    if let Err(err) = metadata.write(path) {
+        eprintln!("[CLIPS_APP] Failed to write clip metadata: {err:#}");
+    }
+
+    let Some(session_id) = metadata.session_id.as_deref() else {
+        return;
+    };
+    let result = SessionLog::load().and_then(|mut log| {
+        log.add_clip(session_id, path);
+        log.save()
+    });
+    if let Err(err) = result {
+        eprintln!("[CLIPS_APP] Failed to log clip in session {session_id}: {err:#}");
+    }
+}
+
+/// Keeps only the last `secs` seconds of `path`, replacing it in place.
+async fn cut_to_last(path: &Path, secs: u32) -> Result<()> {
+    let duration = ffmpeg::probe_duration(path).await?;
+    let start = duration - secs as f64;
+    // Half a second of slack avoids re-muxing a clip that is already short enough
+    if start <= 0.5 {
+        return Ok(());
+    }
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("replay");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+    let cut_path = path.with_file_name(format!("{stem}_cut.{ext}"));
+    eprintln!(
+        "[CLIPS_APP] Cutting {} to last {}s ({:.1}s..{:.1}s)",
+        path.display(),
+        secs,
+        start,
+        duration
+    );
+    ffmpeg::trim_video(path, &cut_path, start, duration, |_| {}).await?;
+    std::fs::rename(&cut_path, path)
+        .with_context(|| format!("replacing {:?} with cut clip", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::testing;
+    use crate::recorder::{ScriptedBackend, ScriptedRun};
+
+    fn settings(output_dir: PathBuf) -> ReplaySettings {
+        let mut settings = ReplaySettings {
+            target: "screen".to_string(),
+            buffer_seconds: 60,
+            bitrate: 0,
+            fps: 0,
+            audio_tracks: Vec::new(),
+            track_map: TrackMap::default(),
+            restore_portal_session: false,
+            replay_storage: ReplayStorage::Ram,
+            output_dir,
+            save_durations: Vec::new(),
+            save_timeout_secs: 0,
+            max_restarts: 2,
+            storage: StoragePolicy::default(),
+        };
+        settings.sanitize();
+        settings
+    }
+
+    fn crash() -> ScriptedRun {
+        ScriptedRun::ExitAfter { polls: 0, status: "exit status: 1".to_string() }
+    }
+
+    fn spawns(calls: &std::sync::Mutex<Vec<String>>) -> usize {
+        calls.lock().unwrap().iter().filter(|call| *call == "spawn").count()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn supervise_restarts_with_backoff() {
+        let backend = ScriptedBackend::new().with_run(crash()).with_run(crash());
+        let calls = backend.calls();
+        let mut controller =
+            ReplayController::new(settings(testing::temp_dir("backoff")), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+
+        assert!(controller.supervise().await.unwrap());
+        let status = controller.status().unwrap();
+        assert!(!status.running);
+        assert_eq!(status.last_exit.as_deref(), Some("exit status: 1"));
+        assert!(status.message.unwrap().contains("restarting in 1s (attempt 1/2)"));
+
+        // Nothing happens until the backoff has passed
+        assert!(!controller.supervise().await.unwrap());
+        tokio::time::advance(Duration::from_millis(999)).await;
+        assert!(!controller.supervise().await.unwrap());
+        assert_eq!(spawns(&calls), 1);
+
+        tokio::time::advance(Duration::from_millis(1)).await;
+        assert!(controller.supervise().await.unwrap());
+        assert_eq!(spawns(&calls), 2);
+
+        // The second quick crash doubles the delay
+        assert!(controller.supervise().await.unwrap());
+        let status = controller.status().unwrap();
+        assert_eq!(status.restart_count, 1);
+        assert!(status.message.unwrap().contains("restarting in 2s (attempt 2/2)"));
+        tokio::time::advance(Duration::from_secs(1)).await;
+        controller.supervise().await.unwrap();
+        assert_eq!(spawns(&calls), 2);
+        tokio::time::advance(Duration::from_secs(1)).await;
+        controller.supervise().await.unwrap();
+        assert_eq!(spawns(&calls), 3);
+
+        let status = controller.status().unwrap();
+        assert!(status.running);
+        assert_eq!(status.restart_count, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn healthy_run_resets_backoff() {
+        let backend = ScriptedBackend::new()
+            .with_run(crash())
+            .with_run(ScriptedRun::ExitAfter { polls: 1, status: "killed".to_string() });
+        let calls = backend.calls();
+        let mut controller =
+            ReplayController::new(settings(testing::temp_dir("healthy")), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+
+        controller.supervise().await.unwrap();
+        tokio::time::advance(RESTART_BACKOFF_BASE).await;
+        controller.supervise().await.unwrap();
+        assert_eq!(spawns(&calls), 2);
+
+        // Long enough to count as healthy, so the next delay starts over
+        controller.supervise().await.unwrap();
+        tokio::time::advance(HEALTHY_RUN).await;
+        assert!(controller.supervise().await.unwrap());
+        assert!(controller
+            .status()
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("restarting in 1s (attempt 1/2)"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn supervise_gives_up_after_max_restarts() {
+        let backend = ScriptedBackend::new()
+            .with_run(crash())
+            .with_run(crash())
+            .with_run(crash());
+        let calls = backend.calls();
+        let mut controller =
+            ReplayController::new(settings(testing::temp_dir("give-up")), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+
+        for _ in 0..2 {
+            controller.supervise().await.unwrap();
+            tokio::time::advance(RESTART_BACKOFF_MAX).await;
+            controller.supervise().await.unwrap();
+        }
+        assert_eq!(spawns(&calls), 3);
+
+        // The third quick crash is one more than max_restarts allows
+        assert!(controller.supervise().await.unwrap());
+        assert!(controller.status().unwrap().message.unwrap().contains("giving up"));
+        tokio::time::advance(RESTART_BACKOFF_MAX * 2).await;
+        assert!(!controller.supervise().await.unwrap());
+        assert_eq!(spawns(&calls), 3);
+        assert!(!controller.status().unwrap().running);
+
+        // Starting by hand tries again
+        controller.ensure_running().await.unwrap();
+        assert_eq!(spawns(&calls), 4);
+        assert!(controller.status().unwrap().running);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_restart_counts_as_a_crash() {
+        let backend = ScriptedBackend::new()
+            .with_run(crash())
+            .with_run(ScriptedRun::FailSpawn("portal closed".to_string()));
+        let calls = backend.calls();
+        let mut controller =
+            ReplayController::new(settings(testing::temp_dir("fail-spawn")), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+
+        controller.supervise().await.unwrap();
+        tokio::time::advance(RESTART_BACKOFF_BASE).await;
+        assert!(controller.supervise().await.unwrap());
+        let status = controller.status().unwrap();
+        assert!(!status.running);
+        assert!(status.last_exit.unwrap().contains("portal closed"));
+        assert!(status.message.unwrap().contains("restarting in 2s (attempt 2/2)"));
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        controller.supervise().await.unwrap();
+        assert_eq!(spawns(&calls), 3);
+        assert!(controller.status().unwrap().running);
+    }
+
+    #[tokio::test]
+    async fn settings_change_queues_the_interrupted_recording() {
+        let dir = testing::temp_dir("finished-recording");
+        let recording = dir.join("recording.mp4");
+        let backend = ScriptedBackend::new().with_save(Ok(recording.clone()));
+        let calls = backend.calls();
+        let mut controller = ReplayController::new(settings(dir.clone()), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+        controller.start_recording().await.unwrap();
+        assert_eq!(controller.take_finished_recording(), None);
+
+        let mut changed = controller.settings().clone();
+        changed.fps = 30;
+        controller.apply_settings(changed).await.unwrap();
+        assert_eq!(controller.take_finished_recording(), Some(recording));
+        assert_eq!(controller.take_finished_recording(), None);
+        assert!(!controller.is_recording());
+        assert!(controller.status().unwrap().running);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["spawn", "start recording", "stop recording", "stop", "spawn"]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crash_queues_the_interrupted_recording() {
+        let dir = testing::temp_dir("crashed-recording");
+        let recording = dir.join("recording.mp4");
+        let backend = ScriptedBackend::new()
+            .with_run(ScriptedRun::ExitAfter { polls: 1, status: "exit status: 1".to_string() })
+            .with_save(Ok(recording.clone()));
+        let calls = backend.calls();
+        let mut controller = ReplayController::new(settings(dir.clone()), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+        controller.start_recording().await.unwrap();
+
+        assert!(controller.supervise().await.unwrap());
+        assert!(!controller.is_recording());
+        assert_eq!(controller.take_finished_recording(), Some(recording));
+
+        // The restarted recorder takes a new recording
+        tokio::time::advance(Duration::from_secs(1)).await;
+        controller.supervise().await.unwrap();
+        controller.start_recording().await.unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["spawn", "start recording", "recover recording", "spawn", "start recording"]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn switching_profiles_restarts_once() {
+        let backend = ScriptedBackend::new();
+        let calls = backend.calls();
+        let mut controller =
+            ReplayController::new(settings(testing::temp_dir("profiles")), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+
+        let profile = |fps| CaptureProfile { fps: Some(fps), ..CaptureProfile::default() };
+        controller.apply_profile("First", &profile(30)).await.unwrap();
+        controller.apply_profile("Second", &profile(120)).await.unwrap();
+        assert_eq!(controller.settings().fps, 120);
+        assert_eq!(controller.base_settings().fps, 60);
+        assert_eq!(controller.active_profile(), Some("Second"));
+        assert_eq!(*calls.lock().unwrap(), ["spawn", "stop", "spawn", "stop", "spawn"]);
+
+        controller.revert_profile().await.unwrap();
+        assert_eq!(controller.settings().fps, 60);
+        assert_eq!(controller.active_profile(), None);
+    }
+
+    #[tokio::test]
+    async fn save_recent_needs_a_running_recorder() {
+        let backend = ScriptedBackend::new().with_save(Ok(PathBuf::from("/nonexistent.mp4")));
+        let calls = backend.calls();
+        let mut controller =
+            ReplayController::new(settings(testing::temp_dir("not-running")), Box::new(backend));
+
+        assert!(controller.save_recent(Some(30)).await.is_err());
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs ffmpeg"]
+    async fn save_recent_cuts_to_the_requested_length() {
+        let dir = testing::temp_dir("save-recent");
+        let long = dir.join("long.mp4");
+        let short = dir.join("short.mp4");
+        testing::write_clip(&long, 10, testing::FPS).await.unwrap();
+        testing::write_clip(&short, 3, testing::FPS).await.unwrap();
+        let short_size = std::fs::metadata(&short).unwrap().len();
+
+        let backend = ScriptedBackend::new()
+            .with_save(Ok(long.clone()))
+            .with_save(Ok(short.clone()));
+        let calls = backend.calls();
+        let mut controller = ReplayController::new(settings(dir.clone()), Box::new(backend));
+        controller.ensure_running().await.unwrap();
+
+        let saved = controller.save_recent(Some(4)).await.unwrap();
+        assert_eq!(saved.as_deref(), Some(long.as_path()));
+        let duration = ffmpeg::probe_duration(&long).await.unwrap();
+        assert!((duration - 4.0).abs() < 0.2, "cut clip is {duration}s");
+        assert!(!dir.join("long_cut.mp4").exists());
+        let metadata = ClipMetadata::read(&long).unwrap().unwrap();
+        assert_eq!(metadata.duration_secs, Some(4));
+
+        // A clip already within the requested length is left alone
+        controller.save_recent(Some(30)).await.unwrap();
+        assert_eq!(std::fs::metadata(&short).unwrap().len(), short_size);
+        assert_eq!(*calls.lock().unwrap(), ["spawn", "save 4", "save 30"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("45").unwrap(), 45);
+        assert_eq!(parse_duration(" 45s ").unwrap(), 45);
+        assert_eq!(parse_duration("2M").unwrap(), 120);
+        assert_eq!(parse_duration("1m30s").unwrap(), 90);
+        assert_eq!(parse_duration("1h").unwrap(), 3600);
+        assert_eq!(parse_duration("7d").unwrap(), 7 * 86_400);
+
+        let error = |value: &str| format!("{:#}", parse_duration(value).unwrap_err());
+        assert!(error("").contains("empty duration"));
+        assert!(error("0").contains("longer than zero"));
+        assert!(error("0m0s").contains("longer than zero"));
+        assert!(error("50000d").contains("too long"));
+        assert!(error("1m30").contains("missing unit"));
+        assert!(error("2x").contains("invalid duration"));
+        assert!(error("m").contains("invalid duration"));
     }
-    Ok(newest.map(|(_, path)| path))
 }
@@ -3,7 +3,14 @@ use std::path::PathBuf;
 use anyhow::{anyhow, ensure, Context, Result};
 use clap::{ArgAction, Parser};
 
-use crate::capture::ReplayStorage;
+use crate::capture::{self, ReplayStorage};
+use crate::ducking::DuckingConfig;
+use crate::hotkeys::{HotkeyAction, HotkeyBinding};
+use crate::loudness::{self, LoudnessConfig};
+use crate::recorder::RecorderKind;
+use crate::smartcut::TrimMode;
+use crate::storage::{self, StoragePolicy};
+use crate::voice::VoiceConfig;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "All-in-one clips processing and replay control")]
@@ -24,7 +31,7 @@ pub struct Cli {
     pub secrets_path: Option<PathBuf>,
 
     #[arg(long = "overlay-bin", value_name = "CMD")]
-    pub overlay_bin: PathBuf,
+    pub overlay_bin: Option<PathBuf>,
 
     #[arg(long = "capture-mode", default_value_t = false)]
     pub capture_mode: bool,
@@ -32,6 +39,10 @@ pub struct Cli {
     #[arg(long = "gpu-screen-recorder-bin", value_name = "CMD")]
     pub gpu_screen_recorder: Option<PathBuf>,
 
+    /// Replay buffer backend: gpu-screen-recorder or ffmpeg
+    #[arg(long = "capture-backend", default_value = "gpu-screen-recorder")]
+    pub capture_backend: String,
+
     #[arg(long = "capture-target", default_value = "portal")]
     pub capture_target: String,
 
@@ -56,8 +67,84 @@ pub struct Cli {
     #[arg(long = "capture-hotkey", default_value = "Alt+X")]
     pub capture_hotkey: String,
 
+    /// Hotkey that starts/stops a full-length recording
+    #[arg(long = "capture-record-hotkey", value_name = "CHORD")]
+    pub capture_record_hotkey: Option<String>,
+
+    /// Extra hotkey binding in the form CHORD=ACTION (e.g. "Alt+F9=save-30")
+    #[arg(long = "capture-bind", value_name = "CHORD=ACTION", action = ArgAction::Append)]
+    pub capture_bindings: Vec<String>,
+
+    /// Save buttons offered in the capture view (e.g. "30s,1m,2m")
+    #[arg(long = "capture-save-durations", value_name = "DURATION", value_delimiter = ',')]
+    pub capture_save_durations: Vec<String>,
+
+    /// Seconds to wait for gpu-screen-recorder to finish writing a saved replay
+    #[arg(long = "capture-save-timeout", default_value_t = 30)]
+    pub capture_save_timeout: u32,
+
+    /// Quick consecutive recorder crashes to retry before giving up (0 disables restarts)
+    #[arg(long = "capture-max-restarts", default_value_t = 5)]
+    pub capture_max_restarts: u32,
+
+    /// Space replays and raw originals may use before the oldest are pruned (e.g. "50G")
+    #[arg(long = "capture-disk-quota", value_name = "SIZE")]
+    pub capture_disk_quota: Option<String>,
+
+    /// Prune unprocessed replays and raw originals older than this (e.g. "14d")
+    #[arg(long = "capture-max-age", value_name = "DURATION")]
+    pub capture_max_age: Option<String>,
+
+    /// Integrated loudness the optional normalisation pass aims for
+    #[arg(long = "loudness-target", value_name = "LUFS", default_value_t = loudness::DEFAULT_TARGET_LUFS, allow_negative_numbers = true)]
+    pub loudness_target: f64,
+
+    /// Peak level in dBFS the limiter at the end of every mix holds audio under
+    #[arg(long = "limiter-ceiling", value_name = "DB", default_value_t = loudness::DEFAULT_LIMITER_DB, allow_negative_numbers = true)]
+    pub limiter_ceiling: f64,
+
+    /// Tick loudness normalisation in the picker by default
+    #[arg(long = "normalize-loudness", default_value_t = false)]
+    pub normalize_loudness: bool,
+
+    /// Voice cleanup preset the picker starts on: off, light, standard, strong or rnnoise
+    #[arg(long = "voice-preset", value_name = "PRESET", default_value = "light")]
+    pub voice_preset: String,
+
+    /// RNNoise model file for the rnnoise voice preset (see arnndn in the ffmpeg docs)
+    #[arg(long = "rnnoise-model", value_name = "FILE")]
+    pub rnnoise_model: Option<PathBuf>,
+
+    /// Tick ducking of game audio under voice and chat in the picker by default
+    #[arg(long = "duck-audio", default_value_t = false)]
+    pub duck_audio: bool,
+
+    /// Voice/chat level in dB above which game audio is ducked
+    #[arg(long = "duck-threshold", value_name = "DB", default_value_t = -30.0, allow_negative_numbers = true)]
+    pub duck_threshold: f64,
+
+    /// How hard game audio is compressed while ducked
+    #[arg(long = "duck-ratio", default_value_t = 6.0)]
+    pub duck_ratio: f64,
+
+    /// Milliseconds for game audio to recover after speech stops
+    #[arg(long = "duck-release", value_name = "MS", default_value_t = 400.0)]
+    pub duck_release: f64,
+
+    /// How stream-copied trims cut: smart (exact, re-encodes the partial GOPs at each end) or keyframe
+    #[arg(long = "trim-mode", value_name = "MODE", default_value = "smart")]
+    pub trim_mode: String,
+
     #[arg(long = "capture-auto-start", default_value_t = false)]
     pub capture_auto_start: bool,
+
+    /// Print every key name accepted in hotkey chords and exit
+    #[arg(long = "list-keys", default_value_t = false)]
+    pub list_keys: bool,
+
+    /// Print the most recent game sessions and the clips saved in each, then exit
+    #[arg(long = "list-sessions", value_name = "COUNT", num_args = 0..=1, default_missing_value = "10")]
+    pub list_sessions: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -68,24 +155,37 @@ pub struct AppConfig {
     pub youtube_uploader: PathBuf,
     pub secrets_path: PathBuf,
     pub overlay_bin: PathBuf,
+    pub loudness: LoudnessConfig,
+    pub voice: VoiceConfig,
+    pub ducking: DuckingConfig,
+    pub trim_mode: TrimMode,
 }
 
 #[derive(Debug, Clone)]
 pub struct CaptureConfig {
     pub overlay_bin: PathBuf,
-    pub gpu_screen_recorder: PathBuf,
+    pub recorder: RecorderKind,
     pub output_dir: PathBuf,
     pub processed_dir: PathBuf,
     pub youtube_uploader: PathBuf,
     pub secrets_path: PathBuf,
+    pub loudness: LoudnessConfig,
+    pub voice: VoiceConfig,
+    pub ducking: DuckingConfig,
+    pub trim_mode: TrimMode,
     pub target: String,
     pub buffer_seconds: u32,
     pub bitrate: u32,
     pub fps: u32,
     pub audio_tracks: Vec<String>,
+    pub save_durations: Vec<u32>,
+    pub save_timeout_secs: u32,
+    pub max_restarts: u32,
+    pub storage: StoragePolicy,
     pub restore_portal_session: bool,
     pub replay_storage: ReplayStorage,
     pub hotkey: String,
+    pub hotkey_bindings: Vec<HotkeyBinding>,
     pub auto_start: bool,
 }
 
@@ -93,11 +193,17 @@ pub struct CaptureConfig {
 pub enum AppMode {
     Process(AppConfig),
     Capture(CaptureConfig),
+    ListKeys,
+    ListSessions(usize),
 }
 
 impl Cli {
     pub fn into_mode(self) -> Result<AppMode> {
-        if self.capture_mode {
+        if self.list_keys {
+            Ok(AppMode::ListKeys)
+        } else if let Some(count) = self.list_sessions {
+            Ok(AppMode::ListSessions(count))
+        } else if self.capture_mode {
             self.into_capture_mode()
         } else {
             self.into_process_mode()
@@ -123,9 +229,23 @@ impl Cli {
 
         let overlay_bin = self
             .overlay_bin
+            .ok_or_else(|| anyhow!("--overlay-bin must be provided"))?
             .canonicalize()
             .context("overlay binary missing")?;
 
+        let loudness =
+            LoudnessConfig::new(self.loudness_target, self.limiter_ceiling, self.normalize_loudness)
+                .context("invalid loudness settings")?;
+        let voice = voice_config(&self.voice_preset, self.rnnoise_model.clone())?;
+        let ducking = DuckingConfig::new(
+            self.duck_threshold,
+            self.duck_ratio,
+            self.duck_release,
+            self.duck_audio,
+        )
+        .context("invalid ducking settings")?;
+        let trim_mode = self.trim_mode.parse().context("invalid --trim-mode")?;
+
         let config = AppConfig::new(
             source,
             unprocessed_dir,
@@ -133,15 +253,16 @@ impl Cli {
             youtube_uploader,
             secrets_path,
             overlay_bin,
+            loudness,
+            voice,
+            ducking,
+            trim_mode,
         )?;
 
         Ok(AppMode::Process(config))
     }
 
     fn into_capture_mode(self) -> Result<AppMode> {
-        let gpu_screen_recorder = self
-            .gpu_screen_recorder
-            .ok_or_else(|| anyhow!("--gpu-screen-recorder-bin must be provided in capture mode"))?;
         let output_dir = self
             .unprocessed_dir
             .ok_or_else(|| anyhow!("--unprocessed-dir must be provided in capture mode"))?;
@@ -155,16 +276,29 @@ impl Cli {
             .secrets_path
             .ok_or_else(|| anyhow!("--secrets-path must be provided in capture mode"))?;
 
-        let gpu_screen_recorder = gpu_screen_recorder
-            .canonicalize()
-            .context("gpu-screen-recorder binary missing")?;
-        ensure!(
-            gpu_screen_recorder.is_file(),
-            "gpu-screen-recorder must be an executable file (got {:?})",
-            gpu_screen_recorder
-        );
+        let recorder = match self.capture_backend.to_ascii_lowercase().as_str() {
+            "gpu-screen-recorder" | "gsr" => {
+                let gpu_screen_recorder = self
+                    .gpu_screen_recorder
+                    .ok_or_else(|| anyhow!("--gpu-screen-recorder-bin must be provided in capture mode"))?
+                    .canonicalize()
+                    .context("gpu-screen-recorder binary missing")?;
+                ensure!(
+                    gpu_screen_recorder.is_file(),
+                    "gpu-screen-recorder must be an executable file (got {:?})",
+                    gpu_screen_recorder
+                );
+                RecorderKind::GpuScreenRecorder(gpu_screen_recorder)
+            }
+            "ffmpeg" => RecorderKind::Ffmpeg,
+            other => anyhow::bail!(
+                "unsupported capture backend '{}', expected 'gpu-screen-recorder' or 'ffmpeg'",
+                other
+            ),
+        };
         let overlay_bin = self
             .overlay_bin
+            .ok_or_else(|| anyhow!("--overlay-bin must be provided"))?
             .canonicalize()
             .context("overlay binary missing")?;
         ensure!(
@@ -190,6 +324,19 @@ impl Cli {
             .canonicalize()
             .context("youtubeuploader secrets file missing")?;
 
+        let loudness =
+            LoudnessConfig::new(self.loudness_target, self.limiter_ceiling, self.normalize_loudness)
+                .context("invalid loudness settings")?;
+        let voice = voice_config(&self.voice_preset, self.rnnoise_model.clone())?;
+        let ducking = DuckingConfig::new(
+            self.duck_threshold,
+            self.duck_ratio,
+            self.duck_release,
+            self.duck_audio,
+        )
+        .context("invalid ducking settings")?;
+        let trim_mode = self.trim_mode.parse().context("invalid --trim-mode")?;
+
         let replay_storage = match self.capture_storage.to_ascii_lowercase().as_str() {
             "ram" => ReplayStorage::Ram,
             "disk" => ReplayStorage::Disk,
@@ -208,27 +355,98 @@ impl Cli {
             ];
         }
 
+        let mut save_durations = self
+            .capture_save_durations
+            .iter()
+            .map(|value| {
+                capture::parse_duration(value)
+                    .with_context(|| format!("invalid --capture-save-durations entry '{}'", value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if save_durations.is_empty() {
+            save_durations = capture::DEFAULT_SAVE_DURATIONS.to_vec();
+        }
+
+        let storage = StoragePolicy {
+            processed_dir: Some(processed_dir.clone()),
+            max_bytes: self
+                .capture_disk_quota
+                .as_deref()
+                .map(|value| {
+                    storage::parse_size(value)
+                        .with_context(|| format!("invalid --capture-disk-quota '{}'", value))
+                })
+                .transpose()?,
+            max_age: self
+                .capture_max_age
+                .as_deref()
+                .map(|value| {
+                    capture::parse_duration(value)
+                        .map(|secs| std::time::Duration::from_secs(secs as u64))
+                        .with_context(|| format!("invalid --capture-max-age '{}'", value))
+                })
+                .transpose()?,
+        };
+
+        // The overlay hotkey is always bound; extra bindings dispatch capture actions directly.
+        let mut hotkey_bindings = vec![HotkeyBinding {
+            chord: self
+                .capture_hotkey
+                .parse()
+                .with_context(|| format!("invalid --capture-hotkey '{}'", self.capture_hotkey))?,
+            action: HotkeyAction::ShowOverlay,
+        }];
+        if let Some(chord) = &self.capture_record_hotkey {
+            hotkey_bindings.push(HotkeyBinding {
+                chord: chord
+                    .parse()
+                    .with_context(|| format!("invalid --capture-record-hotkey '{}'", chord))?,
+                action: HotkeyAction::ToggleRecording,
+            });
+        }
+        for binding in &self.capture_bindings {
+            let binding = binding
+                .parse::<HotkeyBinding>()
+                .with_context(|| format!("invalid --capture-bind '{}'", binding))?;
+            hotkey_bindings.push(binding);
+        }
+
         Ok(AppMode::Capture(CaptureConfig {
             overlay_bin,
-            gpu_screen_recorder,
+            recorder,
             output_dir,
             processed_dir,
             youtube_uploader,
             secrets_path,
+            loudness,
+            voice,
+            ducking,
+            trim_mode,
             target: self.capture_target,
             buffer_seconds: self.capture_buffer_seconds,
             bitrate: self.capture_bitrate,
             fps: self.capture_fps,
             audio_tracks,
+            save_durations,
+            save_timeout_secs: self.capture_save_timeout.max(1),
+            max_restarts: self.capture_max_restarts,
+            storage,
             restore_portal_session: self.capture_restore_portal,
             replay_storage,
             hotkey: self.capture_hotkey,
+            hotkey_bindings,
             auto_start: self.capture_auto_start,
         }))
     }
 }
 
+fn voice_config(preset: &str, rnnoise_model: Option<PathBuf>) -> Result<VoiceConfig> {
+    let preset = preset.parse().context("invalid --voice-preset")?;
+    VoiceConfig::new(preset, rnnoise_model)
+}
+
 impl AppConfig {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         source: PathBuf,
         unprocessed_dir: PathBuf,
@@ -236,6 +454,10 @@ impl AppConfig {
         youtube_uploader: PathBuf,
         secrets_path: PathBuf,
         overlay_bin: PathBuf,
+        loudness: LoudnessConfig,
+        voice: VoiceConfig,
+        ducking: DuckingConfig,
+        trim_mode: TrimMode,
     ) -> Result<Self> {
         let source = source.canonicalize().context("source file missing")?;
         let unprocessed_dir = unprocessed_dir
@@ -269,6 +491,10 @@ impl AppConfig {
             youtube_uploader,
             secrets_path,
             overlay_bin,
+            loudness,
+            voice,
+            ducking,
+            trim_mode,
         })
     }
 
@@ -1 +0,0 @@
-pub const CHANNEL_OPTIONS: &[&str] = &["voice", "discord", "game"];
@@ -0,0 +1,47 @@
+use anyhow::{ensure, Result};
+
+const ATTACK_MS: f64 = 20.0;
+
+/// Settings for compressing game audio while voice or chat is active.
+#[derive(Debug, Clone, Copy)]
+pub struct DuckingConfig {
+    /// Speech level that starts ducking, in dBFS.
+    pub threshold_db: f64,
+    pub ratio: f64,
+    pub release_ms: f64,
+    /// Whether the picker starts with ducking ticked.
+    pub enabled_by_default: bool,
+}
+
+impl DuckingConfig {
+    pub fn new(threshold_db: f64, ratio: f64, release_ms: f64, enabled_by_default: bool) -> Result<Self> {
+        // The ranges sidechaincompress accepts
+        ensure!(
+            (-60.0..=0.0).contains(&threshold_db),
+            "ducking threshold must be between -60 and 0 dB (got {threshold_db})"
+        );
+        ensure!(
+            (1.0..=20.0).contains(&ratio),
+            "ducking ratio must be between 1 and 20 (got {ratio})"
+        );
+        ensure!(
+            (10.0..=9000.0).contains(&release_ms),
+            "ducking release must be between 10 and 9000 ms (got {release_ms})"
+        );
+        Ok(Self {
+            threshold_db,
+            ratio,
+            release_ms,
+            enabled_by_default,
+        })
+    }
+
+    /// The compressor for a ducked track; takes the track then the key.
+    pub fn filter(&self) -> String {
+        let threshold = 10f64.powf(self.threshold_db / 20.0);
+        format!(
+            "sidechaincompress=threshold={threshold:.6}:ratio={}:attack={ATTACK_MS}:release={}",
+            self.ratio, self.release_ms
+        )
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Codec name for presets that keep the captured video as is.
+const COPY_CODEC: &str = "copy";
+/// Encoders that support the two-pass size-targeted mode.
+const SIZE_CODECS: [&str; 2] = ["libx264", "libsvtav1"];
+/// Share of a size budget left for the mp4 container.
+const CONTAINER_OVERHEAD: f64 = 0.03;
+const MIN_AUDIO_KBPS: f64 = 48.0;
+pub const MIN_VIDEO_KBPS: u32 = 150;
+
+/// How the trimmed clip's video is encoded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    /// ffmpeg video encoder, e.g. `libx264`, `libx265` or `hevc_nvenc`, or
+    /// `copy` to keep the capture untouched.
+    pub codec: String,
+    /// Constant quality; mutually exclusive with `bitrate` and
+    /// `target_size_mb`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crf: Option<u32>,
+    /// Video bitrate in kbps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u32>,
+    /// Fit the clip in this many megabytes (of 1,000,000 bytes) with a
+    /// two-pass encode; bitrates are derived from the clip's duration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_size_mb: Option<f64>,
+    /// Encoder speed preset, e.g. `slow`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<String>,
+    /// Output height; the width follows the aspect ratio. Never upscales.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fps: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pixel_format: Option<String>,
+    /// Bitrate of the mixed audio in kbps.
+    #[serde(default = "default_audio_bitrate")]
+    pub audio_bitrate: u32,
+}
+
+fn default_audio_bitrate() -> u32 {
+    192
+}
+
+impl ExportPreset {
+    fn original() -> Self {
+        Self {
+            name: "Original".to_string(),
+            codec: COPY_CODEC.to_string(),
+            crf: None,
+            bitrate: None,
+            target_size_mb: None,
+            speed: None,
+            height: None,
+            fps: None,
+            pixel_format: None,
+            audio_bitrate: default_audio_bitrate(),
+        }
+    }
+
+    fn fit(name: &str, codec: &str, target_size_mb: f64, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            codec: codec.to_string(),
+            target_size_mb: Some(target_size_mb),
+            height: Some(height),
+            pixel_format: Some("yuv420p".to_string()),
+            audio_bitrate: 128,
+            ..Self::original()
+        }
+    }
+
+    fn x264(name: &str, crf: u32, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            codec: "libx264".to_string(),
+            crf: Some(crf),
+            speed: Some("slow".to_string()),
+            height: Some(height),
+            fps: Some(60),
+            pixel_format: Some("yuv420p".to_string()),
+            audio_bitrate: 256,
+            ..Self::original()
+        }
+    }
+
+    /// Whether the video is stream-copied rather than re-encoded.
+    pub fn is_copy(&self) -> bool {
+        self.codec == COPY_CODEC
+    }
+
+    pub fn label(&self) -> String {
+        if self.is_copy() {
+            format!("{} (no re-encode)", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Video and audio bitrates in kbps that fit a clip of `duration`
+    /// seconds in the target size.
+    pub fn size_bitrates(&self, duration: f64) -> Result<(u32, u32)> {
+        let target = self
+            .target_size_mb
+            .with_context(|| format!("preset '{}' has no target size", self.name))?;
+        ensure!(duration.is_finite() && duration > 0.0, "clip duration must be positive");
+
+        let total_kbps = target * 8_000.0 * (1.0 - CONTAINER_OVERHEAD) / duration;
+        let audio_kbps = f64::from(self.audio_bitrate).min(total_kbps / 8.0).max(MIN_AUDIO_KBPS);
+        let video_kbps = total_kbps - audio_kbps;
+        ensure!(
+            video_kbps >= f64::from(MIN_VIDEO_KBPS),
+            "a {duration:.0}s clip is too long to fit in {target} MB"
+        );
+        Ok((video_kbps as u32, audio_kbps as u32))
+    }
+
+    /// The video encoder options, after the input and before the output.
+    pub fn video_args(&self) -> Vec<String> {
+        if self.is_copy() {
+            return vec!["-c:v".to_string(), COPY_CODEC.to_string()];
+        }
+
+        let mut args = vec!["-c:v".to_string(), self.codec.clone()];
+        if let Some(speed) = &self.speed {
+            args.extend(["-preset".to_string(), speed.clone()]);
+        }
+        if let Some(crf) = self.crf {
+            args.extend(["-crf".to_string(), crf.to_string()]);
+        }
+        if let Some(bitrate) = self.bitrate {
+            args.extend([
+                "-b:v".to_string(),
+                format!("{bitrate}k"),
+                "-maxrate".to_string(),
+                format!("{bitrate}k"),
+                "-bufsize".to_string(),
+                format!("{}k", bitrate * 2),
+            ]);
+        }
+
+        let mut filters = Vec::new();
+        if let Some(height) = self.height {
+            filters.push(format!("scale=-2:'min({height},ih)':flags=lanczos"));
+        }
+        if let Some(fps) = self.fps {
+            filters.push(format!("fps={fps}"));
+        }
+        if !filters.is_empty() {
+            args.extend(["-vf".to_string(), filters.join(",")]);
+        }
+        if let Some(pixel_format) = &self.pixel_format {
+            args.extend(["-pix_fmt".to_string(), pixel_format.clone()]);
+        }
+        args
+    }
+}
+
+/// Presets from `~/.config/clips-app/export_presets.json`, or a built-in set
+/// without it. A stream-copy "Original" preset is always available.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPresets {
+    /// Preset the picker starts on; "Original" when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    pub presets: Vec<ExportPreset>,
+}
+
+impl Default for ExportPresets {
+    fn default() -> Self {
+        Self {
+            default: None,
+            presets: vec![
+                ExportPreset::original(),
+                ExportPreset::x264("YouTube 1440p60 x264 CRF18", 18, 1440),
+                ExportPreset::x264("YouTube 1080p60 x264 CRF20", 20, 1080),
+                ExportPreset::fit("Fit 10 MB (720p x264)", "libx264", 10.0, 720),
+                ExportPreset::fit("Fit 25 MB (1080p x264)", "libx264", 25.0, 1080),
+                ExportPreset::fit("Fit 50 MB (1080p AV1)", "libsvtav1", 50.0, 1080),
+                ExportPreset {
+                    name: "Archive HEVC".to_string(),
+                    codec: "libx265".to_string(),
+                    crf: Some(22),
+                    speed: Some("medium".to_string()),
+                    pixel_format: Some("yuv420p10le".to_string()),
+                    ..ExportPreset::original()
+                },
+            ],
+        }
+    }
+}
+
+impl ExportPresets {
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context("failed to read export presets file")?;
+        let mut presets: ExportPresets = serde_json::from_str(&contents)
+            .context("failed to parse export presets file")?;
+        presets
+            .validate()
+            .with_context(|| format!("invalid export presets in {}", path.display()))?;
+        if !presets.presets.iter().any(ExportPreset::is_copy) {
+            presets.presets.insert(0, ExportPreset::original());
+        }
+        Ok(presets)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for preset in &self.presets {
+            if preset.name.trim().is_empty() {
+                bail!("a preset has no name");
+            }
+            if !names.insert(preset.name.to_lowercase()) {
+                bail!("preset name '{}' is used more than once", preset.name);
+            }
+            if preset.codec.trim().is_empty() {
+                bail!("preset '{}' has no codec", preset.name);
+            }
+            if preset.crf.is_some() && preset.bitrate.is_some() {
+                bail!("preset '{}' sets both crf and bitrate", preset.name);
+            }
+            if let Some(target) = preset.target_size_mb {
+                if !target.is_finite() || target <= 0.0 {
+                    bail!("preset '{}' has an invalid target size {}", preset.name, target);
+                }
+                if !SIZE_CODECS.contains(&preset.codec.as_str()) {
+                    bail!(
+                        "preset '{}' targets a size, which needs one of: {}",
+                        preset.name,
+                        SIZE_CODECS.join(", ")
+                    );
+                }
+                if preset.crf.is_some() || preset.bitrate.is_some() {
+                    bail!("preset '{}' sets a target size with crf or bitrate", preset.name);
+                }
+            }
+            if preset.audio_bitrate == 0 {
+                bail!("preset '{}' has no audio bitrate", preset.name);
+            }
+        }
+        if let Some(default) = &self.default {
+            if self.get(default).is_none() {
+                bail!("default preset '{}' is not defined", default);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ExportPreset> {
+        self.presets
+            .iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn default_preset(&self) -> &ExportPreset {
+        self.default
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.presets.iter().find(|preset| preset.is_copy()))
+            .unwrap_or(&self.presets[0])
+    }
+
+    fn config_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".config/clips-app/export_presets.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(preset: &ExportPreset) -> String {
+        preset.video_args().join(" ")
+    }
+
+    fn validation_error(edit: impl FnOnce(&mut ExportPresets)) -> String {
+        let mut presets = ExportPresets::default();
+        edit(&mut presets);
+        format!("{:#}", presets.validate().unwrap_err())
+    }
+
+    #[test]
+    fn size_bitrates_fit_the_target() {
+        let preset = ExportPreset::fit("Fit 10 MB", "libx264", 10.0, 720);
+        assert_eq!(preset.size_bitrates(60.0).unwrap(), (1165, 128));
+        // Longer clips squeeze the audio before giving up on the video
+        assert_eq!(preset.size_bitrates(200.0).unwrap(), (339, 48));
+        let error = format!("{:#}", preset.size_bitrates(600.0).unwrap_err());
+        assert!(error.contains("600s clip is too long to fit in 10 MB"), "{error}");
+
+        assert!(preset.size_bitrates(0.0).is_err());
+        assert!(preset.size_bitrates(f64::NAN).is_err());
+        assert!(ExportPreset::original().size_bitrates(60.0).is_err());
+    }
+
+    #[test]
+    fn video_args_follow_the_preset() {
+        assert_eq!(args(&ExportPreset::original()), "-c:v copy");
+        assert_eq!(
+            args(&ExportPreset::x264("1080p", 20, 1080)),
+            "-c:v libx264 -preset slow -crf 20 -vf scale=-2:'min(1080,ih)':flags=lanczos,fps=60 -pix_fmt yuv420p"
+        );
+        let nvenc = ExportPreset {
+            name: "NVENC".to_string(),
+            codec: "hevc_nvenc".to_string(),
+            bitrate: Some(8000),
+            ..ExportPreset::original()
+        };
+        assert_eq!(args(&nvenc), "-c:v hevc_nvenc -b:v 8000k -maxrate 8000k -bufsize 16000k");
+    }
+
+    #[test]
+    fn validate_rejects_conflicting_presets() {
+        assert!(ExportPresets::default().validate().is_ok());
+
+        assert!(validation_error(|p| p.presets[1].name = "original".to_string())
+            .contains("'original' is used more than once"));
+        assert!(validation_error(|p| p.presets[1].name = " ".to_string()).contains("has no name"));
+        assert!(validation_error(|p| p.presets[1].codec.clear()).contains("has no codec"));
+        assert!(validation_error(|p| p.presets[1].bitrate = Some(8000))
+            .contains("sets both crf and bitrate"));
+        assert!(validation_error(|p| p.presets[3].target_size_mb = Some(0.0))
+            .contains("invalid target size"));
+        assert!(validation_error(|p| p.presets[3].codec = "libx265".to_string())
+            .contains("needs one of: libx264, libsvtav1"));
+        assert!(validation_error(|p| p.presets[3].crf = Some(20)).contains("with crf or bitrate"));
+        assert!(validation_error(|p| p.presets[0].audio_bitrate = 0).contains("no audio bitrate"));
+        assert!(validation_error(|p| p.default = Some("Missing".to_string()))
+            .contains("default preset 'Missing' is not defined"));
+    }
+
+    #[test]
+    fn default_preset_falls_back_to_the_copy() {
+        let mut presets = ExportPresets::default();
+        assert!(presets.default_preset().is_copy());
+        presets.default = Some("fit 10 mb (720p x264)".to_string());
+        assert_eq!(presets.default_preset().name, "Fit 10 MB (720p x264)");
+    }
+}
@@ -95,6 +95,14 @@ impl FailedUploadsList {
         self.uploads.iter().find(|u| u.id == id)
     }
     
+    /// Every file a failed upload still needs for a retry.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.uploads
+            .iter()
+            .flat_map(|u| [u.processed_path.clone(), u.full_path.clone()])
+            .collect()
+    }
+
     fn config_path() -> Result<PathBuf> {
         let home = std::env::var("HOME")
             .context("HOME environment variable not set")?;
@@ -1,16 +1,33 @@
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::{bail, Context, Result};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
+use crate::export::{ExportPreset, MIN_VIDEO_KBPS};
+use crate::process::AudioMix;
+
 pub async fn run_with_progress<F>(
+    command: Command,
+    total_duration: Option<Duration>,
+    callback: F,
+) -> Result<()>
+where
+    F: FnMut(f32),
+{
+    run_with_progress_output(command, total_duration, callback)
+        .await
+        .map(drop)
+}
+
+/// Like [`run_with_progress`], returning what ffmpeg logged to stderr.
+pub async fn run_with_progress_output<F>(
     mut command: Command,
     total_duration: Option<Duration>,
     mut callback: F,
-) -> Result<()>
+) -> Result<String>
 where
     F: FnMut(f32),
 {
@@ -24,6 +41,16 @@ where
         .context("ffmpeg stdout not captured (progress output)")?;
     let mut reader = BufReader::new(stdout).lines();
 
+    // Drain stderr alongside stdout so a chatty ffmpeg can't block on it
+    let stderr = child.stderr.take().map(|stderr| {
+        tokio::spawn(async move {
+            use tokio::io::AsyncReadExt;
+            let mut buf = String::new();
+            let _ = BufReader::new(stderr).read_to_string(&mut buf).await;
+            buf
+        })
+    });
+
     let mut stats = BTreeMap::new();
     let total_micros = total_duration.map(|dur| dur.as_micros() as f64);
 
@@ -59,19 +86,14 @@ where
     }
 
     let status = child.wait().await?;
+    let stderr = match stderr {
+        Some(task) => task.await.unwrap_or_default(),
+        None => String::new(),
+    };
     if !status.success() {
-        let stderr = if let Some(stderr) = child.stderr {
-            use tokio::io::AsyncReadExt;
-            let mut buf = String::new();
-            let mut reader = BufReader::new(stderr);
-            let _ = reader.read_to_string(&mut buf).await;
-            buf
-        } else {
-            String::new()
-        };
         anyhow::bail!("ffmpeg exited with status {status:?}: {stderr}");
     }
-    Ok(())
+    Ok(stderr)
 }
 
 pub async fn probe_duration(path: &Path) -> Result<f64> {
@@ -100,6 +122,105 @@ pub async fn probe_duration(path: &Path) -> Result<f64> {
         .context("Failed to parse duration")
 }
 
+/// Number of audio streams in the file.
+pub async fn probe_audio_streams(path: &Path) -> Result<usize> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-select_streams", "a",
+            "-show_entries", "stream=index",
+            "-of", "csv=p=0",
+            path.to_str().context("path is not valid UTF-8")?,
+        ])
+        .output()
+        .await
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count())
+}
+
+/// Codec, pixel format and frame rate of a file's first video stream.
+#[derive(Debug, Clone)]
+pub struct VideoStream {
+    pub codec: String,
+    pub pix_fmt: String,
+    /// Average frames per second, when ffprobe knows it.
+    pub frame_rate: Option<f64>,
+}
+
+pub async fn probe_video_stream(path: &Path) -> Result<VideoStream> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-show_entries", "stream=codec_name,pix_fmt,avg_frame_rate",
+            "-of", "csv=p=0",
+            path.to_str().context("path is not valid UTF-8")?,
+        ])
+        .output()
+        .await
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next().context("file has no video stream")?;
+    let mut fields = line.trim().split(',');
+    let (Some(codec), Some(pix_fmt)) = (fields.next(), fields.next()) else {
+        bail!("unexpected ffprobe stream output '{line}'");
+    };
+    // Given as a fraction, e.g. 30000/1001, or 0/0 when unknown
+    let frame_rate = fields
+        .next()
+        .and_then(|rate| rate.split_once('/'))
+        .and_then(|(num, den)| Some(num.parse::<f64>().ok()? / den.parse::<f64>().ok()?))
+        .filter(|fps| fps.is_finite() && *fps > 0.0);
+    Ok(VideoStream {
+        codec: codec.to_string(),
+        pix_fmt: pix_fmt.to_string(),
+        frame_rate,
+    })
+}
+
+/// Timestamps of the video keyframes from about `start` to `end` seconds.
+pub async fn probe_keyframes(path: &Path, start: f64, end: f64) -> Result<Vec<f64>> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-read_intervals", &format!("{start}%{end}"),
+            "-show_entries", "packet=pts_time,flags",
+            "-of", "csv=p=0",
+            path.to_str().context("path is not valid UTF-8")?,
+        ])
+        .output()
+        .await
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let mut keyframes: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pts, flags) = line.trim().split_once(',')?;
+            flags.contains('K').then(|| pts.parse().ok()).flatten()
+        })
+        .collect();
+    keyframes.sort_by(f64::total_cmp);
+    Ok(keyframes)
+}
+
 pub async fn trim_video<F>(
     input: &Path,
     output: &Path,
@@ -137,6 +258,9 @@ where
         input_str,
         "-t",
         &duration.to_string(),
+        // Keep every stream, including each captured audio track
+        "-map",
+        "0",
         "-c",
         "copy",
         "-avoid_negative_ts",
@@ -151,3 +275,381 @@ where
     )
     .await
 }
+
+/// Cuts `start_time..end_time` from `input` at the keyframe before each
+/// point, copying the video and mixing the audio with `audio`.
+pub async fn trim_with_mix<F>(
+    input: &Path,
+    output: &Path,
+    start_time: f64,
+    end_time: f64,
+    audio: &AudioMix,
+    on_progress: F,
+) -> Result<()>
+where
+    F: FnMut(f32),
+{
+    let duration = end_time - start_time;
+    if !duration.is_finite() || duration <= 0.0 {
+        bail!("Trim duration must be positive");
+    }
+
+    let input_str = input
+        .to_str()
+        .context("input path is not valid UTF-8")?;
+    let output_str = output
+        .to_str()
+        .context("output path is not valid UTF-8")?;
+
+    let mut command = Command::new("ffmpeg");
+    command.args([
+        "-hide_banner",
+        "-loglevel",
+        "warning",
+        "-y",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-ss",
+        &start_time.to_string(),
+        "-i",
+        input_str,
+        "-t",
+        &duration.to_string(),
+        "-map",
+        "0:v:0",
+    ]);
+    command.args(audio.args(0));
+    command.args([
+        "-c:v",
+        "copy",
+        "-avoid_negative_ts",
+        "make_zero",
+        "-movflags",
+        "+faststart",
+        output_str,
+    ]);
+
+    run_with_progress(
+        command,
+        Some(Duration::from_secs_f64(duration)),
+        on_progress,
+    )
+    .await
+}
+
+/// Trims like [`trim_with_mix`] but re-encodes the video with `preset`.
+pub async fn encode_video<F>(
+    input: &Path,
+    output: &Path,
+    start_time: f64,
+    end_time: f64,
+    preset: &ExportPreset,
+    audio: &AudioMix,
+    on_progress: F,
+) -> Result<()>
+where
+    F: FnMut(f32),
+{
+    let duration = end_time - start_time;
+    if !duration.is_finite() || duration <= 0.0 {
+        bail!("Trim duration must be positive");
+    }
+
+    let input_str = input
+        .to_str()
+        .context("input path is not valid UTF-8")?;
+    let output_str = output
+        .to_str()
+        .context("output path is not valid UTF-8")?;
+
+    let mut command = Command::new("ffmpeg");
+    command.args([
+        "-hide_banner",
+        "-loglevel",
+        "warning",
+        "-y",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-ss",
+        &start_time.to_string(),
+        "-i",
+        input_str,
+        "-t",
+        &duration.to_string(),
+        "-map",
+        "0:v:0",
+    ]);
+    command.args(preset.video_args());
+    command.args(audio.args(0));
+    command.args(["-movflags", "+faststart", output_str]);
+
+    run_with_progress(
+        command,
+        Some(Duration::from_secs_f64(duration)),
+        on_progress,
+    )
+    .await
+}
+
+/// Encodes attempts at most before giving up on a size target.
+const SIZE_ATTEMPTS: usize = 3;
+
+/// Trims and encodes to fit `preset`'s target size with a two-pass encode.
+/// When the result still overshoots, the video bitrate is lowered and both
+/// passes run again. Only the mix is kept, at a bitrate that fits.
+/// `on_progress` gets the pass number and that pass's progress.
+pub async fn encode_to_size<F>(
+    input: &Path,
+    output: &Path,
+    start_time: f64,
+    end_time: f64,
+    preset: &ExportPreset,
+    audio: &AudioMix,
+    mut on_progress: F,
+) -> Result<()>
+where
+    F: FnMut(usize, f32),
+{
+    // The bitrates are only right if the range really is that long, so an
+    // end past the source's own is clamped to it
+    let source_duration = probe_duration(input).await?;
+    let duration = end_time.min(source_duration) - start_time;
+    if !duration.is_finite() || duration <= 0.0 {
+        bail!("Trim duration must be positive");
+    }
+    let target_bytes = preset
+        .target_size_mb
+        .map(|mb| (mb * 1_000_000.0) as u64)
+        .context("preset has no target size")?;
+    let (mut video_kbps, audio_kbps) = preset.size_bitrates(duration)?;
+    let audio = audio.clone().with_bitrate(audio_kbps).mixed_only();
+
+    let input_str = input
+        .to_str()
+        .context("input path is not valid UTF-8")?;
+    let output_str = output
+        .to_str()
+        .context("output path is not valid UTF-8")?;
+    let passlog = output.with_extension("passlog");
+    let passlog_str = passlog
+        .to_str()
+        .context("pass log path is not valid UTF-8")?;
+
+    let mut result = Err(anyhow::anyhow!("no encode attempted"));
+    for attempt in 1..=SIZE_ATTEMPTS {
+        eprintln!(
+            "[CLIPS_APP] Encoding to {} MB at {video_kbps}k video, {audio_kbps}k audio (attempt {attempt})",
+            preset.target_size_mb.unwrap_or_default()
+        );
+        result = async {
+            for pass in 1..=2 {
+                let mut command = Command::new("ffmpeg");
+                command.args([
+                    "-hide_banner",
+                    "-loglevel",
+                    "warning",
+                    "-y",
+                    "-nostats",
+                    "-progress",
+                    "pipe:1",
+                    "-ss",
+                    &start_time.to_string(),
+                    "-i",
+                    input_str,
+                    "-t",
+                    &duration.to_string(),
+                    "-map",
+                    "0:v:0",
+                ]);
+                command.args(preset.video_args());
+                command.args([
+                    "-b:v",
+                    &format!("{video_kbps}k"),
+                    "-pass",
+                    &pass.to_string(),
+                    "-passlogfile",
+                    passlog_str,
+                ]);
+                if pass == 1 {
+                    // The first pass only gathers statistics
+                    command.args(["-an", "-f", "null", "/dev/null"]);
+                } else {
+                    command.args(audio.args(0));
+                    command.args(["-movflags", "+faststart", output_str]);
+                }
+                run_with_progress(
+                    command,
+                    Some(Duration::from_secs_f64(duration)),
+                    |fraction| on_progress(pass, fraction),
+                )
+                .await?;
+            }
+            Ok(())
+        }
+        .await;
+        if result.is_err() {
+            break;
+        }
+
+        let size = std::fs::metadata(output)
+            .with_context(|| format!("reading size of {}", output.display()))?
+            .len();
+        if size <= target_bytes {
+            break;
+        }
+        eprintln!("[CLIPS_APP] Encode is {size} bytes, over the {target_bytes} byte target");
+        result = Err(anyhow::anyhow!(
+            "could not fit the clip in {} MB after {attempt} attempts",
+            preset.target_size_mb.unwrap_or_default()
+        ));
+        video_kbps = (video_kbps as f64 * target_bytes as f64 / size as f64 * 0.95) as u32;
+        if video_kbps < MIN_VIDEO_KBPS {
+            break;
+        }
+    }
+
+    remove_pass_logs(&passlog);
+    result
+}
+
+/// Removes the statistics files an encoder wrote next to `prefix`.
+fn remove_pass_logs(prefix: &Path) {
+    let (Some(dir), Some(name)) = (prefix.parent(), prefix.file_name()) else {
+        return;
+    };
+    let name = name.to_string_lossy();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(name.as_ref()) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Joins `inputs` back to back into `output` without re-encoding, using the
+/// concat demuxer with a list file written to `list_path`.
+pub async fn concat_files(inputs: &[PathBuf], list_path: &Path, output: &Path) -> Result<()> {
+    if inputs.is_empty() {
+        bail!("nothing to concatenate");
+    }
+
+    let mut list = String::new();
+    for input in inputs {
+        let input_str = input
+            .to_str()
+            .context("input path is not valid UTF-8")?;
+        list.push_str(&format!("file '{}'\n", input_str.replace('\'', "'\\''")));
+    }
+    std::fs::write(list_path, list)
+        .with_context(|| format!("writing concat list {:?}", list_path))?;
+
+    let list_str = list_path
+        .to_str()
+        .context("list path is not valid UTF-8")?;
+    let output_str = output
+        .to_str()
+        .context("output path is not valid UTF-8")?;
+
+    let mut command = Command::new("ffmpeg");
+    command.args([
+        "-hide_banner",
+        "-loglevel",
+        "warning",
+        "-y",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-f",
+        "concat",
+        "-safe",
+        "0",
+        "-i",
+        list_str,
+        "-map",
+        "0",
+        "-c",
+        "copy",
+        "-movflags",
+        "+faststart",
+        output_str,
+    ]);
+
+    let result = run_with_progress(command, None, |_| {}).await;
+    let _ = std::fs::remove_file(list_path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportPresets;
+
+    #[tokio::test]
+    #[ignore = "needs ffmpeg"]
+    async fn encode_to_size_sizes_for_the_source_length() {
+        let dir = testing::temp_dir("encode-to-size");
+        let input = dir.join("input.mp4");
+        let output = dir.join("output.mp4");
+        testing::write_clip(&input, 4, testing::FPS).await.unwrap();
+
+        let presets = ExportPresets::default();
+        let preset = ExportPreset {
+            target_size_mb: Some(0.5),
+            ..presets.get("Fit 10 MB (720p x264)").unwrap().clone()
+        };
+        // Sized for the requested 59s this wouldn't fit at any usable bitrate
+        encode_to_size(&input, &output, 1.0, 60.0, &preset, &AudioMix::first_stream(), |_, _| {})
+            .await
+            .unwrap();
+
+        let size = std::fs::metadata(&output).unwrap().len();
+        assert!(size <= 500_000, "{size} bytes");
+        let duration = probe_duration(&output).await.unwrap();
+        assert!((duration - 3.0).abs() < 0.1, "encoded {duration}s");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+/// Media fixtures for tests that run the real ffmpeg.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::{Path, PathBuf};
+
+    use anyhow::{bail, Result};
+    use tokio::process::Command;
+
+    pub const FPS: u32 = 30;
+
+    /// A fresh scratch directory for one test.
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clips-app-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("creating test directory");
+        dir
+    }
+
+    /// Writes a `secs` long H.264 test pattern at [`FPS`] with a keyframe
+    /// every `gop` frames and a tone on one audio track.
+    pub async fn write_clip(path: &Path, secs: u32, gop: u32) -> Result<()> {
+        let status = Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error", "-y", "-f", "lavfi", "-i"])
+            .arg(format!("testsrc=size=320x240:rate={FPS}:duration={secs}"))
+            .args(["-f", "lavfi", "-i"])
+            .arg(format!("sine=frequency=440:duration={secs}"))
+            .args(["-c:v", "libx264", "-preset", "ultrafast", "-pix_fmt", "yuv420p"])
+            .args(["-g", &gop.to_string(), "-keyint_min", &gop.to_string(), "-sc_threshold", "0"])
+            .args(["-c:a", "aac", "-shortest"])
+            .arg(path)
+            .status()
+            .await?;
+        if !status.success() {
+            bail!("ffmpeg failed to write test clip {}", path.display());
+        }
+        Ok(())
+    }
+}
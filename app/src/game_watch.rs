@@ -0,0 +1,355 @@
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use tokio::sync::mpsc;
+
+use crate::games::{DetectedGame, GameDetectorChain};
+
+/// How often to rescan `/proc` when process events aren't available.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Launcher install lists and user rules are re-read this often.
+const CHAIN_REFRESH: Duration = Duration::from_secs(60);
+
+// From linux/connector.h and linux/cn_proc.h
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_EVENT_NONE: u32 = 0;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+const NLMSG_HDR_LEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+/// Offset of `proc_event.event_data` within a connector message.
+const EVENT_DATA: usize = NLMSG_HDR_LEN + CN_MSG_LEN + 16;
+
+/// A game starting or exiting, with when the watcher noticed.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    Started(DetectedGame, SystemTime),
+    Exited(DetectedGame, SystemTime),
+}
+
+/// Watches for games starting and exiting on a background thread. Launches
+/// come from the kernel's process connector when it's permitted (it usually
+/// needs CAP_NET_ADMIN), otherwise from polling `/proc`; exits come from a
+/// pidfd on the game's process.
+pub fn spawn_game_watcher(events: mpsc::UnboundedSender<GameEvent>) -> Result<std::thread::JoinHandle<()>> {
+    std::thread::Builder::new()
+        .name("game-watcher".into())
+        .spawn(move || {
+            let connector = match ProcConnector::open() {
+                Ok(connector) => {
+                    eprintln!("[CLIPS_APP] Watching process launches via netlink");
+                    Some(connector)
+                }
+                Err(err) => {
+                    eprintln!(
+                        "[CLIPS_APP] Process connector unavailable ({err:#}), polling for games every {}s",
+                        POLL_INTERVAL.as_secs()
+                    );
+                    None
+                }
+            };
+            let mut watcher = Watcher {
+                chain: GameDetectorChain::with_defaults(),
+                chain_built: Instant::now(),
+                events,
+                current: None,
+            };
+            if let Err(err) = watcher.run(connector.as_ref()) {
+                eprintln!("[CLIPS_APP] Game watcher stopped: {err:#}");
+            }
+        })
+        .context("spawning game watcher thread")
+}
+
+struct Tracked {
+    game: DetectedGame,
+    pidfd: Option<OwnedFd>,
+}
+
+struct Watcher {
+    chain: GameDetectorChain,
+    chain_built: Instant,
+    events: mpsc::UnboundedSender<GameEvent>,
+    current: Option<Tracked>,
+}
+
+impl Watcher {
+    fn run(&mut self, connector: Option<&ProcConnector>) -> Result<()> {
+        self.rescan()?;
+
+        loop {
+            if self.chain_built.elapsed() >= CHAIN_REFRESH {
+                self.chain = GameDetectorChain::with_defaults();
+                self.chain_built = Instant::now();
+            }
+
+            let pidfd = self
+                .current
+                .as_ref()
+                .and_then(|tracked| tracked.pidfd.as_ref())
+                .map(AsRawFd::as_raw_fd);
+            let mut fds: Vec<libc::pollfd> = connector
+                .map(ProcConnector::as_raw_fd)
+                .into_iter()
+                .chain(pidfd)
+                .map(|fd| libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+
+            // Poll when nothing will tell us about the next launch or exit
+            let polling = match &self.current {
+                None => connector.is_none(),
+                Some(_) => pidfd.is_none(),
+            };
+            let timeout = if polling { POLL_INTERVAL.as_millis() as i32 } else { -1 };
+
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err).context("polling for process events");
+            }
+
+            if ready == 0 {
+                match &self.current {
+                    None => self.rescan()?,
+                    Some(tracked) if !Path::new(&format!("/proc/{}", tracked.game.pid)).exists() => self.rescan()?,
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            for pollfd in fds.iter().filter(|pollfd| pollfd.revents != 0) {
+                if Some(pollfd.fd) == pidfd {
+                    self.rescan()?;
+                } else if let Some(connector) = connector {
+                    for event in connector.read_events()? {
+                        match event {
+                            ProcEvent::Exec(pid) => self.on_exec(pid)?,
+                            ProcEvent::Exit(pid) => {
+                                if self.current.as_ref().is_some_and(|tracked| tracked.game.pid == pid) {
+                                    self.rescan()?;
+                                }
+                            }
+                            ProcEvent::Overrun => {
+                                eprintln!("[CLIPS_APP] Missed process events, rescanning");
+                                self.rescan()?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn on_exec(&mut self, pid: u32) -> Result<()> {
+        if self.current.is_some() {
+            return Ok(());
+        }
+        match self.chain.identify_pid(pid) {
+            Some(game) => self.set_current(Some(game)),
+            None => Ok(()),
+        }
+    }
+
+    /// Looks for a running game from scratch. Called at startup, when the
+    /// tracked process exits (the game may have other processes still
+    /// running) and on every tick while polling.
+    fn rescan(&mut self) -> Result<()> {
+        let game = self.chain.scan()?;
+        self.set_current(game)
+    }
+
+    fn set_current(&mut self, game: Option<DetectedGame>) -> Result<()> {
+        let now = SystemTime::now();
+        let previous = self.current.take();
+        let same_game = match (&previous, &game) {
+            (Some(previous), Some(game)) => previous.game.name == game.name,
+            _ => false,
+        };
+
+        if let Some(previous) = previous.filter(|_| !same_game) {
+            eprintln!("[CLIPS_APP] Game exited: {}", previous.game.name);
+            self.send(GameEvent::Exited(previous.game, now))?;
+        }
+
+        if let Some(game) = game {
+            if !same_game {
+                eprintln!(
+                    "[CLIPS_APP] Game started: {} (pid {}, via {})",
+                    game.name, game.pid, game.source
+                );
+                self.send(GameEvent::Started(game.clone(), now))?;
+            }
+            let pidfd = pidfd_open(game.pid)
+                .map_err(|err| eprintln!("[CLIPS_APP] pidfd_open({}) failed: {err}", game.pid))
+                .ok();
+            self.current = Some(Tracked { game, pidfd });
+        }
+        Ok(())
+    }
+
+    fn send(&self, event: GameEvent) -> Result<()> {
+        if self.events.send(event).is_err() {
+            bail!("event receiver closed");
+        }
+        Ok(())
+    }
+}
+
+fn pidfd_open(pid: u32) -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+enum ProcEvent {
+    Exec(u32),
+    Exit(u32),
+    /// The socket buffer overflowed and events were dropped.
+    Overrun,
+}
+
+/// A netlink socket subscribed to the kernel's process connector.
+struct ProcConnector {
+    fd: OwnedFd,
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+impl ProcConnector {
+    fn open() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error()).context("creating netlink socket");
+        }
+        let connector = Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        };
+
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = CN_IDX_PROC;
+        let bound = unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error()).context("binding to the process connector");
+        }
+
+        // nlmsghdr + cn_msg + PROC_CN_MCAST_LISTEN
+        let mut request = Vec::with_capacity(NLMSG_HDR_LEN + CN_MSG_LEN + 4);
+        request.extend_from_slice(&((NLMSG_HDR_LEN + CN_MSG_LEN + 4) as u32).to_ne_bytes());
+        request.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        request.extend_from_slice(&0u16.to_ne_bytes());
+        request.extend_from_slice(&0u32.to_ne_bytes());
+        request.extend_from_slice(&std::process::id().to_ne_bytes());
+        request.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        request.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        request.extend_from_slice(&0u32.to_ne_bytes());
+        request.extend_from_slice(&0u32.to_ne_bytes());
+        request.extend_from_slice(&4u16.to_ne_bytes());
+        request.extend_from_slice(&0u16.to_ne_bytes());
+        request.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        let sent = unsafe { libc::send(fd, request.as_ptr().cast(), request.len(), 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error()).context("subscribing to process events");
+        }
+
+        // The kernel acks the subscription, with an error if it was refused
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pollfd, 1, 1000) } > 0 {
+            let mut buf = [0u8; 256];
+            let len = unsafe { libc::recv(fd, buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if len >= (EVENT_DATA + 4) as isize
+                && u32_at(&buf, NLMSG_HDR_LEN + CN_MSG_LEN) == PROC_EVENT_NONE
+            {
+                let err = u32_at(&buf, EVENT_DATA);
+                if err != 0 {
+                    return Err(io::Error::from_raw_os_error(err as i32))
+                        .context("subscribing to process events");
+                }
+            }
+        }
+
+        Ok(connector)
+    }
+
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    fn read_events(&self) -> Result<Vec<ProcEvent>> {
+        let mut buf = [0u8; 4096];
+        let len = unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                libc::MSG_DONTWAIT,
+            )
+        };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENOBUFS) => Ok(vec![ProcEvent::Overrun]),
+                Some(libc::EAGAIN) | Some(libc::EINTR) => Ok(Vec::new()),
+                _ => Err(err).context("reading process events"),
+            };
+        }
+
+        let buf = &buf[..len as usize];
+        let mut events = Vec::new();
+        let mut offset = 0;
+        while offset + NLMSG_HDR_LEN <= buf.len() {
+            let msg_len = u32_at(buf, offset) as usize;
+            if msg_len < NLMSG_HDR_LEN || offset + msg_len > buf.len() {
+                break;
+            }
+            let message = &buf[offset..offset + msg_len];
+            if message.len() >= EVENT_DATA + 8 {
+                let what = u32_at(message, NLMSG_HDR_LEN + CN_MSG_LEN);
+                let pid = u32_at(message, EVENT_DATA);
+                let tgid = u32_at(message, EVENT_DATA + 4);
+                // Thread execs and exits don't matter, only whole processes
+                if pid == tgid {
+                    match what {
+                        PROC_EVENT_EXEC => events.push(ProcEvent::Exec(tgid)),
+                        PROC_EVENT_EXIT => events.push(ProcEvent::Exit(tgid)),
+                        _ => {}
+                    }
+                }
+            }
+            offset += (msg_len + 3) & !3;
+        }
+        Ok(events)
+    }
+}
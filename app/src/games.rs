@@ -0,0 +1,624 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::steam::SteamLibraries;
+
+/// Launchers shipped as Flatpaks that are categorised as games themselves.
+const FLATPAK_LAUNCHERS: &[&str] = &[
+    "com.valvesoftware.Steam",
+    "net.lutris.Lutris",
+    "com.heroicgameslauncher.hgl",
+    "com.usebottles.bottles",
+    "org.prismlauncher.PrismLauncher",
+];
+
+/// What a detector can see of a running process.
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub exe: Option<PathBuf>,
+    pub cmdline: Vec<String>,
+    pub environ: HashMap<String, String>,
+}
+
+impl ProcessInfo {
+    /// Reads `/proc/<pid>`, skipping processes owned by other users.
+    pub fn read(pid: u32) -> Option<Self> {
+        let dir = PathBuf::from(format!("/proc/{pid}"));
+        let current_uid = unsafe { libc::getuid() };
+        let status = fs::read_to_string(dir.join("status")).ok()?;
+        let owned = status.lines().any(|line| {
+            line.starts_with("Uid:")
+                && line
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|uid| uid.parse::<u32>().ok())
+                    .is_some_and(|uid| uid == current_uid)
+        });
+        if !owned {
+            return None;
+        }
+
+        let split_nul = |data: Vec<u8>| -> Vec<String> {
+            data.split(|b| *b == 0)
+                .filter(|part| !part.is_empty())
+                .map(|part| String::from_utf8_lossy(part).into_owned())
+                .collect()
+        };
+        let environ = split_nul(fs::read(dir.join("environ")).unwrap_or_default())
+            .into_iter()
+            .filter_map(|var| {
+                let (key, value) = var.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect();
+
+        Some(Self {
+            pid,
+            exe: fs::read_link(dir.join("exe")).ok(),
+            cmdline: split_nul(fs::read(dir.join("cmdline")).unwrap_or_default()),
+            environ,
+        })
+    }
+
+    pub fn env(&self, key: &str) -> Option<&str> {
+        self.environ
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// The executable's file name plus that of `argv[0]`, which is the only
+    /// place a Wine game's own `.exe` shows up.
+    fn exe_names(&self) -> Vec<String> {
+        self.exe
+            .iter()
+            .map(|exe| exe.to_string_lossy().into_owned())
+            .chain(self.cmdline.first().cloned())
+            .filter_map(|path| {
+                unix_path(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            })
+            .collect()
+    }
+
+    /// Every path-looking argument, with Wine drive paths mapped back to Unix.
+    fn paths(&self) -> Vec<PathBuf> {
+        self.exe
+            .iter()
+            .cloned()
+            .chain(
+                self.cmdline
+                    .iter()
+                    .filter(|arg| arg.contains('/') || arg.contains('\\'))
+                    .map(|arg| unix_path(arg)),
+            )
+            .collect()
+    }
+}
+
+/// Maps `Z:\home\me\game.exe` style arguments to `/home/me/game.exe`.
+fn unix_path(arg: &str) -> PathBuf {
+    let arg = arg.replace('\\', "/");
+    match arg.get(..2) {
+        Some(drive) if drive.eq_ignore_ascii_case("z:") => PathBuf::from(&arg[2..]),
+        _ => PathBuf::from(arg),
+    }
+}
+
+/// A game as a detector names it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameIdentity {
+    pub name: String,
+    /// The launcher's own ID: Steam app ID, Heroic app name, Flatpak app ID
+    /// or Lutris game UUID.
+    pub appid: Option<String>,
+}
+
+impl GameIdentity {
+    fn new(name: impl Into<String>, appid: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            appid: Some(appid.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedGame {
+    pub name: String,
+    pub appid: Option<String>,
+    pub pid: u32,
+    /// Which detector recognised it.
+    pub source: &'static str,
+}
+
+/// Recognises games from one launcher or naming scheme.
+pub trait GameDetector {
+    fn name(&self) -> &'static str;
+    /// Names the game `process` belongs to, if any.
+    fn identify(&self, process: &ProcessInfo) -> Option<GameIdentity>;
+}
+
+/// Detectors tried in order against each process; the first match wins.
+pub struct GameDetectorChain {
+    detectors: Vec<Box<dyn GameDetector>>,
+}
+
+impl GameDetectorChain {
+    pub fn new(detectors: Vec<Box<dyn GameDetector>>) -> Self {
+        Self { detectors }
+    }
+
+    /// User rules first so they can rename anything a launcher reports.
+    pub fn with_defaults() -> Self {
+        let rules = GameRules::load().unwrap_or_else(|err| {
+            eprintln!("[CLIPS_APP] Failed to load game rules: {err:#}");
+            GameRules::default()
+        });
+        let rules = RuleDetector::new(&rules).unwrap_or_else(|err| {
+            eprintln!("[CLIPS_APP] Ignoring game rules: {err:#}");
+            RuleDetector::default()
+        });
+        Self::new(vec![
+            Box::new(rules),
+            Box::new(SteamDetector::new()),
+            Box::new(LutrisDetector),
+            Box::new(HeroicDetector::new()),
+            Box::new(FlatpakDetector::default()),
+        ])
+    }
+
+    pub fn identify(&self, process: &ProcessInfo) -> Option<DetectedGame> {
+        self.detectors.iter().find_map(|detector| {
+            let identity = detector.identify(process)?;
+            Some(DetectedGame {
+                name: identity.name,
+                appid: identity.appid,
+                pid: process.pid,
+                source: detector.name(),
+            })
+        })
+    }
+
+    pub fn identify_pid(&self, pid: u32) -> Option<DetectedGame> {
+        self.identify(&ProcessInfo::read(pid)?)
+    }
+
+    /// Checks every process in `/proc` and returns the first game found.
+    pub fn scan(&self) -> Result<Option<DetectedGame>> {
+        let proc_dir = fs::read_dir("/proc").context("failed to read /proc")?;
+        for entry in proc_dir.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+                continue;
+            };
+            if let Some(game) = self.identify_pid(pid) {
+                return Ok(Some(game));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Scans for a running game with the default detector chain.
+pub fn detect_running_game() -> Result<Option<DetectedGame>> {
+    GameDetectorChain::with_defaults().scan()
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// Games launched by Steam carry `SteamAppId` (or `SteamGameId`) in their
+/// environment; the name comes from the app manifest in whichever library
+/// the game is installed to.
+pub struct SteamDetector {
+    libraries: SteamLibraries,
+}
+
+impl SteamDetector {
+    pub fn new() -> Self {
+        Self {
+            libraries: SteamLibraries::discover(),
+        }
+    }
+}
+
+impl Default for SteamDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameDetector for SteamDetector {
+    fn name(&self) -> &'static str {
+        "steam"
+    }
+
+    fn identify(&self, process: &ProcessInfo) -> Option<GameIdentity> {
+        let appid = process
+            .env("SteamAppId")
+            .or_else(|| process.env("SteamGameId"))?
+            .parse::<u32>()
+            .ok()?;
+        self.libraries
+            .app(appid)
+            .map(|app| GameIdentity::new(app.name, appid.to_string()))
+    }
+}
+
+/// Lutris exports the game's name to everything it launches.
+pub struct LutrisDetector;
+
+impl GameDetector for LutrisDetector {
+    fn name(&self) -> &'static str {
+        "lutris"
+    }
+
+    fn identify(&self, process: &ProcessInfo) -> Option<GameIdentity> {
+        let uuid = process.env("LUTRIS_GAME_UUID")?;
+        process.env("GAME_NAME").map(|name| GameIdentity::new(name, uuid))
+    }
+}
+
+struct InstalledGame {
+    app_name: String,
+    title: String,
+    install_path: PathBuf,
+}
+
+/// Epic games installed through Legendary (standalone or inside Heroic) and
+/// GOG games installed through Heroic. Matched by `HEROIC_APP_NAME` or by
+/// the process running from an install directory.
+pub struct HeroicDetector {
+    installed: Vec<InstalledGame>,
+}
+
+#[derive(Deserialize)]
+struct LegendaryInstall {
+    app_name: String,
+    title: String,
+    install_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct GogInstalled {
+    installed: Vec<GogInstall>,
+}
+
+#[derive(Deserialize)]
+struct GogInstall {
+    #[serde(rename = "appName")]
+    app_name: String,
+    install_path: PathBuf,
+}
+
+impl HeroicDetector {
+    pub fn new() -> Self {
+        let mut installed = Vec::new();
+        let Some(home) = home_dir() else {
+            return Self { installed };
+        };
+        let heroic_roots = [
+            home.join(".config/heroic"),
+            home.join(".var/app/com.heroicgameslauncher.hgl/config/heroic"),
+        ];
+
+        let legendary_files = std::iter::once(home.join(".config/legendary/installed.json"))
+            .chain(heroic_roots.iter().map(|root| root.join("legendaryConfig/legendary/installed.json")));
+        for path in legendary_files {
+            let Some(games) = read_json::<HashMap<String, LegendaryInstall>>(&path) else {
+                continue;
+            };
+            installed.extend(games.into_values().map(|game| InstalledGame {
+                app_name: game.app_name,
+                title: game.title,
+                install_path: game.install_path,
+            }));
+        }
+
+        for root in &heroic_roots {
+            let Some(gog) = read_json::<GogInstalled>(&root.join("gog_store/installed.json")) else {
+                continue;
+            };
+            // GOG's install list has no titles, but the install directory is
+            // named after the game
+            installed.extend(gog.installed.into_iter().filter_map(|game| {
+                let title = game.install_path.file_name()?.to_string_lossy().into_owned();
+                Some(InstalledGame {
+                    app_name: game.app_name,
+                    title,
+                    install_path: game.install_path,
+                })
+            }));
+        }
+
+        Self { installed }
+    }
+}
+
+impl Default for HeroicDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|err| eprintln!("[CLIPS_APP] Failed to parse {}: {err}", path.display()))
+        .ok()
+}
+
+impl GameDetector for HeroicDetector {
+    fn name(&self) -> &'static str {
+        "heroic"
+    }
+
+    fn identify(&self, process: &ProcessInfo) -> Option<GameIdentity> {
+        if let Some(app_name) = process.env("HEROIC_APP_NAME") {
+            if let Some(game) = self.installed.iter().find(|game| game.app_name == app_name) {
+                return Some(GameIdentity::new(&game.title, &game.app_name));
+            }
+        }
+
+        let paths = process.paths();
+        self.installed
+            .iter()
+            .find(|game| paths.iter().any(|path| path.starts_with(&game.install_path)))
+            .map(|game| GameIdentity::new(&game.title, &game.app_name))
+    }
+}
+
+/// Flatpak apps in the `Game` category, named from their desktop entry.
+#[derive(Default)]
+pub struct FlatpakDetector {
+    /// App ID to game name (or `None` for non-games), filled as apps are seen.
+    names: RefCell<HashMap<String, Option<String>>>,
+}
+
+impl FlatpakDetector {
+    fn lookup(app_id: &str) -> Option<String> {
+        let file_name = format!("{app_id}.desktop");
+        let dirs = home_dir()
+            .map(|home| home.join(".local/share/flatpak/exports/share/applications"))
+            .into_iter()
+            .chain(std::iter::once(PathBuf::from("/var/lib/flatpak/exports/share/applications")));
+        for dir in dirs {
+            let Ok(contents) = fs::read_to_string(dir.join(&file_name)) else {
+                continue;
+            };
+            let mut name = None;
+            let mut is_game = false;
+            let mut in_entry = false;
+            for line in contents.lines() {
+                let line = line.trim();
+                if line.starts_with('[') {
+                    in_entry = line == "[Desktop Entry]";
+                    continue;
+                }
+                if !in_entry {
+                    continue;
+                }
+                if let Some(value) = line.strip_prefix("Name=") {
+                    name = Some(value.to_string());
+                } else if let Some(value) = line.strip_prefix("Categories=") {
+                    is_game = value.split(';').any(|category| category == "Game");
+                }
+            }
+            return name.filter(|_| is_game);
+        }
+        None
+    }
+}
+
+impl GameDetector for FlatpakDetector {
+    fn name(&self) -> &'static str {
+        "flatpak"
+    }
+
+    fn identify(&self, process: &ProcessInfo) -> Option<GameIdentity> {
+        let app_id = process.env("FLATPAK_ID")?;
+        if FLATPAK_LAUNCHERS.contains(&app_id) {
+            return None;
+        }
+        self.names
+            .borrow_mut()
+            .entry(app_id.to_string())
+            .or_insert_with(|| Self::lookup(app_id))
+            .clone()
+            .map(|name| GameIdentity::new(name, app_id))
+    }
+}
+
+/// A user-defined mapping from an executable name and/or a command line
+/// pattern to a display name. When both are set both must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameRule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmdline: Option<String>,
+}
+
+/// Rules loaded from `~/.config/clips-app/game_rules.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameRules {
+    pub rules: Vec<GameRule>,
+}
+
+impl GameRules {
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context("failed to read game rules file")?;
+        let rules: GameRules = serde_json::from_str(&contents)
+            .context("failed to parse game rules file")?;
+        Ok(rules)
+    }
+
+    fn config_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".config/clips-app/game_rules.json"))
+    }
+}
+
+struct CompiledRule {
+    name: String,
+    exe: Option<String>,
+    cmdline: Option<Regex>,
+}
+
+#[derive(Default)]
+pub struct RuleDetector {
+    rules: Vec<CompiledRule>,
+}
+
+fn strip_exe(name: &str) -> &str {
+    name.strip_suffix(".exe").unwrap_or(name)
+}
+
+impl RuleDetector {
+    pub fn new(rules: &GameRules) -> Result<Self> {
+        let rules = rules
+            .rules
+            .iter()
+            .filter(|rule| rule.exe.is_some() || rule.cmdline.is_some())
+            .map(|rule| {
+                let cmdline = rule
+                    .cmdline
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .with_context(|| format!("invalid cmdline pattern for '{}'", rule.name))?;
+                Ok(CompiledRule {
+                    name: rule.name.clone(),
+                    exe: rule.exe.as_deref().map(|exe| strip_exe(&exe.to_ascii_lowercase()).to_string()),
+                    cmdline,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+}
+
+impl GameDetector for RuleDetector {
+    fn name(&self) -> &'static str {
+        "rules"
+    }
+
+    fn identify(&self, process: &ProcessInfo) -> Option<GameIdentity> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let exe_names = process.exe_names();
+        let cmdline = process.cmdline.join(" ");
+        self.rules
+            .iter()
+            .find(|rule| {
+                let exe_matches = rule.exe.as_ref().is_none_or(|exe| {
+                    exe_names.iter().any(|name| strip_exe(name) == exe)
+                });
+                let cmdline_matches = rule
+                    .cmdline
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.is_match(&cmdline));
+                exe_matches && cmdline_matches
+            })
+            .map(|rule| GameIdentity {
+                name: rule.name.clone(),
+                appid: None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(exe: Option<&str>, cmdline: &[&str]) -> ProcessInfo {
+        ProcessInfo {
+            pid: 1,
+            exe: exe.map(PathBuf::from),
+            cmdline: cmdline.iter().map(|arg| arg.to_string()).collect(),
+            environ: HashMap::new(),
+        }
+    }
+
+    fn rules(rules: &[(&str, Option<&str>, Option<&str>)]) -> RuleDetector {
+        let rules = GameRules {
+            rules: rules
+                .iter()
+                .map(|(name, exe, cmdline)| GameRule {
+                    name: name.to_string(),
+                    exe: exe.map(str::to_string),
+                    cmdline: cmdline.map(str::to_string),
+                })
+                .collect(),
+        };
+        RuleDetector::new(&rules).unwrap()
+    }
+
+    fn identify(detector: &RuleDetector, process: &ProcessInfo) -> Option<String> {
+        detector.identify(process).map(|identity| identity.name)
+    }
+
+    #[test]
+    fn maps_wine_paths_to_unix() {
+        assert_eq!(unix_path(r"Z:\home\me\Game\game.exe"), Path::new("/home/me/Game/game.exe"));
+        assert_eq!(unix_path(r"z:\opt\game.exe"), Path::new("/opt/game.exe"));
+        assert_eq!(unix_path(r"C:\windows\system32\explorer.exe"), Path::new("C:/windows/system32/explorer.exe"));
+        assert_eq!(unix_path("/usr/bin/game"), Path::new("/usr/bin/game"));
+    }
+
+    #[test]
+    fn rules_match_native_and_wine_executables() {
+        let detector = rules(&[("Celeste", Some("Celeste.exe"), None)]);
+        let native = process(Some("/games/celeste/Celeste"), &["/games/celeste/Celeste"]);
+        assert_eq!(identify(&detector, &native).as_deref(), Some("Celeste"));
+
+        // Under Wine the exe is the loader and the game only shows up in argv[0]
+        let wine = process(
+            Some("/usr/bin/wine64-preloader"),
+            &[r"Z:\games\celeste\CELESTE.EXE"],
+        );
+        assert_eq!(identify(&detector, &wine).as_deref(), Some("Celeste"));
+        assert_eq!(identify(&detector, &process(Some("/usr/bin/bash"), &["bash"])), None);
+    }
+
+    #[test]
+    fn rules_need_every_set_field_to_match() {
+        let detector = rules(&[
+            ("Modded", Some("java"), Some(r"--modpack\s+\w+")),
+            ("Minecraft", Some("java"), None),
+            ("Ignored", None, None),
+        ]);
+        let modded = process(Some("/usr/bin/java"), &["java", "--modpack", "skyfactory"]);
+        assert_eq!(identify(&detector, &modded).as_deref(), Some("Modded"));
+        let vanilla = process(Some("/usr/bin/java"), &["java", "-jar", "launcher.jar"]);
+        assert_eq!(identify(&detector, &vanilla).as_deref(), Some("Minecraft"));
+
+        assert!(identify(&RuleDetector::default(), &vanilla).is_none());
+        let invalid = GameRules {
+            rules: vec![GameRule {
+                name: "Broken".to_string(),
+                exe: None,
+                cmdline: Some("(".to_string()),
+            }],
+        };
+        assert!(RuleDetector::new(&invalid).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use evdev::Key;
+
+use crate::capture::parse_duration;
+
+/// Friendly names accepted in addition to the evdev constant names.
+const KEY_ALIASES: &[(&str, Key)] = &[
+    ("alt", Key::KEY_LEFTALT),
+    ("ctrl", Key::KEY_LEFTCTRL),
+    ("control", Key::KEY_LEFTCTRL),
+    ("shift", Key::KEY_LEFTSHIFT),
+    ("super", Key::KEY_LEFTMETA),
+    ("meta", Key::KEY_LEFTMETA),
+    ("win", Key::KEY_LEFTMETA),
+    ("rightctrl", Key::KEY_RIGHTCTRL),
+    ("rightcontrol", Key::KEY_RIGHTCTRL),
+    ("rightsuper", Key::KEY_RIGHTMETA),
+    ("rightwin", Key::KEY_RIGHTMETA),
+    ("altgr", Key::KEY_RIGHTALT),
+    ("escape", Key::KEY_ESC),
+    ("return", Key::KEY_ENTER),
+    ("del", Key::KEY_DELETE),
+    ("ins", Key::KEY_INSERT),
+    ("pgup", Key::KEY_PAGEUP),
+    ("pgdn", Key::KEY_PAGEDOWN),
+    ("printscreen", Key::KEY_SYSRQ),
+    ("prtsc", Key::KEY_SYSRQ),
+    ("plus", Key::KEY_KPPLUS),
+    ("mouseleft", Key::BTN_LEFT),
+    ("mouseright", Key::BTN_RIGHT),
+    ("mousemiddle", Key::BTN_MIDDLE),
+    ("mouse1", Key::BTN_LEFT),
+    ("mouse2", Key::BTN_RIGHT),
+    ("mouse3", Key::BTN_MIDDLE),
+    ("mouse4", Key::BTN_SIDE),
+    ("mouse5", Key::BTN_EXTRA),
+    ("mouseside", Key::BTN_SIDE),
+    ("mouseextra", Key::BTN_EXTRA),
+    ("mouseforward", Key::BTN_FORWARD),
+    ("mouseback", Key::BTN_BACK),
+];
+
+/// Resolves a single key name such as `F9`, `RightAlt`, `kp5`, `mouse4` or
+/// `KEY_LEFTMETA`. Names are case-insensitive.
+pub fn parse_key_name(name: &str) -> Result<Key> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("empty key name");
+    }
+
+    let lower = name.to_ascii_lowercase();
+    if let Some((_, key)) = KEY_ALIASES.iter().find(|(alias, _)| *alias == lower) {
+        return Ok(*key);
+    }
+
+    // numpad5 / num5 -> KP5
+    if let Some(rest) = lower.strip_prefix("numpad").or_else(|| lower.strip_prefix("num")) {
+        if let Ok(key) = format!("KEY_KP{}", rest.to_ascii_uppercase()).parse::<Key>() {
+            return Ok(key);
+        }
+    }
+
+    let upper = lower.to_ascii_uppercase();
+    if upper.starts_with("KEY_") || upper.starts_with("BTN_") {
+        if let Ok(key) = upper.parse::<Key>() {
+            return Ok(key);
+        }
+    }
+    format!("KEY_{upper}")
+        .parse::<Key>()
+        .map_err(|_| anyhow!("unknown key name '{}' (run with --list-keys to see valid names)", name))
+}
+
+/// Every key name `parse_key_name` accepts, aliases first.
+pub fn key_names() -> Vec<String> {
+    let mut names: Vec<String> = KEY_ALIASES
+        .iter()
+        .map(|(alias, key)| format!("{alias} ({key:?})"))
+        .collect();
+    for code in 0..0x300u16 {
+        let name = format!("{:?}", Key::new(code));
+        if name.starts_with("unknown") {
+            continue;
+        }
+        names.push(
+            name.strip_prefix("KEY_")
+                .map(str::to_ascii_lowercase)
+                .unwrap_or_else(|| name.to_ascii_lowercase()),
+        );
+    }
+    names
+}
+
+/// A parsed chord like `Ctrl+Alt or RightAlt+F9`. Each `+`-separated part is
+/// a group of alternatives joined by `or` (any case) or `|`; every modifier
+/// group must be held when one of the final group's keys is pressed. As `+`
+/// separates keys, the plus key is written `plus` (numpad) or `equal`.
+#[derive(Debug, Clone)]
+pub struct Chord {
+    text: String,
+    pub modifiers: Vec<Vec<Key>>,
+    pub keys: Vec<Key>,
+}
+
+impl Chord {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// All keys that act as modifiers for this chord.
+    pub fn modifier_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.modifiers.iter().flatten().copied()
+    }
+
+    pub fn matches(&self, key: Key, pressed_modifiers: &HashSet<Key>) -> bool {
+        self.keys.contains(&key)
+            && self
+                .modifiers
+                .iter()
+                .all(|group| group.iter().any(|m| pressed_modifiers.contains(m)))
+    }
+}
+
+impl FromStr for Chord {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut groups = Vec::new();
+        for part in value.split('+') {
+            let group = alternatives(part)
+                .and_then(|names| names.into_iter().map(parse_key_name).collect::<Result<Vec<_>>>())
+                .with_context(|| format!("in hotkey '{}'", value))?;
+            groups.push(group);
+        }
+
+        let keys = groups
+            .pop()
+            .filter(|keys| !keys.is_empty())
+            .ok_or_else(|| anyhow!("hotkey '{}' has no key", value))?;
+
+        Ok(Self {
+            text: value.trim().to_string(),
+            modifiers: groups,
+            keys,
+        })
+    }
+}
+
+/// Splits one chord part like `Alt or RightAlt` or `Alt|RightAlt` into its
+/// key names.
+fn alternatives(part: &str) -> Result<Vec<&str>> {
+    let mut names = Vec::new();
+    for alternative in part.split('|') {
+        let mut expect_name = true;
+        for word in alternative.split_whitespace() {
+            if word.eq_ignore_ascii_case("or") {
+                if expect_name {
+                    bail!("'{}' is missing a key before '{}'", part.trim(), word);
+                }
+                expect_name = true;
+            } else {
+                if !expect_name {
+                    bail!("'{}' needs 'or' between its keys", part.trim());
+                }
+                names.push(word);
+                expect_name = false;
+            }
+        }
+        if expect_name {
+            bail!("empty key name (write the plus key as 'plus' or 'equal', '+' separates keys)");
+        }
+    }
+    Ok(names)
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    ShowOverlay,
+    ToggleReplay,
+    /// Start or stop a full-length recording.
+    ToggleRecording,
+    /// Save the last N seconds, or the whole buffer when `None`.
+    SaveRecent(Option<u32>),
+    Bookmark,
+}
+
+impl FromStr for HotkeyAction {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "show-overlay" | "overlay" => Ok(HotkeyAction::ShowOverlay),
+            "toggle-replay" | "toggle" => Ok(HotkeyAction::ToggleReplay),
+            "toggle-recording" | "record" => Ok(HotkeyAction::ToggleRecording),
+            "save-full" | "save" => Ok(HotkeyAction::SaveRecent(None)),
+            "bookmark" => Ok(HotkeyAction::Bookmark),
+            other => {
+                let duration = other
+                    .strip_prefix("save-")
+                    .ok_or_else(|| anyhow!(
+                        "unknown hotkey action '{}', expected show-overlay, toggle-replay, toggle-recording, save-full, save-<duration> or bookmark",
+                        other
+                    ))?;
+                let secs = parse_duration(duration)
+                    .with_context(|| format!("invalid save duration in hotkey action '{}'", other))?;
+                Ok(HotkeyAction::SaveRecent(Some(secs)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for HotkeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HotkeyAction::ShowOverlay => write!(f, "show-overlay"),
+            HotkeyAction::ToggleReplay => write!(f, "toggle-replay"),
+            HotkeyAction::ToggleRecording => write!(f, "toggle-recording"),
+            HotkeyAction::SaveRecent(None) => write!(f, "save-full"),
+            HotkeyAction::SaveRecent(Some(secs)) => write!(f, "save-{secs}"),
+            HotkeyAction::Bookmark => write!(f, "bookmark"),
+        }
+    }
+}
+
+/// A chord (e.g. "Alt+F9") paired with the action it triggers.
+#[derive(Debug, Clone)]
+pub struct HotkeyBinding {
+    pub chord: Chord,
+    pub action: HotkeyAction,
+}
+
+impl FromStr for HotkeyBinding {
+    type Err = anyhow::Error;
+
+    /// Parses bindings of the form `CHORD=ACTION`, e.g. `Alt+F9=save-30`.
+    fn from_str(value: &str) -> Result<Self> {
+        let (chord, action) = value
+            .rsplit_once('=')
+            .ok_or_else(|| anyhow!("hotkey binding '{}' must look like CHORD=ACTION", value))?;
+        if chord.trim().is_empty() {
+            bail!("hotkey binding '{}' is missing a chord", value);
+        }
+        Ok(Self {
+            chord: chord.parse()?,
+            action: action.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(value: &str) -> Chord {
+        value.parse().unwrap()
+    }
+
+    fn error(value: &str) -> String {
+        format!("{:#}", value.parse::<Chord>().unwrap_err())
+    }
+
+    #[test]
+    fn parses_aliases_and_constant_names() {
+        let parsed = chord("Ctrl+Shift+Super+F9");
+        assert_eq!(
+            parsed.modifiers,
+            [vec![Key::KEY_LEFTCTRL], vec![Key::KEY_LEFTSHIFT], vec![Key::KEY_LEFTMETA]]
+        );
+        assert_eq!(parsed.keys, [Key::KEY_F9]);
+        assert_eq!(chord("KEY_LEFTALT+key_f12").modifiers, [vec![Key::KEY_LEFTALT]]);
+        assert_eq!(chord("escape").keys, [Key::KEY_ESC]);
+        assert_eq!(chord("Alt+F24").keys, [Key::KEY_F24]);
+    }
+
+    #[test]
+    fn parses_right_hand_modifiers() {
+        let parsed = chord("RightCtrl+AltGr+RightShift+RightWin+A");
+        assert_eq!(
+            parsed.modifier_keys().collect::<Vec<_>>(),
+            [Key::KEY_RIGHTCTRL, Key::KEY_RIGHTALT, Key::KEY_RIGHTSHIFT, Key::KEY_RIGHTMETA]
+        );
+    }
+
+    #[test]
+    fn parses_numpad_and_mouse_buttons() {
+        assert_eq!(chord("numpad5").keys, [Key::KEY_KP5]);
+        assert_eq!(chord("Num0").keys, [Key::KEY_KP0]);
+        assert_eq!(chord("Ctrl+plus").keys, [Key::KEY_KPPLUS]);
+        assert_eq!(chord("NumpadPlus").keys, [Key::KEY_KPPLUS]);
+        assert_eq!(chord("mouse4").keys, [Key::BTN_SIDE]);
+        assert_eq!(chord("Alt+Mouse5").keys, [Key::BTN_EXTRA]);
+        assert_eq!(chord("MouseForward").keys, [Key::BTN_FORWARD]);
+        assert_eq!(chord("btn_back").keys, [Key::BTN_BACK]);
+    }
+
+    #[test]
+    fn parses_alternatives() {
+        for value in ["Alt or RightAlt+F9", "Alt OR RightAlt+F9", "Alt|RightAlt+F9", "Alt | RightAlt+F9"] {
+            let parsed = chord(value);
+            assert_eq!(parsed.modifiers, [[Key::KEY_LEFTALT, Key::KEY_RIGHTALT]], "{value}");
+            assert_eq!(parsed.keys, [Key::KEY_F9], "{value}");
+        }
+        assert_eq!(chord("Alt+F9 or F10|mouse4").keys, [Key::KEY_F9, Key::KEY_F10, Key::BTN_SIDE]);
+    }
+
+    #[test]
+    fn matches_any_alternative() {
+        let parsed = chord("Alt or RightAlt+F9");
+        let held = HashSet::from([Key::KEY_RIGHTALT]);
+        assert!(parsed.matches(Key::KEY_F9, &held));
+        assert!(!parsed.matches(Key::KEY_F10, &held));
+        assert!(!parsed.matches(Key::KEY_F9, &HashSet::new()));
+    }
+
+    #[test]
+    fn reports_bad_chords() {
+        assert!(error("Alt+Fnord").contains("unknown key name 'Fnord'"));
+        assert!(error("Alt+Fnord").contains("in hotkey 'Alt+Fnord'"));
+        assert!(error("Ctrl+").contains("empty key name"));
+        assert!(error("Ctrl++").contains("'plus'"));
+        assert!(error("").contains("empty key name"));
+        assert!(error("Alt RightAlt+F9").contains("needs 'or'"));
+        assert!(error("or Alt+F9").contains("missing a key"));
+        assert!(error("Alt|+F9").contains("empty key name"));
+    }
+
+    #[test]
+    fn parses_save_durations() {
+        let action = |value: &str| value.parse::<HotkeyAction>().unwrap();
+        assert_eq!(action("save-30"), HotkeyAction::SaveRecent(Some(30)));
+        assert_eq!(action("save-45s"), HotkeyAction::SaveRecent(Some(45)));
+        assert_eq!(action("Save-2m"), HotkeyAction::SaveRecent(Some(120)));
+        assert_eq!(action("save-1m30s"), HotkeyAction::SaveRecent(Some(90)));
+        assert_eq!(action("save"), HotkeyAction::SaveRecent(None));
+        assert!("save-0".parse::<HotkeyAction>().is_err());
+        assert!("save-2x".parse::<HotkeyAction>().is_err());
+    }
+}
@@ -1,11 +1,25 @@
+pub mod audio;
 pub mod config;
-pub mod constants;
+pub mod ducking;
+pub mod export;
 pub mod ffmpeg;
+pub mod game_watch;
+pub mod games;
+pub mod hotkeys;
+pub mod loudness;
+pub mod metadata;
 pub mod overlay;
 pub mod process;
 pub mod progress;
+pub mod recorder;
 pub mod upload;
+pub mod vdf;
+pub mod voice;
+pub mod sessions;
 pub mod settings;
+pub mod smartcut;
+pub mod steam;
+pub mod storage;
 pub mod failed_uploads;
 
 pub mod capture;
@@ -0,0 +1,176 @@
+use anyhow::{ensure, Context, Result};
+use serde::Deserialize;
+
+/// YouTube's playback reference level.
+pub const DEFAULT_TARGET_LUFS: f64 = -14.0;
+const TRUE_PEAK_DBTP: f64 = -1.0;
+const LOUDNESS_RANGE: f64 = 11.0;
+/// The largest range every supported ffmpeg accepts.
+const MAX_LOUDNESS_RANGE: f64 = 20.0;
+/// Peak ceiling of the limiter at the end of every mix.
+pub const DEFAULT_LIMITER_DB: f64 = TRUE_PEAK_DBTP;
+
+/// Settings for the optional EBU R128 normalisation pass.
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessConfig {
+    pub target_lufs: f64,
+    pub limiter_db: f64,
+    /// Whether the picker starts with normalisation ticked.
+    pub enabled_by_default: bool,
+}
+
+impl LoudnessConfig {
+    pub fn new(target_lufs: f64, limiter_db: f64, enabled_by_default: bool) -> Result<Self> {
+        ensure!(
+            (-70.0..=-5.0).contains(&target_lufs),
+            "loudness target must be between -70 and -5 LUFS (got {target_lufs})"
+        );
+        ensure!(
+            (-20.0..=0.0).contains(&limiter_db),
+            "limiter ceiling must be between -20 and 0 dBFS (got {limiter_db})"
+        );
+        Ok(Self {
+            target_lufs,
+            limiter_db,
+            enabled_by_default,
+        })
+    }
+
+    /// The limiter ceiling as the linear level `alimiter` takes.
+    pub fn limiter_linear(&self) -> f64 {
+        10f64.powf(self.limiter_db / 20.0)
+    }
+
+    /// First pass: measures the mix and prints the result as JSON.
+    pub fn analysis_filter(&self) -> String {
+        format!(
+            "loudnorm=I={}:TP={TRUE_PEAK_DBTP}:LRA={LOUDNESS_RANGE}:print_format=json",
+            self.target_lufs
+        )
+    }
+
+    /// Second pass: a single gain change to the target using the measurement.
+    pub fn normalize_filter(&self, measured: &LoudnessMeasurement) -> String {
+        // loudnorm falls back to dynamic compression unless the target range
+        // covers the measured one
+        let range = measured.input_lra.clamp(LOUDNESS_RANGE, MAX_LOUDNESS_RANGE);
+        format!(
+            "loudnorm=I={}:TP={TRUE_PEAK_DBTP}:LRA={range}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=summary",
+            self.target_lufs,
+            measured.input_i,
+            measured.input_tp,
+            measured.input_lra,
+            measured.input_thresh,
+            measured.target_offset,
+        )
+    }
+}
+
+/// What `loudnorm` measured in the analysis pass.
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessMeasurement {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+impl LoudnessMeasurement {
+    /// Silence measures as `-inf` and can't be normalised.
+    pub fn is_silent(&self) -> bool {
+        !self.input_i.is_finite() || !self.input_thresh.is_finite()
+    }
+}
+
+/// Pulls the JSON block `loudnorm` prints at the end of ffmpeg's stderr.
+pub fn parse_measurement(stderr: &str) -> Result<LoudnessMeasurement> {
+    #[derive(Deserialize)]
+    struct Raw {
+        input_i: String,
+        input_tp: String,
+        input_lra: String,
+        input_thresh: String,
+        target_offset: String,
+    }
+
+    let start = stderr
+        .rfind('{')
+        .context("loudnorm printed no measurement")?;
+    let end = stderr[start..]
+        .find('}')
+        .map(|offset| start + offset + 1)
+        .context("loudnorm measurement is truncated")?;
+    let raw: Raw = serde_json::from_str(&stderr[start..end])
+        .context("failed to parse loudnorm measurement")?;
+
+    let field = |name: &str, value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .with_context(|| format!("invalid loudnorm {name} '{value}'"))
+    };
+    Ok(LoudnessMeasurement {
+        input_i: field("input_i", &raw.input_i)?,
+        input_tp: field("input_tp", &raw.input_tp)?,
+        input_lra: field("input_lra", &raw.input_lra)?,
+        input_thresh: field("input_thresh", &raw.input_thresh)?,
+        target_offset: field("target_offset", &raw.target_offset)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = r#"size=N/A time=00:00:29.98 bitrate=N/A speed= 412x
+[Parsed_loudnorm_0 @ 0x55d4c8a0e2c0]
+{
+	"input_i" : "-23.54",
+	"input_tp" : "-4.12",
+	"input_lra" : "6.30",
+	"input_thresh" : "-34.01",
+	"output_i" : "-14.38",
+	"output_tp" : "-1.00",
+	"output_lra" : "5.10",
+	"output_thresh" : "-24.82",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.38"
+}
+"#;
+
+    #[test]
+    fn parses_the_loudnorm_summary() {
+        let measured = parse_measurement(STDERR).unwrap();
+        assert_eq!(measured.input_i, -23.54);
+        assert_eq!(measured.input_tp, -4.12);
+        assert_eq!(measured.input_lra, 6.3);
+        assert_eq!(measured.input_thresh, -34.01);
+        assert_eq!(measured.target_offset, 0.38);
+        assert!(!measured.is_silent());
+
+        let filter = LoudnessConfig::new(-14.0, DEFAULT_LIMITER_DB, true).unwrap().normalize_filter(&measured);
+        // The measured range is below the default, which is used instead
+        assert!(filter.contains(":LRA=11:measured_I=-23.54:"), "{filter}");
+    }
+
+    #[test]
+    fn silence_measures_as_infinite() {
+        let stderr = STDERR
+            .replace(r#""input_i" : "-23.54""#, r#""input_i" : "-inf""#)
+            .replace(r#""input_thresh" : "-34.01""#, r#""input_thresh" : "-inf""#);
+        assert!(parse_measurement(&stderr).unwrap().is_silent());
+    }
+
+    #[test]
+    fn reports_missing_or_broken_measurements() {
+        let error = |stderr: &str| format!("{:#}", parse_measurement(stderr).unwrap_err());
+        assert!(error("Conversion failed!").contains("printed no measurement"));
+        assert!(error("{\n\t\"input_i\" : \"-23.54\",").contains("truncated"));
+        assert!(error(&STDERR.replace("\"-4.12\"", "\"loud\"")).contains("invalid loudnorm input_tp 'loud'"));
+        assert!(LoudnessConfig::new(-3.0, DEFAULT_LIMITER_DB, false).is_err());
+        assert!(LoudnessConfig::new(-14.0, 1.0, false).is_err());
+        let config = LoudnessConfig::new(-14.0, -6.0, false).unwrap();
+        assert!((config.limiter_linear() - 0.501).abs() < 0.001);
+    }
+}
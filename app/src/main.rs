@@ -1,20 +1,31 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc};
 
 use anyhow::{Context, Result};
 use clap::Parser;
+use tokio::sync::{mpsc, watch};
 use tokio::task::JoinHandle;
 
 use evdev::{Device, InputEventKind, Key};
-use clips_app::capture::{ReplayController, ReplaySettings};
+use clips_app::audio::{AudioExport, TrackMap};
+use clips_app::export::ExportPresets;
+use clips_app::capture::{self, ReplayController, ReplaySettings};
 use clips_app::config::{AppConfig, AppMode, CaptureConfig, Cli};
-use clips_app::constants::CHANNEL_OPTIONS;
 use clips_app::ffmpeg;
+use clips_app::game_watch::{self, GameEvent};
+use clips_app::games::{self, DetectedGame};
+use clips_app::hotkeys::{self, HotkeyAction, HotkeyBinding};
+use clips_app::metadata::{self, ClipMetadata};
 use clips_app::overlay;
-use clips_app::overlay::{CaptureActionPayload, CaptureStatusPayload};
+use clips_app::overlay::{CaptureActionPayload, CaptureSettingsPayload, CaptureStatusPayload};
 use clips_app::process;
 use clips_app::progress::{format_stage_detail, Stage};
-use clips_app::settings::{PersistedSettings, ReplayMode};
+use clips_app::sessions::{format_local_time, SessionLog};
+use clips_app::settings::{
+    CaptureProfile, GameMixes, GameProfiles, PersistedSettings, ReplayMode, TrackLevel,
+};
+use clips_app::smartcut::{self, TrimMode};
 use clips_app::upload;
 
 fn main() -> Result<()> {
@@ -28,7 +39,31 @@ fn main() -> Result<()> {
             runtime.block_on(run_capture_mode(cfg))
         }
         AppMode::Process(config) => run_process_mode(config),
+        AppMode::ListKeys => {
+            for name in hotkeys::key_names() {
+                println!("{name}");
+            }
+            Ok(())
+        }
+        AppMode::ListSessions(count) => list_sessions(count),
+    }
+}
+
+fn list_sessions(count: usize) -> Result<()> {
+    let log = SessionLog::load()?;
+    for session in log.sessions.iter().rev().take(count) {
+        println!("{}  ({})", session.display_name(), session.id);
+        for bookmark in &session.bookmarks {
+            println!("    bookmark at {}", format_local_time(*bookmark));
+        }
+        for clip in &session.clips {
+            match &clip.processed {
+                Some(processed) => println!("    {} -> {}", clip.path.display(), processed.display()),
+                None => println!("    {}", clip.path.display()),
+            }
+        }
     }
+    Ok(())
 }
 
 fn run_process_mode(config: AppConfig) -> Result<()> {
@@ -61,15 +96,19 @@ async fn run_capture_mode(cfg: CaptureConfig) -> Result<()> {
 
     // Load saved settings or use defaults from config
     let mut settings = ReplaySettings {
-        binary: cfg.gpu_screen_recorder.clone(),
         target: cfg.target.clone(),
         buffer_seconds: cfg.buffer_seconds,
         bitrate: cfg.bitrate,
         fps: cfg.fps,
         audio_tracks: cfg.audio_tracks.clone(),
+        track_map: TrackMap::load()?,
         restore_portal_session: cfg.restore_portal_session,
         replay_storage: cfg.replay_storage,
         output_dir: cfg.output_dir.clone(),
+        save_durations: cfg.save_durations.clone(),
+        save_timeout_secs: cfg.save_timeout_secs,
+        max_restarts: cfg.max_restarts,
+        storage: cfg.storage.clone(),
     };
 
     // Load replay mode and enabled state from persisted settings
@@ -93,7 +132,8 @@ async fn run_capture_mode(cfg: CaptureConfig) -> Result<()> {
         .unwrap_or_default();
     eprintln!("[CLIPS_APP] Loaded {} failed uploads", failed_uploads_list.uploads.len());
 
-    let mut controller = ReplayController::new(settings);
+    let mut controller = ReplayController::new(settings, cfg.recorder.build());
+    enforce_storage_policy(&mut controller, &failed_uploads_list);
 
     // In manual mode, start based on persisted/config state
     // In auto mode, we'll handle it in the game detection loop
@@ -109,26 +149,39 @@ async fn run_capture_mode(cfg: CaptureConfig) -> Result<()> {
 
     let overlay_for_hotkey = overlay_handle.clone();
     let visible_for_hotkey = visible.clone();
-    let hotkey = cfg.hotkey.clone();
+    let (hotkey_tx, mut hotkey_rx) = mpsc::unbounded_channel::<HotkeyAction>();
     
-    // BUG FIX: Returns a Vec of handles now
-    let hotkey_tasks = spawn_hotkey_listener(hotkey.clone(), overlay_for_hotkey, visible_for_hotkey).await;
-
-    if hotkey_tasks.is_empty() {
+    let (hotkey_listener, mut hotkey_devices) = spawn_hotkey_listener(
+        cfg.hotkey_bindings.clone(),
+        overlay_for_hotkey,
+        visible_for_hotkey,
+        hotkey_tx,
+    ).await;
+
+    if *hotkey_devices.borrow() == 0 {
         eprintln!("[CLIPS_APP] Global shortcut portal unavailable/no keyboards found, showing overlay by default");
         set_overlay_visible(&overlay_handle, &visible, true)?;
     }
 
-    // Game detection is handled inside the capture loop
+    let (game_tx, mut game_rx) = mpsc::unbounded_channel::<GameEvent>();
+    if let Err(err) = game_watch::spawn_game_watcher(game_tx) {
+        eprintln!("[CLIPS_APP] Failed to start game watcher: {err:#}");
+    }
+    let mut current_game: Option<DetectedGame> = None;
+    // Outlives each capture loop so a game that exits while a clip is in
+    // the picker still stops the replay and reverts its profile
+    let mut game_was_running = false;
+
+    // Game events are acted on inside the capture loop
     loop {
         // Ensure overlay reflects latest status when opened
         let mode = *replay_mode.lock().unwrap();
-        let status = build_capture_status(&controller.status()?, &cfg.hotkey, &failed_uploads_list, mode);
+        let status = build_capture_status(&controller.status()?, &cfg.hotkey, &failed_uploads_list, mode, *hotkey_devices.borrow());
         let session = overlay_handle
             .show_capture(status)
             .context("failed to show capture panel")?;
 
-        let outcome = run_capture_loop(&cfg, &mut controller, session.clone(), &overlay_handle, &visible, &mut failed_uploads_list, &replay_mode).await?;
+        let outcome = run_capture_loop(&cfg, &mut controller, session.clone(), &overlay_handle, &visible, &mut failed_uploads_list, &replay_mode, &mut hotkey_rx, &mut hotkey_devices, &mut game_rx, &mut current_game, &mut game_was_running).await?;
 
         match outcome {
             CaptureLoopOutcome::Saved(path) => {
@@ -139,10 +192,48 @@ async fn run_capture_mode(cfg: CaptureConfig) -> Result<()> {
                     cfg.youtube_uploader.clone(),
                     cfg.secrets_path.clone(),
                     cfg.overlay_bin.clone(),
+                    cfg.loudness,
+                    cfg.voice.clone(),
+                    cfg.ducking,
+                    cfg.trim_mode,
                 )?;
                 app_config.ensure_dirs()?;
                 set_overlay_visible(&overlay_handle, &visible, true)?;
-                match process_clip(&app_config, &overlay_handle, &mut failed_uploads_list).await {
+                // Processing runs on its own task, blocking on the overlay, so the
+                // recorder keeps being supervised while the user is busy with it
+                let processing_overlay = overlay_handle.clone();
+                let mut processing_uploads = std::mem::take(&mut failed_uploads_list);
+                let mut processing = tokio::spawn(async move {
+                    let result = process_clip(&app_config, &processing_overlay, &mut processing_uploads).await;
+                    (result, processing_uploads)
+                });
+                let mut supervisor_interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+                supervisor_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                let result = loop {
+                    tokio::select! {
+                        joined = &mut processing => {
+                            let (result, uploads) = joined.context("clip processing task failed")?;
+                            failed_uploads_list = uploads;
+                            break result;
+                        }
+                        _ = supervisor_interval.tick() => {
+                            if let Err(err) = controller.supervise().await {
+                                eprintln!("[CLIPS_APP] Failed to supervise replay recorder: {err:#}");
+                            }
+                        }
+                        Some(action) = hotkey_rx.recv() => {
+                            // The recorder is still live, so saves and bookmarks
+                            // work as usual; a finished recording waits its turn
+                            let mode = *replay_mode.lock().unwrap();
+                            match handle_hotkey_action(&mut controller, action, mode).await {
+                                Ok(Some(path)) => controller.queue_finished_recording(path),
+                                Ok(None) => {}
+                                Err(err) => eprintln!("[CLIPS_APP] Hotkey action failed: {err:#}"),
+                            }
+                        }
+                    }
+                };
+                match result {
                     Ok(_) => {},
                     Err(err) => {
                         // Show error for 5 seconds before returning to capture view
@@ -156,6 +247,7 @@ async fn run_capture_mode(cfg: CaptureConfig) -> Result<()> {
                 set_overlay_visible(&overlay_handle, &visible, false)?;
                 controller.clear_last_saved();
                 controller.set_message("Replay recorder ready");
+                enforce_storage_policy(&mut controller, &failed_uploads_list);
             }
             CaptureLoopOutcome::Exit => {
                 break;
@@ -164,12 +256,15 @@ async fn run_capture_mode(cfg: CaptureConfig) -> Result<()> {
 
         // After processing, refresh capture view status before waiting for next loop
         let mode = *replay_mode.lock().unwrap();
-        overlay_handle.send_capture_status(build_capture_status(&controller.status()?, &cfg.hotkey, &failed_uploads_list, mode))?;
+        overlay_handle.send_capture_status(build_capture_status(&controller.status()?, &cfg.hotkey, &failed_uploads_list, mode, *hotkey_devices.borrow()))?;
     }
 
     // BUG FIX: Clean up all hotkey listener threads
-    for handle in hotkey_tasks {
-        handle.abort();
+    hotkey_listener.shutdown();
+
+    if let Some(game) = current_game.take() {
+        // Close the session now rather than leaving it unfinished
+        apply_game_event(&mut controller, &mut None, GameEvent::Exited(game, std::time::SystemTime::now()));
     }
 
     if let Err(err) = set_overlay_visible(&overlay_handle, &visible, false) {
@@ -185,6 +280,7 @@ enum CaptureLoopOutcome {
     Exit,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_capture_loop(
     cfg: &CaptureConfig,
     controller: &mut ReplayController,
@@ -193,6 +289,11 @@ async fn run_capture_loop(
     visible: &std::sync::Arc<std::sync::atomic::AtomicBool>,
     failed_uploads_list: &mut clips_app::failed_uploads::FailedUploadsList,
     replay_mode: &Arc<std::sync::Mutex<ReplayMode>>,
+    hotkey_rx: &mut mpsc::UnboundedReceiver<HotkeyAction>,
+    hotkey_devices: &mut watch::Receiver<usize>,
+    game_rx: &mut mpsc::UnboundedReceiver<GameEvent>,
+    current_game: &mut Option<DetectedGame>,
+    game_was_running: &mut bool,
 ) -> Result<CaptureLoopOutcome> {
     fn spawn_action_task(
         session: overlay::CaptureSession,
@@ -200,21 +301,31 @@ async fn run_capture_loop(
         tokio::task::spawn_blocking(move || session.wait_for_action())
     }
 
+    // Game events still matter: catch up on whatever started or exited
+    while let Ok(event) = game_rx.try_recv() {
+        apply_game_event(controller, current_game, event);
+    }
+
     let mut action_task = spawn_action_task(session.clone());
 
-    let mut detection_interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
-    detection_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-    let mut game_was_running = false;
+    let mut supervisor_interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+    supervisor_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let mut storage_interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+    storage_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // The first tick fires immediately; usage was just refreshed by the caller
+    storage_interval.tick().await;
 
     {
         let mode = *replay_mode.lock().unwrap();
         if mode == ReplayMode::AutoWithGame
-            && maybe_handle_game_detection(controller, &mut game_was_running).await? {
+            && maybe_handle_game_detection(controller, current_game.as_ref(), game_was_running).await {
                 let status = build_capture_status(
                     &controller.status()?,
                     &cfg.hotkey,
                     failed_uploads_list,
                     mode,
+                    *hotkey_devices.borrow(),
                 );
                 session
                     .update_status(status)
@@ -225,6 +336,14 @@ async fn run_capture_loop(
     let mut outcome: Option<CaptureLoopOutcome> = None;
 
     while outcome.is_none() {
+        // Settings changes, profile switches and game exits restart or stop
+        // the recorder, which finishes any recording in progress
+        if let Some(path) = controller.take_finished_recording() {
+            set_overlay_visible(overlay_handle, visible, true)?;
+            outcome = Some(CaptureLoopOutcome::Saved(path));
+            break;
+        }
+
         tokio::select! {
             action = &mut action_task => {
                 let action = action.context("failed to join capture action task")??;
@@ -237,36 +356,7 @@ async fn run_capture_loop(
                         match payload {
                             CaptureActionPayload::Toggle { enable } => {
                                 let mode = *replay_mode.lock().unwrap();
-                                if mode == ReplayMode::Manual {
-                                    if enable {
-                                        match controller.ensure_running().await {
-                                            Ok(_) => controller.set_message("Replay recorder started"),
-                                            Err(err) => {
-                                                eprintln!("[CLIPS_APP] Failed to enable replay: {err:#}");
-                                                controller.set_message(format!("Failed to enable replay: {err:#}"));
-                                            }
-                                        }
-                                    } else {
-                                        match controller.stop().await {
-                                            Ok(_) => controller.set_message("Replay recorder stopped"),
-                                            Err(err) => {
-                                                eprintln!("[CLIPS_APP] Failed to stop replay: {err:#}");
-                                                controller.set_message(format!("Failed to stop replay: {err:#}"));
-                                            }
-                                        }
-                                    }
-
-                                    let persisted = PersistedSettings::from_replay_settings(
-                                        controller.settings(),
-                                        mode,
-                                        enable,
-                                    );
-                                    if let Err(err) = persisted.save() {
-                                        eprintln!("[CLIPS_APP] Failed to save settings: {err:#}");
-                                    }
-                                } else {
-                                    controller.set_message("Toggle disabled in auto mode");
-                                }
+                                set_replay_enabled(controller, mode, enable).await;
                             }
                             CaptureActionPayload::Save { duration_secs } => {
                                 controller.set_message("Saving replay clip...");
@@ -276,6 +366,7 @@ async fn run_capture_loop(
                                     &cfg.hotkey,
                                     failed_uploads_list,
                                     mode,
+                                    *hotkey_devices.borrow(),
                                 );
                                 status.is_saving = true;
                                 session
@@ -294,6 +385,7 @@ async fn run_capture_loop(
                                     &cfg.hotkey,
                                     failed_uploads_list,
                                     mode,
+                                    *hotkey_devices.borrow(),
                                 );
                                 status.is_saving = false;
 
@@ -320,6 +412,12 @@ async fn run_capture_loop(
                                     }
                                 }
                             }
+                            CaptureActionPayload::Record { enable } => {
+                                if let Some(path) = set_recording(controller, enable).await {
+                                    set_overlay_visible(overlay_handle, visible, true)?;
+                                    outcome = Some(CaptureLoopOutcome::Saved(path));
+                                }
+                            }
                             CaptureActionPayload::UpdateSettings { settings } => {
                                 let mut new_settings = controller.settings().clone();
                                 if !settings.target.trim().is_empty() {
@@ -331,15 +429,37 @@ async fn run_capture_loop(
                                 if !settings.audio_tracks.is_empty() {
                                     new_settings.audio_tracks = settings.audio_tracks.clone();
                                 }
+                                let mut durations_error = None;
+                                if !settings.save_durations.trim().is_empty() {
+                                    match settings
+                                        .save_durations
+                                        .split(',')
+                                        .map(capture::parse_duration)
+                                        .collect::<Result<Vec<_>>>()
+                                    {
+                                        Ok(durations) => new_settings.save_durations = durations,
+                                        Err(err) => durations_error = Some(err),
+                                    }
+                                }
 
                                 if let Err(err) = controller.apply_settings(new_settings.clone()).await {
                                     controller.set_message(format!("Apply failed: {err:#}"));
                                 } else {
-                                    controller.set_message("Settings updated");
+                                    match (durations_error, controller.active_profile()) {
+                                        (Some(err), _) => controller.set_message(format!("Settings updated, save durations unchanged: {err:#}")),
+                                        (None, Some(game)) => controller.set_message(format!(
+                                            "Settings updated until {game} exits (use Save as profile to keep them)"
+                                        )),
+                                        (None, None) => controller.set_message("Settings updated"),
+                                    }
+                                    // While a profile is active only the save durations
+                                    // carry over into the persisted base settings
+                                    let mut base_settings = controller.base_settings().clone();
+                                    base_settings.save_durations = new_settings.save_durations.clone();
                                     let mode = *replay_mode.lock().unwrap();
                                     let is_running = controller.status().map(|s| s.running).unwrap_or(false);
                                     let persisted = PersistedSettings::from_replay_settings(
-                                        &new_settings,
+                                        &base_settings,
                                         mode,
                                         is_running,
                                     );
@@ -370,20 +490,21 @@ async fn run_capture_loop(
                                             } else {
                                                 controller.set_message("Manual mode enabled");
                                             }
-                                            game_was_running = false;
+                                            if let Err(err) = controller.revert_profile().await {
+                                                eprintln!("[CLIPS_APP] Failed to revert capture profile: {err:#}");
+                                            }
+                                            *game_was_running = false;
                                         }
                                         ReplayMode::AutoWithGame => {
                                             controller.set_message("Auto mode enabled");
-                                            game_was_running = false;
-                                            if maybe_handle_game_detection(controller, &mut game_was_running).await? {
-                                                // helper already adjusted state
-                                            }
+                                            *game_was_running = false;
+                                            maybe_handle_game_detection(controller, current_game.as_ref(), game_was_running).await;
                                         }
                                     }
 
                                     let is_running = controller.status().map(|s| s.running).unwrap_or(false);
                                     let persisted = PersistedSettings::from_replay_settings(
-                                        controller.settings(),
+                                        controller.base_settings(),
                                         new_mode,
                                         is_running,
                                     );
@@ -392,6 +513,12 @@ async fn run_capture_loop(
                                     }
                                 }
                             }
+                            CaptureActionPayload::SaveProfile { game, settings } => {
+                                save_game_profile(controller, &game, &settings).await;
+                            }
+                            CaptureActionPayload::DeleteProfile { game } => {
+                                delete_game_profile(controller, &game).await;
+                            }
                             CaptureActionPayload::FailedUpload { upload_action, id } => {
                                 // ... [Failed Upload Handling Logic - same as original] ...
                                 match upload_action.as_str() {
@@ -408,6 +535,10 @@ async fn run_capture_loop(
                                                     youtube_uploader: cfg.youtube_uploader.clone(),
                                                     secrets_path: cfg.secrets_path.clone(),
                                                     overlay_bin: cfg.overlay_bin.clone(),
+                                                    loudness: cfg.loudness,
+                                                    voice: cfg.voice.clone(),
+                                                    ducking: cfg.ducking,
+                                                    trim_mode: cfg.trim_mode,
                                                 },
                                                 &failed_upload.processed_path,
                                                 &failed_upload.title,
@@ -432,6 +563,10 @@ async fn run_capture_loop(
                                                             youtube_uploader: cfg.youtube_uploader.clone(),
                                                             secrets_path: cfg.secrets_path.clone(),
                                                             overlay_bin: cfg.overlay_bin.clone(),
+                                                            loudness: cfg.loudness,
+                                                            voice: cfg.voice.clone(),
+                                                            ducking: cfg.ducking,
+                                                            trim_mode: cfg.trim_mode,
                                                         },
                                                         &failed_upload.full_path,
                                                         &failed_upload.processed_path,
@@ -501,6 +636,7 @@ async fn run_capture_loop(
                                     &cfg.hotkey,
                                     failed_uploads_list,
                                     mode,
+                                    *hotkey_devices.borrow(),
                                 ))
                                 .context("failed to update capture status")?;
                         }
@@ -511,21 +647,78 @@ async fn run_capture_loop(
                     action_task = spawn_action_task(session.clone());
                 }
             }
-            _ = detection_interval.tick(), if outcome.is_none() => {
+            Some(action) = hotkey_rx.recv(), if outcome.is_none() => {
+                let mode = *replay_mode.lock().unwrap();
+                if let Some(path) = handle_hotkey_action(controller, action, mode).await? {
+                    set_overlay_visible(overlay_handle, visible, true)?;
+                    outcome = Some(CaptureLoopOutcome::Saved(path));
+                }
+                session
+                    .update_status(build_capture_status(
+                        &controller.status()?,
+                        &cfg.hotkey,
+                        failed_uploads_list,
+                        mode,
+                        *hotkey_devices.borrow(),
+                    ))
+                    .context("failed to update capture status")?;
+            }
+            Ok(()) = hotkey_devices.changed(), if outcome.is_none() => {
+                let mode = *replay_mode.lock().unwrap();
+                session
+                    .update_status(build_capture_status(
+                        &controller.status()?,
+                        &cfg.hotkey,
+                        failed_uploads_list,
+                        mode,
+                        *hotkey_devices.borrow(),
+                    ))
+                    .context("failed to update capture status")?;
+            }
+            _ = supervisor_interval.tick(), if outcome.is_none() => {
+                if controller.supervise().await? {
+                    let mode = *replay_mode.lock().unwrap();
+                    session
+                        .update_status(build_capture_status(
+                            &controller.status()?,
+                            &cfg.hotkey,
+                            failed_uploads_list,
+                            mode,
+                            *hotkey_devices.borrow(),
+                        ))
+                        .context("failed to update capture status")?;
+                }
+            }
+            _ = storage_interval.tick(), if outcome.is_none() => {
+                enforce_storage_policy(controller, failed_uploads_list);
+                let mode = *replay_mode.lock().unwrap();
+                session
+                    .update_status(build_capture_status(
+                        &controller.status()?,
+                        &cfg.hotkey,
+                        failed_uploads_list,
+                        mode,
+                        *hotkey_devices.borrow(),
+                    ))
+                    .context("failed to update capture status")?;
+            }
+            Some(event) = game_rx.recv(), if outcome.is_none() => {
+                apply_game_event(controller, current_game, event);
                 let mode = *replay_mode.lock().unwrap();
                 if mode == ReplayMode::AutoWithGame {
-                    if maybe_handle_game_detection(controller, &mut game_was_running).await? {
+                    if maybe_handle_game_detection(controller, current_game.as_ref(), game_was_running).await {
                         session
                             .update_status(build_capture_status(
                                 &controller.status()?,
                                 &cfg.hotkey,
                                 failed_uploads_list,
                                 mode,
+                                *hotkey_devices.borrow(),
                             ))
                             .context("failed to update capture status")?;
                     }
-                } else if game_was_running {
-                    game_was_running = false;
+                } else if *game_was_running {
+                    *game_was_running = false;
                 }
             }
         }
@@ -539,57 +732,290 @@ async fn run_capture_loop(
     Ok(outcome.expect("capture loop must produce an outcome"))
 }
 
-async fn maybe_handle_game_detection(
+async fn set_replay_enabled(controller: &mut ReplayController, mode: ReplayMode, enable: bool) {
+    if mode != ReplayMode::Manual {
+        controller.set_message("Toggle disabled in auto mode");
+        return;
+    }
+
+    if enable {
+        match controller.ensure_running().await {
+            Ok(_) => controller.set_message("Replay recorder started"),
+            Err(err) => {
+                eprintln!("[CLIPS_APP] Failed to enable replay: {err:#}");
+                controller.set_message(format!("Failed to enable replay: {err:#}"));
+            }
+        }
+    } else {
+        match controller.stop().await {
+            Ok(_) => controller.set_message("Replay recorder stopped"),
+            Err(err) => {
+                eprintln!("[CLIPS_APP] Failed to stop replay: {err:#}");
+                controller.set_message(format!("Failed to stop replay: {err:#}"));
+            }
+        }
+    }
+
+    let persisted = PersistedSettings::from_replay_settings(
+        controller.base_settings(),
+        mode,
+        enable,
+    );
+    if let Err(err) = persisted.save() {
+        eprintln!("[CLIPS_APP] Failed to save settings: {err:#}");
+    }
+}
+
+fn enforce_storage_policy(
     controller: &mut ReplayController,
-    game_was_running: &mut bool,
-) -> Result<bool> {
-    match detect_steam_game() {
-        Ok(game_name) => {
-            let game_running = !game_name.is_empty();
-            eprintln!(
-                "[CLIPS_APP] Game detection check: '{}' (running: {})",
-                game_name, game_running
-            );
+    failed_uploads: &clips_app::failed_uploads::FailedUploadsList,
+) {
+    if let Err(err) = controller.enforce_storage_policy(&failed_uploads.paths()) {
+        eprintln!("[CLIPS_APP] Failed to apply storage policy: {err:#}");
+    }
+}
 
-            if game_running && !*game_was_running {
-                eprintln!("[CLIPS_APP] Game detected, ensuring replay is running");
-                match controller.ensure_running().await {
-                    Ok(_) => {
-                        let message = if game_name.is_empty() {
-                            "Replay started (game detected)".to_string()
-                        } else {
-                            format!("Replay started ({game_name})")
-                        };
-                        controller.set_message(message);
-                        *game_was_running = true;
-                    }
-                    Err(err) => {
-                        eprintln!("[CLIPS_APP] Failed to start replay: {err:#}");
-                        controller.set_message(format!("Failed to start replay: {err:#}"));
-                    }
+/// Starts or finishes a full-length recording. A finished recording is
+/// returned so it can go through the picker like a saved replay.
+async fn set_recording(controller: &mut ReplayController, enable: bool) -> Option<PathBuf> {
+    if enable {
+        if let Err(err) = controller.start_recording().await {
+            eprintln!("[CLIPS_APP] Failed to start recording: {err:#}");
+            controller.set_message(format!("Failed to start recording: {err:#}"));
+        }
+        return None;
+    }
+
+    match controller.stop_recording().await {
+        Ok(path) => Some(path),
+        Err(err) => {
+            eprintln!("[CLIPS_APP] Failed to finish recording: {err:#}");
+            controller.set_message(format!("Failed to finish recording: {err:#}"));
+            None
+        }
+    }
+}
+
+/// Runs a hotkey-bound action without opening the overlay. Clips saved this
+/// way stay in the unprocessed directory so play isn't interrupted; finished
+/// recordings are returned for processing.
+async fn handle_hotkey_action(
+    controller: &mut ReplayController,
+    action: HotkeyAction,
+    mode: ReplayMode,
+) -> Result<Option<PathBuf>> {
+    eprintln!("[CLIPS_APP] Hotkey action: {action}");
+    match action {
+        HotkeyAction::ShowOverlay => {
+            // Toggled directly by the listener thread
+        }
+        HotkeyAction::ToggleReplay => {
+            let enable = !controller.status()?.running;
+            set_replay_enabled(controller, mode, enable).await;
+        }
+        HotkeyAction::ToggleRecording => {
+            let enable = !controller.is_recording();
+            return Ok(set_recording(controller, enable).await);
+        }
+        HotkeyAction::SaveRecent(duration) => {
+            match controller.save_recent(duration).await {
+                Ok(Some(path)) => {
+                    let name = path
+                        .file_name()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    controller.set_message(format!("Saved {name}"));
                 }
-                return Ok(true);
-            } else if !game_running && *game_was_running {
-                eprintln!("[CLIPS_APP] Game exited, stopping replay");
-                match controller.stop().await {
-                    Ok(_) => {
-                        controller.set_message("Replay stopped (game exited)");
-                    }
-                    Err(err) => {
-                        eprintln!("[CLIPS_APP] Failed to stop replay: {err:#}");
-                        controller.set_message(format!("Failed to stop replay: {err:#}"));
-                    }
+                Ok(None) => controller.set_message("No new replay file generated"),
+                Err(err) => {
+                    eprintln!("[CLIPS_APP] Hotkey save failed: {err:#}");
+                    controller.set_message(format!("Save failed: {err:#}"));
                 }
-                *game_was_running = false;
-                return Ok(true);
             }
         }
+        HotkeyAction::Bookmark => {
+            controller.add_bookmark();
+        }
+    }
+    Ok(None)
+}
+
+/// Starts or stops the replay to follow `game`, the game the watcher last
+/// reported as running.
+async fn maybe_handle_game_detection(
+    controller: &mut ReplayController,
+    game: Option<&DetectedGame>,
+    game_was_running: &mut bool,
+) -> bool {
+    let mut changed = false;
+    if let Some(game) = game {
+        if controller.active_profile() != Some(game.name.as_str()) {
+            // Covers both a fresh start and switching straight to another game
+            apply_game_profile(controller, &game.name).await;
+            changed = true;
+        }
+    }
+
+    if let (Some(game), false) = (game, *game_was_running) {
+        eprintln!("[CLIPS_APP] Game detected, ensuring replay is running");
+        match controller.ensure_running().await {
+            Ok(_) => {
+                controller.set_message(format!("Replay started ({})", game.name));
+                *game_was_running = true;
+            }
+            Err(err) => {
+                eprintln!("[CLIPS_APP] Failed to start replay: {err:#}");
+                controller.set_message(format!("Failed to start replay: {err:#}"));
+            }
+        }
+        return true;
+    } else if game.is_none() && *game_was_running {
+        eprintln!("[CLIPS_APP] Game exited, stopping replay");
+        match controller.stop().await {
+            Ok(_) => {
+                controller.set_message("Replay stopped (game exited)");
+            }
+            Err(err) => {
+                eprintln!("[CLIPS_APP] Failed to stop replay: {err:#}");
+                controller.set_message(format!("Failed to stop replay: {err:#}"));
+            }
+        }
+        if let Err(err) = controller.revert_profile().await {
+            eprintln!("[CLIPS_APP] Failed to revert capture profile: {err:#}");
+        }
+        *game_was_running = false;
+        return true;
+    }
+
+    changed
+}
+
+/// Tracks the running game and logs its play session.
+fn apply_game_event(
+    controller: &mut ReplayController,
+    current_game: &mut Option<DetectedGame>,
+    event: GameEvent,
+) {
+    let mut log = match SessionLog::load() {
+        Ok(log) => Some(log),
+        Err(err) => {
+            eprintln!("[CLIPS_APP] Failed to load session log: {err:#}");
+            None
+        }
+    };
+
+    let at = match &event {
+        GameEvent::Started(_, at) | GameEvent::Exited(_, at) => *at,
+    };
+    if let (Some(log), Some(id)) = (log.as_mut(), controller.session_id()) {
+        log.end(id, at);
+    }
+
+    *current_game = match event {
+        GameEvent::Started(game, at) => {
+            let session_id = log.as_mut().map(|log| log.start(&game, at));
+            controller.set_game(Some(game.clone()), session_id);
+            Some(game)
+        }
+        GameEvent::Exited(..) => {
+            controller.set_game(None, None);
+            None
+        }
+    };
+
+    if let Some(Err(err)) = log.map(|log| log.save()) {
+        eprintln!("[CLIPS_APP] Failed to save session log: {err:#}");
+    }
+}
+
+/// Applies the saved profile for `game`, or reverts to the base settings when
+/// it has none.
+async fn apply_game_profile(controller: &mut ReplayController, game: &str) {
+    let profiles = GameProfiles::load().unwrap_or_else(|err| {
+        eprintln!("[CLIPS_APP] Failed to load game profiles: {err:#}");
+        GameProfiles::default()
+    });
+
+    let result = match profiles.get(game) {
+        Some((name, profile)) => {
+            eprintln!("[CLIPS_APP] Applying capture profile for {name}");
+            controller.apply_profile(game, profile).await
+        }
+        None => controller.revert_profile().await,
+    };
+    if let Err(err) = result {
+        eprintln!("[CLIPS_APP] Failed to apply capture profile for {game}: {err:#}");
+        controller.set_message(format!("Failed to apply profile for {game}: {err:#}"));
+    }
+}
+
+async fn save_game_profile(controller: &mut ReplayController, game: &str, settings: &CaptureSettingsPayload) {
+    let game = game.trim();
+    if game.is_empty() {
+        controller.set_message("Enter a game name for the profile");
+        return;
+    }
+
+    // Saving over a file that failed to load would erase every other profile
+    let mut profiles = match GameProfiles::load() {
+        Ok(profiles) => profiles,
         Err(err) => {
-            eprintln!("[CLIPS_APP] Game detection error: {err:#}");
+            controller.set_message(format!("Failed to save profile: {err:#}"));
+            return;
         }
+    };
+    profiles.set(game, CaptureProfile {
+        buffer_seconds: Some(settings.buffer_seconds),
+        bitrate: Some(settings.bitrate),
+        fps: Some(settings.fps),
+        target: Some(settings.target.clone()).filter(|target| !target.trim().is_empty()),
+        audio_tracks: Some(settings.audio_tracks.clone()).filter(|tracks| !tracks.is_empty()),
+    });
+    if let Err(err) = profiles.save() {
+        controller.set_message(format!("Failed to save profile: {err:#}"));
+        return;
     }
 
-    Ok(false)
+    // Pick up the edit straight away if that game is being captured
+    let active = controller
+        .active_profile()
+        .is_some_and(|name| name.eq_ignore_ascii_case(game));
+    let running = games::detect_running_game()
+        .ok()
+        .flatten()
+        .is_some_and(|detected| detected.name.eq_ignore_ascii_case(game));
+    if active || running {
+        apply_game_profile(controller, game).await;
+    }
+    controller.set_message(format!("Saved profile for {game}"));
+}
+
+async fn delete_game_profile(controller: &mut ReplayController, game: &str) {
+    let mut profiles = match GameProfiles::load() {
+        Ok(profiles) => profiles,
+        Err(err) => {
+            controller.set_message(format!("Failed to delete profile: {err:#}"));
+            return;
+        }
+    };
+    if profiles.remove(game).is_none() {
+        controller.set_message(format!("No profile saved for {}", game.trim()));
+        return;
+    }
+    if let Err(err) = profiles.save() {
+        controller.set_message(format!("Failed to delete profile: {err:#}"));
+        return;
+    }
+
+    if controller
+        .active_profile()
+        .is_some_and(|name| name.eq_ignore_ascii_case(game.trim()))
+    {
+        if let Err(err) = controller.revert_profile().await {
+            eprintln!("[CLIPS_APP] Failed to revert capture profile: {err:#}");
+        }
+    }
+    controller.set_message(format!("Deleted profile for {}", game.trim()));
 }
 
 fn failed_uploads_to_entries(uploads: &clips_app::failed_uploads::FailedUploadsList) -> Vec<overlay::FailedUploadEntry> {
@@ -604,6 +1030,7 @@ fn build_capture_status(
     hotkey: &str,
     failed_uploads: &clips_app::failed_uploads::FailedUploadsList,
     replay_mode: ReplayMode,
+    hotkey_devices: usize,
 ) -> CaptureStatusPayload {
     let mode_str = match replay_mode {
         ReplayMode::Manual => "manual",
@@ -617,6 +1044,7 @@ fn build_capture_status(
         fps: status.fps,
         target: status.target.clone(),
         audio_tracks: status.audio_tracks.clone(),
+        save_durations: status.save_durations.clone(),
         last_saved: status
             .last_saved
             .as_ref()
@@ -626,6 +1054,66 @@ fn build_capture_status(
         is_saving: false,
         failed_uploads: failed_uploads_to_entries(failed_uploads),
         replay_mode: mode_str.to_string(),
+        hotkey_devices,
+        restart_count: status.restart_count,
+        last_exit: status.last_exit.clone(),
+        recent_stderr: status.recent_stderr.clone(),
+        recording_secs: status.recording_secs,
+        disk_used_bytes: status.disk_usage.map(|usage| usage.used_bytes),
+        disk_quota_bytes: status.disk_usage.and_then(|usage| usage.quota_bytes),
+        disk_ring_bytes: status.disk_usage.map(|usage| usage.ring_bytes),
+        active_profile: status.active_profile.clone(),
+    }
+}
+
+/// The track map recorded with the clip, or the configured one trimmed to the
+/// streams the file actually has.
+async fn clip_track_map(config: &AppConfig, clip_metadata: Option<&ClipMetadata>) -> TrackMap {
+    if let Some(metadata) = clip_metadata.filter(|metadata| !metadata.tracks.is_empty()) {
+        return TrackMap {
+            tracks: metadata.tracks.clone(),
+        };
+    }
+
+    let track_map = TrackMap::load().unwrap_or_else(|err| {
+        eprintln!("[CLIPS_APP] Using the default audio track map: {err:#}");
+        TrackMap::default()
+    });
+    match ffmpeg::probe_audio_streams(&config.source).await {
+        Ok(count) => track_map.for_stream_count(count),
+        Err(err) => {
+            eprintln!("[CLIPS_APP] Failed to count audio streams in {}: {err:#}", config.source.display());
+            track_map
+        }
+    }
+}
+
+/// Applies a game's saved picker levels to the tracks it names.
+fn apply_track_levels(track_map: &mut TrackMap, levels: &BTreeMap<String, TrackLevel>) {
+    for (name, level) in levels {
+        if let Some(track) = track_map.get_mut(name) {
+            track.weight = level.volume;
+            track.enabled = level.enabled;
+        }
+    }
+}
+
+/// Remembers the picker's levels for `game`, keeping any saved for tracks
+/// this clip doesn't have.
+fn save_track_levels(mixes: &mut GameMixes, game: &str, track_map: &TrackMap) {
+    if game.trim().is_empty() {
+        return;
+    }
+    let mut levels = mixes.get(game).cloned().unwrap_or_default();
+    for track in &track_map.tracks {
+        levels.insert(track.name.clone(), TrackLevel {
+            volume: track.weight,
+            enabled: track.enabled,
+        });
+    }
+    mixes.set(game, levels);
+    if let Err(err) = mixes.save() {
+        eprintln!("[CLIPS_APP] Failed to save mix levels: {err:#}");
     }
 }
 
@@ -638,21 +1126,52 @@ async fn process_clip(
     overlay_handle
         .update(Stage::Detected, 0.0, format!("Detected: {}", config.source_file_name()))?;
 
-    let detected_game = detect_game_name();
+    let mut sessions = SessionLog::load().unwrap_or_else(|err| {
+        eprintln!("[CLIPS_APP] Failed to load session log: {err:#}");
+        SessionLog::default()
+    });
+    let clip_metadata = ClipMetadata::read(&config.source).unwrap_or_else(|err| {
+        eprintln!("[CLIPS_APP] Ignoring clip metadata: {err:#}");
+        None
+    });
+    // Context recorded at save time beats detecting whatever runs now
+    let detected_game = if let Some(game) = clip_metadata.as_ref().and_then(|m| m.game.clone()) {
+        eprintln!("[CLIPS_APP] Game from clip metadata: {game}");
+        game
+    } else if let Some(session) = sessions.find_by_clip(&config.source) {
+        eprintln!("[CLIPS_APP] Clip belongs to session {}", session.id);
+        session.game.clone()
+    } else {
+        detect_game_name()
+    };
+
+    let mut track_map = clip_track_map(config, clip_metadata.as_ref()).await;
+    let mut mixes = GameMixes::load().unwrap_or_else(|err| {
+        eprintln!("[CLIPS_APP] Failed to load mix levels: {err:#}");
+        GameMixes::default()
+    });
+    if let Some(levels) = mixes.get(&detected_game) {
+        apply_track_levels(&mut track_map, levels);
+    }
+    let export_presets = ExportPresets::load().unwrap_or_else(|err| {
+        eprintln!("[CLIPS_APP] Failed to load export presets: {err:#}");
+        ExportPresets::default()
+    });
 
-    let available_channels = CHANNEL_OPTIONS
-        .iter()
-        .map(|channel| channel.to_string())
-        .collect::<Vec<_>>();
     let picker_result = match overlay_handle.show_picker(
         Some(&config.source),
         &config.source_file_name(),
         &detected_game,
-        &available_channels,
+        &track_map.tracks,
+        &config.voice,
+        &config.ducking,
+        &config.loudness,
+        &export_presets,
     )? {
         Some(result) => result,
         None => {
             std::fs::remove_file(&config.source).ok();
+            metadata::remove_sidecar(&config.source);
             overlay_handle.update(Stage::Done, 1.0, "Cancelled")?;
             return Ok(());
         }
@@ -663,42 +1182,165 @@ async fn process_clip(
 
     if matches!(picker_result.action, overlay::ActionChoice::Discard) {
         std::fs::remove_file(&config.source).ok();
+        metadata::remove_sidecar(&config.source);
         overlay_handle.update(Stage::Done, 1.0, "Discarded")?;
         return Ok(());
     }
 
-    let transformed = process::mix_audio(config, &picker_result.channels, overlay_handle).await?;
+    for picked in &picker_result.tracks {
+        if let Some(track) = track_map.get_mut(&picked.name) {
+            track.enabled = picked.enabled;
+            if picked.volume.is_finite() {
+                track.weight = picked.volume.max(0.0);
+            }
+        }
+    }
+    save_track_levels(&mut mixes, &picker_result.game, &track_map);
+
+    let export_preset = export_presets
+        .get(&picker_result.export_preset)
+        .with_context(|| format!("unknown export preset '{}'", picker_result.export_preset))?;
+
+    let mix_options = process::MixOptions {
+        voice_preset: picker_result.voice_preset,
+        duck_audio: picker_result.duck_audio,
+        normalize_loudness: picker_result.normalize_loudness,
+        audio_export: picker_result.audio_export,
+        audio_bitrate: export_preset.audio_bitrate,
+    };
+    // The trimmer plays the source with a quick mix of the whole clip; the
+    // real mix only covers the range picked there
+    let preview = match process::preview_mix(config, &track_map, overlay_handle).await {
+        Ok(path) => Some(path),
+        Err(err) => {
+            eprintln!("[CLIPS_APP] Failed to mix preview audio, trimming with the source audio: {err:#}");
+            None
+        }
+    };
 
     overlay_handle.update(Stage::AwaitExport, 0.0, "Probing video duration...")?;
-    let duration = ffmpeg::probe_duration(&transformed).await?;
+    let duration = ffmpeg::probe_duration(&config.source).await?;
 
     overlay_handle.update(Stage::AwaitExport, 0.05, "Waiting for trim selection...")?;
-    let trim_result = match overlay_handle.show_trimmer(&transformed, duration)? {
+    let bookmarks = clip_metadata
+        .as_ref()
+        .map(|metadata| metadata.bookmark_offsets(duration))
+        .unwrap_or_default();
+    let trim_result = overlay_handle.show_trimmer(&config.source, preview.as_deref(), duration, &bookmarks);
+    if let Some(preview) = &preview {
+        std::fs::remove_file(preview).ok();
+    }
+    let trim_result = match trim_result? {
         Some(result) => result,
         None => {
             std::fs::remove_file(&config.source).ok();
-            std::fs::remove_file(&transformed).ok();
+            metadata::remove_sidecar(&config.source);
             overlay_handle.update(Stage::Done, 1.0, "Cancelled")?;
             return Ok(());
         }
     };
 
-    overlay_handle.update(Stage::AwaitExport, 0.1, "Preparing trim…")?;
-    let parent = config.source.parent().context("source file has no parent directory")?;
-    let stem = config.source.file_stem().context("source file has no stem")?;
-    let trimmed = parent.join(format!("{}_trimmed.mp4", stem.to_string_lossy()));
-    ffmpeg::trim_video(
-        &transformed,
-        &trimmed,
+    let export_start = if mix_options.normalize_loudness {
+        overlay_handle.update(Stage::AwaitExport, 0.1, "Measuring loudness…")?;
+        0.3
+    } else {
+        0.1
+    };
+    let audio = process::prepare_mix(
+        config,
+        &track_map,
+        &mix_options,
         trim_result.start_time,
         trim_result.end_time,
         |fraction| {
-            let stage_fraction = (0.1 + fraction * 0.9).min(1.0);
-            let detail = format_stage_detail(Stage::AwaitExport, fraction, "trimmed");
-            let _ = overlay_handle.update(Stage::AwaitExport, stage_fraction, detail);
+            let detail = format_stage_detail(Stage::AwaitExport, fraction, "analysed");
+            let _ = overlay_handle.update(Stage::AwaitExport, 0.1 + fraction * 0.2, detail);
         },
     )
     .await?;
+
+    let parent = config.source.parent().context("source file has no parent directory")?;
+    let stem = config.source.file_stem().context("source file has no stem")?;
+    let trimmed = parent.join(format!("{}_trimmed.mp4", stem.to_string_lossy()));
+    let verb = if export_preset.is_copy() { "trimmed" } else { "encoded" };
+    let on_progress = |fraction: f32| {
+        let stage_fraction = (export_start + fraction * (1.0 - export_start)).min(1.0);
+        let detail = format_stage_detail(Stage::AwaitExport, fraction, verb);
+        let _ = overlay_handle.update(Stage::AwaitExport, stage_fraction, detail);
+    };
+    if export_preset.is_copy() {
+        overlay_handle.update(Stage::AwaitExport, export_start, "Preparing trim…")?;
+        match config.trim_mode {
+            TrimMode::Smart => {
+                smartcut::smart_trim(
+                    &config.source,
+                    &trimmed,
+                    trim_result.start_time,
+                    trim_result.end_time,
+                    &audio,
+                    on_progress,
+                )
+                .await?;
+            }
+            TrimMode::Keyframe => {
+                ffmpeg::trim_with_mix(
+                    &config.source,
+                    &trimmed,
+                    trim_result.start_time,
+                    trim_result.end_time,
+                    &audio,
+                    on_progress,
+                )
+                .await?
+            }
+        }
+    } else {
+        eprintln!("[CLIPS_APP] Encoding with export preset '{}'", export_preset.name);
+        overlay_handle.update(
+            Stage::AwaitExport,
+            export_start,
+            format!("Encoding {}…", export_preset.name),
+        )?;
+        if export_preset.target_size_mb.is_some() {
+            // The picker locks size-target presets to the mix; say so if stems got through anyway
+            let stems_dropped = picker_result.audio_export == AudioExport::Stems;
+            if stems_dropped {
+                eprintln!("[CLIPS_APP] Dropping stems to fit the size target");
+            }
+            // Both passes share the stage equally
+            ffmpeg::encode_to_size(
+                &config.source,
+                &trimmed,
+                trim_result.start_time,
+                trim_result.end_time,
+                export_preset,
+                &audio,
+                |pass, fraction| {
+                    let overall = ((pass - 1) as f32 + fraction) / 2.0;
+                    let stage_fraction = (export_start + overall * (1.0 - export_start)).min(1.0);
+                    let label = if stems_dropped {
+                        format!("encoded (pass {pass}/2, stems dropped to fit the size)")
+                    } else {
+                        format!("encoded (pass {pass}/2)")
+                    };
+                    let detail = format_stage_detail(Stage::AwaitExport, fraction, &label);
+                    let _ = overlay_handle.update(Stage::AwaitExport, stage_fraction, detail);
+                },
+            )
+            .await?;
+        } else {
+            ffmpeg::encode_video(
+                &config.source,
+                &trimmed,
+                trim_result.start_time,
+                trim_result.end_time,
+                export_preset,
+                &audio,
+                on_progress,
+            )
+            .await?;
+        }
+    }
     overlay_handle.update(Stage::AwaitExport, 1.0, "Trim complete")?;
 
     overlay_handle.update(Stage::Finalise, 0.0, "Finalising files…")?;
@@ -706,9 +1348,9 @@ async fn process_clip(
         config,
         &config.source,
         &trimmed,
-        &transformed,
         &safe_title,
     )?;
+    metadata::remove_sidecar(&config.source);
     overlay_handle.update(Stage::Finalise, 1.0, "Files ready")?;
 
     let base_title_with_game = if safe_game.is_empty() {
@@ -728,6 +1370,7 @@ async fn process_clip(
             )?;
             overlay_handle.update(Stage::Done, 1.0, "Saved (no upload)")?;
             println!("Saved clip to {:?}", dest_dir);
+            record_processed_clip(&mut sessions, &config.source, &dest_dir);
         }
         overlay::ActionChoice::Upload => {
             let title_with_game = base_title_with_game.clone();
@@ -751,6 +1394,7 @@ async fn process_clip(
                     &id,
                 )?;
                 println!("Uploaded video id: {id} (stored at {:?})", dest_dir);
+                record_processed_clip(&mut sessions, &config.source, &dest_dir);
                 open_uploaded_video(&id);
             } else {
                 // Upload failed - treat like a move action (save without YouTube ID)
@@ -763,6 +1407,7 @@ async fn process_clip(
                     &safe_title,
                 )?;
                 println!("Upload failed, saved clip to {:?}", dest_dir);
+                record_processed_clip(&mut sessions, &config.source, &dest_dir);
                 
                 // Add to failed uploads list for retry later
                 let processed_file = dest_dir.join(format!("{title_with_game}.mp4"));
@@ -789,6 +1434,14 @@ async fn process_clip(
     Ok(())
 }
 
+fn record_processed_clip(sessions: &mut SessionLog, source: &Path, dest_dir: &Path) {
+    if sessions.set_processed(source, dest_dir) {
+        if let Err(err) = sessions.save() {
+            eprintln!("[CLIPS_APP] Failed to save session log: {err:#}");
+        }
+    }
+}
+
 fn set_overlay_visible(
     overlay_handle: &overlay::OverlayHandle,
     visible_state: &Arc<AtomicBool>,
@@ -801,68 +1454,240 @@ fn set_overlay_visible(
     Ok(())
 }
 
-// BUG FIX: Changed return type to Vec<JoinHandle<()>> to manage multiple listener threads
+type MonitorMap = HashMap<PathBuf, (u64, JoinHandle<()>)>;
+
+/// Shared state for the per-device monitor threads. Devices come and go as
+/// `/dev/input` changes, so the set of monitors is keyed by device path.
+#[derive(Clone)]
+struct DeviceMonitors {
+    bindings: Vec<HotkeyBinding>,
+    bound_keys: Vec<Key>,
+    // Modifier state is shared so a chord can span devices (e.g. Alt + mouse side button)
+    pressed_modifiers: Arc<std::sync::Mutex<HashSet<Key>>>,
+    overlay_handle: overlay::OverlayHandle,
+    visible_state: Arc<AtomicBool>,
+    action_tx: mpsc::UnboundedSender<HotkeyAction>,
+    monitors: Arc<std::sync::Mutex<MonitorMap>>,
+    next_id: Arc<AtomicU64>,
+    count_tx: Arc<watch::Sender<usize>>,
+    // Blocking reads can't be aborted, so the threads poll with a timeout and check this
+    shutdown: Arc<AtomicBool>,
+}
+
+impl DeviceMonitors {
+    /// Starts monitoring `path` if it is a device we care about and isn't
+    /// monitored already.
+    fn try_add(&self, path: &Path) {
+        if self.shutdown.load(Ordering::SeqCst) || self.monitors.lock().unwrap().contains_key(path) {
+            return;
+        }
+        if !is_hotkey_device(path, &self.bound_keys) {
+            return;
+        }
+
+        // Held until the entry is inserted so a monitor that exits straight away still cleans up
+        let mut monitors = self.monitors.lock().unwrap();
+        if monitors.contains_key(path) {
+            return;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let this = self.clone();
+        let device_path = path.to_path_buf();
+        let handle = tokio::task::spawn_blocking(move || {
+            monitor_device(
+                device_path.to_string_lossy().to_string(),
+                this.bindings.clone(),
+                this.pressed_modifiers.clone(),
+                this.overlay_handle.clone(),
+                this.visible_state.clone(),
+                this.action_tx.clone(),
+                &this.shutdown,
+            );
+            // Device went away (or failed to open); forget it unless it was re-added meanwhile
+            let mut monitors = this.monitors.lock().unwrap();
+            if monitors.get(&device_path).is_some_and(|(entry_id, _)| *entry_id == id) {
+                monitors.remove(&device_path);
+            }
+            this.count_tx.send_replace(monitors.len());
+        });
+
+        monitors.insert(path.to_path_buf(), (id, handle));
+        self.count_tx.send_replace(monitors.len());
+    }
+
+    fn stop_all(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        self.monitors.lock().unwrap().clear();
+    }
+}
+
+/// Handles for the hotkey monitors and the `/dev/input` watcher.
+struct HotkeyListener {
+    devices: DeviceMonitors,
+}
+
+impl HotkeyListener {
+    /// Signals the watcher and monitor threads to stop. They notice within
+    /// `POLL_INTERVAL_MS` and exit on their own.
+    fn shutdown(self) {
+        self.devices.stop_all();
+    }
+}
+
+// BUG FIX: Monitors every input device and follows hot-plug events, so keyboards that reconnect keep working
 async fn spawn_hotkey_listener(
-    hotkey: String,
+    bindings: Vec<HotkeyBinding>,
     overlay_handle: overlay::OverlayHandle,
     visible_state: Arc<AtomicBool>,
-) -> Vec<JoinHandle<()>> {
-    // Parse the hotkey string (e.g., "Alt+X")
-    let (modifier_keys, target_key) = parse_hotkey(&hotkey);
-    
-    eprintln!("[CLIPS_APP] Attempting to register global hotkey: {}", hotkey);
-    
-    // Find all keyboard devices
-    let keyboard_devices = match find_keyboard_devices() {
-        Ok(devices) if !devices.is_empty() => devices,
-        Ok(_) => {
-            eprintln!("[CLIPS_APP] No keyboard devices found in /dev/input");
-            eprintln!("[CLIPS_APP] Make sure your user is in the 'input' group");
-            return Vec::new();
+    action_tx: mpsc::UnboundedSender<HotkeyAction>,
+) -> (HotkeyListener, watch::Receiver<usize>) {
+    for binding in &bindings {
+        eprintln!(
+            "[CLIPS_APP] Attempting to register global hotkey: {} -> {}",
+            binding.chord, binding.action
+        );
+    }
+
+    // Mouse buttons and other non-keyboard keys pull in the devices that emit them
+    let bound_keys: Vec<Key> = bindings
+        .iter()
+        .flat_map(|binding| binding.chord.keys.iter().copied())
+        .collect();
+
+    let (count_tx, count_rx) = watch::channel(0usize);
+    let devices = DeviceMonitors {
+        bindings,
+        bound_keys,
+        pressed_modifiers: Arc::new(std::sync::Mutex::new(HashSet::new())),
+        overlay_handle,
+        visible_state,
+        action_tx,
+        monitors: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        next_id: Arc::new(AtomicU64::new(0)),
+        count_tx: Arc::new(count_tx),
+        shutdown: Arc::new(AtomicBool::new(false)),
+    };
+
+    // Find all keyboard devices present right now
+    match std::fs::read_dir(INPUT_DIR) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                if is_event_node(&entry.path()) {
+                    devices.try_add(&entry.path());
+                }
+            }
         }
         Err(err) => {
             eprintln!("[CLIPS_APP] Failed to enumerate keyboard devices: {err:#}");
             eprintln!("[CLIPS_APP] Make sure your user is in the 'input' group");
-            return Vec::new();
         }
-    };
+    }
 
-    eprintln!("[CLIPS_APP] Found {} keyboard device(s), spawning monitors...", keyboard_devices.len());
+    let found = *count_rx.borrow();
+    if found == 0 {
+        eprintln!("[CLIPS_APP] No keyboard devices found in {INPUT_DIR}");
+        eprintln!("[CLIPS_APP] Make sure your user is in the 'input' group");
+    } else {
+        eprintln!("[CLIPS_APP] Monitoring {} keyboard device(s)", found);
+    }
 
-    let mut handles = Vec::new();
+    if let Err(err) = watch_input_dir(devices.clone()) {
+        eprintln!("[CLIPS_APP] Failed to watch {INPUT_DIR} for new devices: {err:#}");
+    }
 
-    // BUG FIX: Spawn a separate thread for each keyboard device found
-    for device_path in keyboard_devices {
-        // Clone shared state for each thread
-        let path_clone = device_path.clone();
-        let modifier_keys_clone = modifier_keys.clone();
-        let overlay_clone = overlay_handle.clone();
-        let visible_clone = visible_state.clone();
+    (HotkeyListener { devices }, count_rx)
+}
 
-        let handle = tokio::task::spawn_blocking(move || {
-            monitor_device(
-                path_clone,
-                target_key,
-                modifier_keys_clone,
-                overlay_clone,
-                visible_clone
-            );
-        });
-        
-        handles.push(handle);
+const INPUT_DIR: &str = "/dev/input";
+const POLL_INTERVAL_MS: i32 = 200;
+
+/// Waits up to `POLL_INTERVAL_MS` for `fd` to become readable.
+fn wait_readable(fd: std::os::fd::RawFd) -> std::io::Result<bool> {
+    let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    // SAFETY: `pollfd` is a valid, exclusively borrowed array of length 1
+    match unsafe { libc::poll(&mut pollfd, 1, POLL_INTERVAL_MS) } {
+        -1 => {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                Ok(false)
+            } else {
+                Err(err)
+            }
+        }
+        0 => Ok(false),
+        _ => Ok(true),
     }
-    
-    handles
+}
+
+fn is_event_node(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with("event"))
+}
+
+/// Follows `/dev/input` with inotify and starts monitors for new devices.
+/// Removed devices are dropped by their monitor thread once reads fail.
+fn watch_input_dir(devices: DeviceMonitors) -> Result<()> {
+    use inotify::{EventMask, Inotify, WatchMask};
+
+    let mut inotify = Inotify::init().context("initialising inotify")?;
+    // udev fixes up permissions after creating the node, hence ATTRIB
+    inotify
+        .watches()
+        .add(INPUT_DIR, WatchMask::CREATE | WatchMask::ATTRIB | WatchMask::DELETE)
+        .with_context(|| format!("watching {INPUT_DIR}"))?;
+
+    tokio::task::spawn_blocking(move || {
+        use std::os::fd::AsRawFd;
+
+        let mut buffer = [0u8; 4096];
+        let fd = inotify.as_raw_fd();
+        while !devices.shutdown.load(Ordering::SeqCst) {
+            match wait_readable(fd) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    eprintln!("[CLIPS_APP] Stopped watching {INPUT_DIR}: {err}");
+                    break;
+                }
+            }
+            // The inotify fd is non-blocking, so a spurious wakeup just reads nothing
+            let events = match inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => continue,
+                Err(err) => {
+                    eprintln!("[CLIPS_APP] Stopped watching {INPUT_DIR}: {err}");
+                    break;
+                }
+            };
+            for event in events {
+                let Some(name) = event.name else { continue };
+                let path = Path::new(INPUT_DIR).join(name);
+                if !is_event_node(&path) {
+                    continue;
+                }
+                if event.mask.intersects(EventMask::CREATE | EventMask::ATTRIB) {
+                    devices.try_add(&path);
+                } else if event.mask.contains(EventMask::DELETE) {
+                    eprintln!("[CLIPS_APP] Input device removed: {}", path.display());
+                }
+            }
+        }
+    });
+    Ok(())
 }
 
 // Extracted blocking logic to a separate function for clarity
 fn monitor_device(
     device_path: String,
-    target_key: Key,
-    modifier_keys: Vec<Key>,
+    bindings: Vec<HotkeyBinding>,
+    pressed_modifiers: Arc<std::sync::Mutex<HashSet<Key>>>,
     overlay_handle: overlay::OverlayHandle,
-    visible_state: Arc<AtomicBool>
+    visible_state: Arc<AtomicBool>,
+    action_tx: mpsc::UnboundedSender<HotkeyAction>,
+    shutdown: &AtomicBool,
 ) {
+    use std::os::fd::AsRawFd;
+
     let mut device = match Device::open(&device_path) {
         Ok(dev) => dev,
         Err(err) => {
@@ -872,30 +1697,54 @@ fn monitor_device(
     };
 
     eprintln!("[CLIPS_APP] Monitoring keyboard: {:?}", device.name());
-    let mut pressed_modifiers = std::collections::HashSet::new();
-
-    loop {
+    let modifier_keys: HashSet<Key> = bindings
+        .iter()
+        .flat_map(|binding| binding.chord.modifier_keys())
+        .collect();
+    // Modifiers held on this device, released from the shared set when it goes away
+    let mut held_here: HashSet<Key> = HashSet::new();
+
+    while !shutdown.load(Ordering::SeqCst) {
+        match wait_readable(device.as_raw_fd()) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(err) => {
+                eprintln!("[CLIPS_APP] Error waiting on {}: {}", device_path, err);
+                break;
+            }
+        }
         match device.fetch_events() {
             Ok(events) => {
                 for ev in events {
                     if let InputEventKind::Key(key) = ev.kind() {
                         let pressed = ev.value() == 1;
                         
+                        let mut pressed_modifiers = pressed_modifiers.lock().unwrap();
+
                         // Track modifier keys
                         if modifier_keys.contains(&key) {
                             if pressed {
                                 pressed_modifiers.insert(key);
+                                held_here.insert(key);
                             } else if ev.value() == 0 { // Handle release explicitly
                                 pressed_modifiers.remove(&key);
+                                held_here.remove(&key);
                             }
                         }
                         
-                        // Check if hotkey is pressed
-                        if key == target_key && pressed {
-                            let all_modifiers_pressed = modifier_keys.iter()
-                                .all(|m| pressed_modifiers.contains(m));
-                            
-                            if all_modifiers_pressed {
+                        if !pressed {
+                            continue;
+                        }
+
+                        // Prefer the most specific chord when several share a key
+                        let matched = bindings
+                            .iter()
+                            .filter(|binding| binding.chord.matches(key, &pressed_modifiers))
+                            .max_by_key(|binding| binding.chord.modifiers.len());
+                        drop(pressed_modifiers);
+
+                        match matched.map(|binding| binding.action) {
+                            Some(HotkeyAction::ShowOverlay) => {
                                 let desired = !visible_state.load(Ordering::SeqCst);
                                 if let Err(err) = overlay_handle.set_visibility(desired) {
                                     eprintln!("[CLIPS_APP] Failed to toggle overlay: {err:#}");
@@ -904,6 +1753,10 @@ fn monitor_device(
                                     eprintln!("[CLIPS_APP] Toggled overlay to: {}", desired);
                                 }
                             }
+                            Some(action) if action_tx.send(action).is_err() => {
+                                eprintln!("[CLIPS_APP] Capture loop gone, dropping hotkey action {action}");
+                            }
+                            _ => {}
                         }
                     }
                 }
@@ -917,66 +1770,37 @@ fn monitor_device(
             }
         }
     }
-}
 
-fn find_keyboard_devices() -> Result<Vec<String>> {
-    use std::fs;
-    
-    let mut keyboards = Vec::new();
-    
-    for entry in fs::read_dir("/dev/input")? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if let Some(filename) = path.file_name() {
-            if filename.to_string_lossy().starts_with("event") {
-                if let Ok(device) = Device::open(&path) {
-                    // BUG FIX: Better keyboard detection
-                    // Check for a combination of keys that indicates a "real" keyboard
-                    // Checking only for KEY_X allows mice with side buttons to be detected
-                    if let Some(keys) = device.supported_keys() {
-                        if keys.contains(Key::KEY_ENTER) 
-                            && keys.contains(Key::KEY_ESC) 
-                            && keys.contains(Key::KEY_A) 
-                        {
-                            keyboards.push(path.to_string_lossy().to_string());
-                        }
-                    }
-                }
-            }
-        }
+    let mut pressed_modifiers = pressed_modifiers.lock().unwrap();
+    for key in held_here {
+        pressed_modifiers.remove(&key);
     }
-    
-    Ok(keyboards)
 }
 
-fn parse_hotkey(hotkey: &str) -> (Vec<Key>, Key) {
-    let parts: Vec<&str> = hotkey.split('+').map(|s| s.trim()).collect();
-    let mut modifiers = Vec::new();
-    let mut target = Key::KEY_X;
-    
-    for part in parts {
-        match part.to_lowercase().as_str() {
-            "alt" => modifiers.push(Key::KEY_LEFTALT),
-            "ctrl" | "control" => modifiers.push(Key::KEY_LEFTCTRL),
-            "shift" => modifiers.push(Key::KEY_LEFTSHIFT),
-            "super" | "meta" | "win" => modifiers.push(Key::KEY_LEFTMETA),
-            "x" => target = Key::KEY_X,
-            "c" => target = Key::KEY_C,
-            "v" => target = Key::KEY_V,
-            "s" => target = Key::KEY_S,
-            _ => {}
-        }
-    }
-    
-    (modifiers, target)
+fn is_hotkey_device(path: &Path, bound_keys: &[Key]) -> bool {
+    let Ok(device) = Device::open(path) else {
+        return false;
+    };
+    // BUG FIX: Better keyboard detection
+    // Check for a combination of keys that indicates a "real" keyboard
+    // Checking only for KEY_X allows mice with side buttons to be detected
+    let Some(keys) = device.supported_keys() else {
+        return false;
+    };
+    let is_keyboard = keys.contains(Key::KEY_ENTER)
+        && keys.contains(Key::KEY_ESC)
+        && keys.contains(Key::KEY_A);
+    // Also watch mice etc. when a binding uses one of their buttons
+    let emits_bound_button = bound_keys
+        .iter()
+        .any(|key| key.code() >= Key::BTN_0.code() && keys.contains(*key));
+    is_keyboard || emits_bound_button
 }
 
 fn finalise_files(
     config: &AppConfig,
     source: &Path,
     new_clip: &Path,
-    transformed: &Path,
     safe_title: &str,
 ) -> Result<(PathBuf, PathBuf)> {
     use std::fs;
@@ -996,9 +1820,6 @@ fn finalise_files(
         }
         fs::rename(new_clip, &out_processed).context("moving processed clip")?;
     }
-    if transformed.exists() && transformed != out_processed.as_path() {
-        let _ = fs::remove_file(transformed);
-    }
     Ok((out_full, out_processed))
 }
 
@@ -1040,109 +1861,18 @@ fn detect_game_name() -> String {
         }
     }
     
-    // Detect running Steam games by checking process environments for SteamAppId
-    if let Ok(game) = detect_steam_game() {
-        if !game.is_empty() {
-            eprintln!("[CLIPS_APP] Detected game: {}", game);
-            return game;
+    match games::detect_running_game() {
+        Ok(Some(game)) => {
+            eprintln!("[CLIPS_APP] Detected game: {} (via {})", game.name, game.source);
+            return game.name;
         }
+        Ok(None) => {}
+        Err(err) => eprintln!("[CLIPS_APP] Game detection error: {err:#}"),
     }
     
     String::new()
 }
 
-fn detect_steam_game() -> Result<String> {
-    use std::fs;
-    use std::io::Read;
-    
-    // Find Steam base directory
-    let home = std::env::var("HOME")?;
-    let steam_base = if std::path::Path::new(&format!("{}/.steam/steam", home)).exists() {
-        format!("{}/.steam/steam", home)
-    } else {
-        format!("{}/.local/share/Steam", home)
-    };
-    
-    // Get current user ID for filtering processes
-    let current_uid = unsafe { libc::getuid() };
-    
-    // Search /proc for processes with SteamAppId
-    let proc_dir = fs::read_dir("/proc").context("Failed to read /proc")?;
-    
-    for entry in proc_dir.flatten() {
-        let path = entry.path();
-        
-        // Only check numeric directories (PIDs)
-        if let Some(name) = path.file_name() {
-            if name.to_string_lossy().chars().all(|c| c.is_ascii_digit()) {
-                // Check if process belongs to current user
-                if let Ok(status) = fs::read_to_string(path.join("status")) {
-                    if !status.lines().any(|line| {
-                        line.starts_with("Uid:") && line.split_whitespace()
-                            .nth(1)
-                            .and_then(|uid_str| uid_str.parse::<u32>().ok())
-                            .is_some_and(|uid| uid == current_uid)
-                    }) {
-                        continue;
-                    }
-                }
-                
-                // Read environment variables
-                if let Ok(mut env_file) = fs::File::open(path.join("environ")) {
-                    let mut env_data = Vec::new();
-                    if env_file.read_to_end(&mut env_data).is_ok() {
-                        // Parse null-separated environment variables
-                        let env_str = String::from_utf8_lossy(&env_data);
-                        
-                        // Look for SteamAppId or SteamGameId
-                        for env_var in env_str.split('\0') {
-                            if let Some(appid_str) = env_var.strip_prefix("SteamAppId=")
-                                .or_else(|| env_var.strip_prefix("SteamGameId="))
-                            {
-                                if let Ok(appid) = appid_str.parse::<u32>() {
-                                    // Found an AppID! Look up the game name
-                                    let manifest_path = format!(
-                                        "{}/steamapps/appmanifest_{}.acf",
-                                        steam_base, appid
-                                    );
-                                    
-                                    if let Ok(manifest) = fs::read_to_string(&manifest_path) {
-                                        // Parse the manifest for the game name
-                                        for line in manifest.lines() {
-                                            if let Some(name) = parse_acf_name_line(line) {
-                                                return Ok(name);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    Ok(String::new())
-}
-
-fn parse_acf_name_line(line: &str) -> Option<String> {
-    // Parse lines like:  "name"  "Game Name"
-    let trimmed = line.trim();
-    if trimmed.starts_with("\"name\"") {
-        // Find the second quoted string
-        if let Some(start) = trimmed.find('"').and_then(|i| trimmed[i + 1..].find('"').map(|j| i + j + 2)) {
-            if let Some(value_start) = trimmed[start..].find('"') {
-                let value_start_abs = start + value_start + 1;
-                if let Some(value_end) = trimmed[value_start_abs..].find('"') {
-                    return Some(trimmed[value_start_abs..value_start_abs + value_end].to_string());
-                }
-            }
-        }
-    }
-    None
-}
-
 fn sanitize_text(value: &str) -> String {
     let mut cleaned = value
         .chars()
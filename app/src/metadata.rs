@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioTrack;
+use crate::capture::ReplaySettings;
+use crate::games::DetectedGame;
+
+const SIDECAR_EXTENSION: &str = "json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipKind {
+    Replay,
+    Recording,
+}
+
+/// The capture settings a clip was recorded with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureInfo {
+    pub backend: String,
+    pub target: String,
+    pub fps: u32,
+    pub bitrate: u32,
+    pub buffer_seconds: u32,
+}
+
+/// Context captured when a clip is saved, written next to it as
+/// `<clip>.json` so processing doesn't have to guess it later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipMetadata {
+    pub kind: ClipKind,
+    pub saved_at: u64,  // Unix timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Requested length for replay saves; `None` for the whole buffer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u32>,
+    pub capture: CaptureInfo,
+    /// Audio sources in stream order.
+    pub audio_tracks: Vec<String>,
+    /// What each audio stream holds, for the picker and the mix.
+    #[serde(default)]
+    pub tracks: Vec<AudioTrack>,
+    /// Bookmarks made while the clip was being captured (Unix timestamps).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<u64>,
+}
+
+impl ClipMetadata {
+    pub fn new(
+        kind: ClipKind,
+        settings: &ReplaySettings,
+        backend: &str,
+        game: Option<&DetectedGame>,
+        session_id: Option<&str>,
+    ) -> Self {
+        Self {
+            kind,
+            saved_at: unix_now(),
+            game: game.map(|game| game.name.clone()),
+            appid: game.and_then(|game| game.appid.clone()),
+            session_id: session_id.map(str::to_string),
+            duration_secs: None,
+            capture: CaptureInfo {
+                backend: backend.to_string(),
+                target: settings.target.clone(),
+                fps: settings.fps,
+                bitrate: settings.bitrate,
+                buffer_seconds: settings.buffer_seconds,
+            },
+            audio_tracks: settings.audio_tracks.clone(),
+            tracks: settings.track_map.for_sources(&settings.audio_tracks).tracks,
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// Stamps the save time, for recordings whose context was taken at start.
+    pub fn mark_saved(&mut self) {
+        self.saved_at = unix_now();
+    }
+
+    /// Bookmarks as offsets into a clip of `duration` seconds, which ends at
+    /// the save time.
+    pub fn bookmark_offsets(&self, duration: f64) -> Vec<f64> {
+        self.bookmarks
+            .iter()
+            .map(|&at| duration - self.saved_at.saturating_sub(at) as f64)
+            .filter(|offset| (0.0..=duration).contains(offset))
+            .collect()
+    }
+
+    /// Reads the sidecar for `clip`, if it has one.
+    pub fn read(clip: &Path) -> Result<Option<Self>> {
+        let path = sidecar_path(clip);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let metadata = serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        Ok(Some(metadata))
+    }
+
+    pub fn write(&self, clip: &Path) -> Result<()> {
+        let path = sidecar_path(clip);
+        let contents = serde_json::to_string_pretty(self)
+            .context("failed to serialize clip metadata")?;
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub fn sidecar_path(clip: &Path) -> PathBuf {
+    clip.with_extension(SIDECAR_EXTENSION)
+}
+
+pub fn is_sidecar(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some(SIDECAR_EXTENSION)
+}
+
+/// Deletes the sidecar for `clip` once the clip has been consumed.
+pub fn remove_sidecar(clip: &Path) {
+    let path = sidecar_path(clip);
+    if let Err(err) = fs::remove_file(&path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            eprintln!("[CLIPS_APP] Failed to remove {}: {err}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(saved_at: u64, bookmarks: Vec<u64>) -> ClipMetadata {
+        ClipMetadata {
+            kind: ClipKind::Replay,
+            saved_at,
+            game: None,
+            appid: None,
+            session_id: None,
+            duration_secs: Some(60),
+            capture: CaptureInfo {
+                backend: "ffmpeg".to_string(),
+                target: "screen".to_string(),
+                fps: 60,
+                bitrate: 12_000,
+                buffer_seconds: 60,
+            },
+            audio_tracks: Vec::new(),
+            tracks: Vec::new(),
+            bookmarks,
+        }
+    }
+
+    #[test]
+    fn bookmark_offsets_count_back_from_the_save() {
+        let clip = metadata(1_000, vec![930, 950, 990, 1_000, 1_005]);
+        // 930 is before the clip starts; 1_005 came after the save and clamps to its end
+        assert_eq!(clip.bookmark_offsets(60.0), [10.0, 50.0, 60.0, 60.0]);
+        assert_eq!(clip.bookmark_offsets(20.0), [10.0, 20.0, 20.0]);
+        assert!(metadata(1_000, Vec::new()).bookmark_offsets(60.0).is_empty());
+    }
+
+    #[test]
+    fn sidecars_sit_next_to_the_clip() {
+        let clip = Path::new("/clips/Replay_2024-05-01_20-15-02.mp4");
+        let sidecar = sidecar_path(clip);
+        assert_eq!(sidecar, Path::new("/clips/Replay_2024-05-01_20-15-02.json"));
+        assert!(is_sidecar(&sidecar));
+        assert!(!is_sidecar(clip));
+    }
+}
@@ -3,7 +3,12 @@ use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{bail, ensure, Context, Result};
+use crate::audio::{AudioExport, AudioTrack, TrackRole};
+use crate::ducking::DuckingConfig;
+use crate::export::ExportPresets;
+use crate::loudness::LoudnessConfig;
 use crate::progress::Stage;
+use crate::voice::{VoiceConfig, VoicePreset};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,12 +25,29 @@ enum OverlayCommand {
         preview_path: Option<String>,
         default_title: String,
         default_game: String,
-        available_channels: Vec<String>,
+        audio_tracks: Vec<PickerTrack>,
+        voice_presets: Vec<PickerOption>,
+        voice_preset: String,
+        /// `None` hides the option when the tracks have nothing to duck.
+        duck_audio: Option<bool>,
+        loudness_target: f64,
+        normalize_loudness: bool,
+        audio_exports: Vec<PickerOption>,
+        export_presets: Vec<PickerOption>,
+        export_preset: String,
+        /// Presets with a size target, which carry the mix but no stems.
+        #[serde(default)]
+        mixed_only_presets: Vec<String>,
     },
     #[serde(rename = "show_trimmer")]
     ShowTrimmer {
         video_path: String,
+        /// Audio played in place of the video's own, e.g. a preview mix.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        audio_path: Option<String>,
         duration: f64,
+        /// Bookmarked moments as offsets in seconds.
+        bookmarks: Vec<f64>,
     },
     #[serde(rename = "show_capture")]
     ShowCapture {
@@ -51,7 +73,12 @@ pub enum OverlayResponse {
         title: String,
         game: String,
         action: String,
-        channels: Vec<String>,
+        tracks: Vec<PickerTrack>,
+        voice_preset: String,
+        duck_audio: bool,
+        normalize_loudness: bool,
+        audio_export: String,
+        export_preset: String,
     },
     #[serde(rename = "trimmer_result")]
     TrimmerResult {
@@ -66,6 +93,21 @@ pub enum OverlayResponse {
     Cancelled,
 }
 
+/// An audio track in the picker, with its mute checkbox and volume slider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickerTrack {
+    pub name: String,
+    pub enabled: bool,
+    pub volume: f32,
+}
+
+/// One entry in a picker dropdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickerOption {
+    pub id: String,
+    pub label: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FailedUploadEntry {
     pub id: String,
@@ -80,12 +122,22 @@ pub struct CaptureStatusPayload {
     pub fps: u32,
     pub target: String,
     pub audio_tracks: Vec<String>,
+    pub save_durations: Vec<u32>,
     pub last_saved: Option<String>,
     pub hotkey: String,
     pub message: Option<String>,
     pub is_saving: bool,
     pub failed_uploads: Vec<FailedUploadEntry>,
     pub replay_mode: String,
+    pub hotkey_devices: usize,
+    pub restart_count: u32,
+    pub last_exit: Option<String>,
+    pub recent_stderr: Vec<String>,
+    pub recording_secs: Option<u64>,
+    pub disk_used_bytes: Option<u64>,
+    pub disk_quota_bytes: Option<u64>,
+    pub disk_ring_bytes: Option<u64>,
+    pub active_profile: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +147,8 @@ pub struct CaptureSettingsPayload {
     pub fps: u32,
     pub target: String,
     pub audio_tracks: Vec<String>,
+    /// Comma-separated save button durations as typed, e.g. "30s, 1m, 2m"
+    pub save_durations: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,9 +156,12 @@ pub struct CaptureSettingsPayload {
 pub enum CaptureActionPayload {
     Toggle { enable: bool },
     Save { duration_secs: u32 },
+    Record { enable: bool },
     UpdateSettings { settings: CaptureSettingsPayload },
     UpdateMode { mode: String },
     FailedUpload { upload_action: String, id: String },
+    SaveProfile { game: String, settings: CaptureSettingsPayload },
+    DeleteProfile { game: String },
 }
 
 #[derive(Clone)]
@@ -155,18 +212,71 @@ impl OverlayHandle {
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn show_picker(
         &self,
         preview_path: Option<&std::path::Path>,
         default_title: &str,
         default_game: &str,
-        available_channels: &[String],
+        tracks: &[AudioTrack],
+        voice: &VoiceConfig,
+        ducking: &DuckingConfig,
+        loudness: &LoudnessConfig,
+        exports: &ExportPresets,
     ) -> Result<Option<PickerResult>> {
         let cmd = OverlayCommand::ShowPicker {
             preview_path: preview_path.map(|p| p.to_string_lossy().to_string()),
             default_title: default_title.to_string(),
             default_game: default_game.to_string(),
-            available_channels: available_channels.to_vec(),
+            audio_tracks: tracks
+                .iter()
+                .map(|track| PickerTrack {
+                    name: track.name.clone(),
+                    enabled: track.enabled,
+                    volume: track.weight,
+                })
+                .collect(),
+            // Without a voice track there's nothing to clean up
+            voice_presets: if tracks.iter().any(|track| track.role == TrackRole::Voice) {
+                voice
+                    .presets()
+                    .into_iter()
+                    .map(|preset| PickerOption {
+                        id: preset.as_str().to_string(),
+                        label: preset.label().to_string(),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            voice_preset: voice.default_preset.as_str().to_string(),
+            duck_audio: (tracks.iter().any(|track| track.role.is_speech())
+                && tracks.iter().any(|track| track.role == TrackRole::Game))
+            .then_some(ducking.enabled_by_default),
+            loudness_target: loudness.target_lufs,
+            normalize_loudness: loudness.enabled_by_default,
+            audio_exports: AudioExport::ALL
+                .into_iter()
+                .map(|export| PickerOption {
+                    id: export.as_str().to_string(),
+                    label: export.label().to_string(),
+                })
+                .collect(),
+            export_presets: exports
+                .presets
+                .iter()
+                .map(|preset| PickerOption {
+                    id: preset.name.clone(),
+                    label: preset.label(),
+                })
+                .collect(),
+            export_preset: exports.default_preset().name.clone(),
+            mixed_only_presets: exports
+                .presets
+                .iter()
+                .filter(|preset| preset.target_size_mb.is_some())
+                .map(|preset| preset.name.clone())
+                .collect(),
         };
 
         self.send_command(&cmd)?;
@@ -181,7 +291,12 @@ impl OverlayHandle {
                 title,
                 game,
                 action,
-                channels,
+                tracks,
+                voice_preset,
+                duck_audio,
+                normalize_loudness,
+                audio_export,
+                export_preset,
             } => {
                 let action = match action.as_str() {
                     "upload" => ActionChoice::Upload,
@@ -189,11 +304,22 @@ impl OverlayHandle {
                     "discard" => ActionChoice::Discard,
                     other => bail!("overlay returned unknown picker action: {other}"),
                 };
+                let voice_preset = voice_preset
+                    .parse()
+                    .context("overlay returned an unknown voice preset")?;
+                let audio_export = audio_export
+                    .parse()
+                    .context("overlay returned an unknown audio export")?;
                 Ok(Some(PickerResult {
                     title,
                     game,
                     action,
-                    channels,
+                    tracks,
+                    voice_preset,
+                    duck_audio,
+                    normalize_loudness,
+                    audio_export,
+                    export_preset,
                 }))
             }
             OverlayResponse::Cancelled => Ok(None),
@@ -204,11 +330,15 @@ impl OverlayHandle {
     pub fn show_trimmer(
         &self,
         video_path: &std::path::Path,
+        audio_path: Option<&std::path::Path>,
         duration: f64,
+        bookmarks: &[f64],
     ) -> Result<Option<TrimmerResult>> {
         let cmd = OverlayCommand::ShowTrimmer {
             video_path: video_path.to_string_lossy().to_string(),
+            audio_path: audio_path.map(|path| path.to_string_lossy().to_string()),
             duration,
+            bookmarks: bookmarks.to_vec(),
         };
 
         self.send_command(&cmd)?;
@@ -364,7 +494,13 @@ pub struct PickerResult {
     pub title: String,
     pub game: String,
     pub action: ActionChoice,
-    pub channels: Vec<String>,
+    pub tracks: Vec<PickerTrack>,
+    pub voice_preset: VoicePreset,
+    pub duck_audio: bool,
+    pub normalize_loudness: bool,
+    pub audio_export: AudioExport,
+    /// Name of the chosen export preset.
+    pub export_preset: String,
 }
 
 #[derive(Debug, Clone)]
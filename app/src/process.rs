@@ -1,42 +1,207 @@
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use tokio::process::Command;
 use std::time::Duration;
 
+use crate::audio::{AudioExport, AudioTrack, ChannelLayout, TrackMap, TrackRole};
 use crate::config::AppConfig;
-use crate::constants::CHANNEL_OPTIONS;
-use crate::ffmpeg::{probe_duration, run_with_progress};
+use crate::ffmpeg::{probe_duration, run_with_progress, run_with_progress_output};
+use crate::loudness::{self, LoudnessMeasurement};
 use crate::overlay::OverlayHandle;
 use crate::progress::{format_stage_detail, Stage};
+use crate::voice::VoicePreset;
 
-pub async fn mix_audio(
-    config: &AppConfig,
-    channels: &[String],
-    overlay: &OverlayHandle,
-) -> Result<PathBuf> {
-    let mut selected: Vec<String> = CHANNEL_OPTIONS
+/// Audio choices made in the picker.
+#[derive(Debug, Clone, Copy)]
+pub struct MixOptions {
+    pub voice_preset: VoicePreset,
+    pub duck_audio: bool,
+    pub normalize_loudness: bool,
+    pub audio_export: AudioExport,
+    /// Mix bitrate in kbps, from the export preset.
+    pub audio_bitrate: u32,
+}
+
+/// The audio half of an export: the mix graph plus the options that map and
+/// encode it, ready to join the video options of a single ffmpeg run.
+#[derive(Debug, Clone)]
+pub struct AudioMix {
+    tracks: Vec<AudioTrack>,
+    filters: MixFilters,
+    loudnorm: Option<String>,
+    /// Source tracks copied after the mix.
+    stems: Vec<AudioTrack>,
+    bitrate: u32,
+}
+
+impl AudioMix {
+    pub fn with_bitrate(mut self, bitrate: u32) -> Self {
+        self.bitrate = bitrate;
+        self
+    }
+
+    /// Drops the stems, leaving only the mix.
+    pub fn mixed_only(mut self) -> Self {
+        self.stems.clear();
+        self
+    }
+
+    /// Output options mixing the audio of ffmpeg input `input`. Map the
+    /// video before these so the mix stays the first audio stream.
+    pub fn args(&self, input: usize) -> Vec<String> {
+        let tracks: Vec<&AudioTrack> = self.tracks.iter().collect();
+        let mut args = vec![
+            "-filter_complex".to_string(),
+            build_filter_chain(&tracks, &self.filters, self.loudnorm.as_deref(), input),
+            "-map".to_string(),
+            "[aout]".to_string(),
+        ];
+        for track in &self.stems {
+            args.extend(["-map".to_string(), format!("{input}:a:{}", track.stream)]);
+        }
+        args.extend([
+            "-c:a:0".to_string(),
+            "aac".to_string(),
+            "-b:a:0".to_string(),
+            format!("{}k", self.bitrate),
+            "-ar:a:0".to_string(),
+            "48000".to_string(),
+            "-ac:a:0".to_string(),
+            "2".to_string(),
+        ]);
+        if !self.stems.is_empty() {
+            // The mix plays by default; stems are copied untouched for editing
+            args.extend([
+                "-metadata:s:a:0".to_string(),
+                "title=Mix".to_string(),
+                "-disposition:a:0".to_string(),
+                "default".to_string(),
+            ]);
+            for (index, track) in self.stems.iter().enumerate() {
+                let stream = index + 1;
+                args.extend([
+                    format!("-c:a:{stream}"),
+                    "copy".to_string(),
+                    format!("-metadata:s:a:{stream}"),
+                    format!("title={}", track.title()),
+                    format!("-disposition:a:{stream}"),
+                    "0".to_string(),
+                ]);
+            }
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+impl AudioMix {
+    /// The first audio stream alone, mixed without any processing.
+    pub(crate) fn first_stream() -> Self {
+        let game = TrackMap::default().tracks[2].clone();
+        Self {
+            tracks: vec![AudioTrack { stream: 0, ..game }],
+            filters: MixFilters {
+                limiter_linear: 1.0,
+                voice: None,
+                ducking: None,
+            },
+            loudnorm: None,
+            stems: Vec::new(),
+            bitrate: 128,
+        }
+    }
+}
+
+/// The tracks to mix: the enabled ones, or all of them if every one is muted.
+fn selected_tracks(track_map: &TrackMap) -> Vec<AudioTrack> {
+    let selected: Vec<AudioTrack> = track_map
+        .tracks
         .iter()
-        .filter_map(|candidate| {
-            let candidate = *candidate;
-            channels
-                .iter()
-                .any(|chosen| chosen.eq_ignore_ascii_case(candidate))
-                .then(|| candidate.to_string())
-        })
+        .filter(|track| track.enabled)
+        .cloned()
         .collect();
 
-    if channels.is_empty() || selected.is_empty() {
-        selected = CHANNEL_OPTIONS
-            .iter()
-            .map(|channel| channel.to_string())
-            .collect();
+    if selected.is_empty() {
+        eprintln!("[CLIPS_APP] Every audio track is muted, mixing all of them");
+        return track_map.tracks.clone();
+    }
+    selected
+}
+
+/// Builds the export mix for `start_time..end_time` of the source. With
+/// normalisation on, loudness is measured over just that range first and
+/// `on_progress` follows the analysis.
+pub async fn prepare_mix<F>(
+    config: &AppConfig,
+    track_map: &TrackMap,
+    options: &MixOptions,
+    start_time: f64,
+    end_time: f64,
+    on_progress: F,
+) -> Result<AudioMix>
+where
+    F: FnMut(f32),
+{
+    let mut mix = AudioMix {
+        tracks: selected_tracks(track_map),
+        filters: MixFilters {
+            limiter_linear: config.loudness.limiter_linear(),
+            voice: config.voice.filter(options.voice_preset),
+            ducking: options.duck_audio.then(|| config.ducking.filter()),
+        },
+        loudnorm: None,
+        stems: match options.audio_export {
+            AudioExport::Mixed => Vec::new(),
+            AudioExport::Stems => track_map.tracks.clone(),
+        },
+        bitrate: options.audio_bitrate,
+    };
+
+    if options.normalize_loudness {
+        let measured = measure_loudness(config, &mix, start_time, end_time, on_progress).await?;
+        if measured.is_silent() {
+            eprintln!("[CLIPS_APP] Mix is silent, skipping loudness normalisation");
+        } else {
+            eprintln!(
+                "[CLIPS_APP] Measured {:.1} LUFS, normalising to {} LUFS",
+                measured.input_i, config.loudness.target_lufs
+            );
+            mix.loudnorm = Some(config.loudness.normalize_filter(&measured));
+        }
     }
+    Ok(mix)
+}
+
+/// Writes a quick, audio-only mix of the chosen tracks over the whole source
+/// for the trimmer to play alongside the source video. It skips cleanup,
+/// ducking and normalisation, which only the exported range gets.
+pub async fn preview_mix(
+    config: &AppConfig,
+    track_map: &TrackMap,
+    overlay: &OverlayHandle,
+) -> Result<PathBuf> {
+    let tracks = selected_tracks(track_map);
+    let tracks: Vec<&AudioTrack> = tracks.iter().collect();
+    let filters = MixFilters {
+        limiter_linear: config.loudness.limiter_linear(),
+        voice: None,
+        ducking: None,
+    };
 
-    let limiter_linear = linear_from_db(std::env::var("LIM_DB").ok().as_deref().unwrap_or("-1.0"));
-    let weights = AudioWeights::from_env();
+    let total_duration = match probe_duration(&config.source).await {
+        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Some(Duration::from_secs_f64(seconds)),
+        Ok(_) => {
+            eprintln!("[CLIPS_APP] Ignoring non-finite duration for {}", config.source.display());
+            None
+        }
+        Err(err) => {
+            eprintln!("[CLIPS_APP] Failed to probe duration for {}: {err:?}", config.source.display());
+            None
+        }
+    };
 
-    let output_path = transformed_path(&config.source);
+    let output_path = preview_path(&config.source);
     let mut cmd = Command::new("ffmpeg");
     cmd.args([
         "-hide_banner",
@@ -49,42 +214,21 @@ pub async fn mix_audio(
         "-i",
         config.source.to_string_lossy().as_ref(),
         "-filter_complex",
-        &build_filter_chain(&selected, limiter_linear, &weights),
-        "-map",
-        "0:v:0",
+        &build_filter_chain(&tracks, &filters, None, 0),
         "-map",
         "[aout]",
-        "-c:v",
-        "copy",
+        "-vn",
         "-c:a",
         "aac",
         "-b:a",
-        "192k",
-        "-ar",
-        "48000",
-        "-ac",
-        "2",
-        "-movflags",
-        "+faststart",
+        "96k",
         output_path.to_string_lossy().as_ref(),
     ]);
 
-    let total_duration = match probe_duration(&config.source).await {
-        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Some(Duration::from_secs_f64(seconds)),
-        Ok(_) => {
-            eprintln!("[CLIPS_APP] Ignoring non-finite duration for {}", config.source.display());
-            None
-        }
-        Err(err) => {
-            eprintln!("[CLIPS_APP] Failed to probe duration for {}: {err:?}", config.source.display());
-            None
-        }
-    };
-
-    let _ = overlay.update(Stage::Transform, 0.0, "Preparing ffmpeg…");
+    let _ = overlay.update(Stage::Transform, 0.0, "Preparing preview…");
 
     run_with_progress(cmd, total_duration, |fraction| {
-        let detail = format_stage_detail(Stage::Transform, fraction, "encoded");
+        let detail = format_stage_detail(Stage::Transform, fraction, "previewed");
         let _ = overlay.update(Stage::Transform, fraction, detail);
     })
     .await?;
@@ -92,91 +236,165 @@ pub async fn mix_audio(
     Ok(output_path)
 }
 
-fn transformed_path(source: &Path) -> PathBuf {
+/// Runs the mix over `start_time..end_time` through `loudnorm` in analysis
+/// mode without writing anything.
+async fn measure_loudness<F>(
+    config: &AppConfig,
+    mix: &AudioMix,
+    start_time: f64,
+    end_time: f64,
+    on_progress: F,
+) -> Result<LoudnessMeasurement>
+where
+    F: FnMut(f32),
+{
+    let duration = end_time - start_time;
+    let tracks: Vec<&AudioTrack> = mix.tracks.iter().collect();
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args([
+        "-hide_banner",
+        // loudnorm prints its measurement at info level
+        "-loglevel",
+        "info",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-ss",
+        &start_time.to_string(),
+        "-t",
+        &duration.to_string(),
+        "-i",
+        config.source.to_string_lossy().as_ref(),
+        "-filter_complex",
+        &build_filter_chain(&tracks, &mix.filters, Some(&config.loudness.analysis_filter()), 0),
+        "-map",
+        "[aout]",
+        "-f",
+        "null",
+        "-",
+    ]);
+
+    let stderr = run_with_progress_output(cmd, Some(Duration::from_secs_f64(duration)), on_progress)
+        .await
+        .context("loudness analysis failed")?;
+
+    loudness::parse_measurement(&stderr)
+}
+
+fn preview_path(source: &Path) -> PathBuf {
     let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("clip");
-    let ext = source.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
-    source.with_file_name(format!("{stem}_transformed.{ext}"))
+    source.with_file_name(format!("{stem}_preview.m4a"))
 }
 
+/// Per-clip settings that shape the mix graph.
 #[derive(Debug, Clone)]
-struct AudioWeights {
-    mic: String,
-    discord: String,
-    game: String,
+struct MixFilters {
+    limiter_linear: f64,
+    /// Cleanup for the voice track.
+    voice: Option<String>,
+    /// Compressor applied to game tracks, keyed off voice and chat.
+    ducking: Option<String>,
 }
 
-impl AudioWeights {
-    fn from_env() -> Self {
-        Self {
-            mic: std::env::var("MIC_W").unwrap_or_else(|_| "1.0".into()),
-            discord: std::env::var("VC_W").unwrap_or_else(|_| "1.0".into()),
-            game: std::env::var("GAME_W").unwrap_or_else(|_| "1.0".into()),
-        }
-    }
+/// Mixes `tracks` from ffmpeg input `input` into `[aout]`. `post` runs after
+/// the limiter, before the final resample.
+fn build_filter_chain(
+    tracks: &[&AudioTrack],
+    mix: &MixFilters,
+    post: Option<&str>,
+    input: usize,
+) -> String {
+    const AUDIO_FILTER: &str = "aformat=sample_fmts=fltp:sample_rates=48000:channel_layouts=stereo";
+    // Mono sources are upmixed by duplicating the channel to both L+R
+    const MONO_FILTER: &str = "aformat=sample_fmts=fltp:sample_rates=48000,pan=stereo|FL<c0|FR<c0";
 
-    fn weight_for(&self, channel: &str) -> &str {
-        match channel {
-            "voice" => &self.mic,
-            "discord" => &self.discord,
-            "game" => &self.game,
-            _ => "1.0",
+    let mut sections = Vec::new();
+    let mut inputs = Vec::new();
+
+    // Ducking needs both something to duck and something to key it
+    let ducking = mix.ducking.as_deref().filter(|_| {
+        tracks.iter().any(|track| track.role.is_speech())
+            && tracks.iter().any(|track| track.role == TrackRole::Game)
+    });
+    let mut keys = Vec::new();
+
+    for track in tracks {
+        let filter = match track.layout {
+            ChannelLayout::Mono => MONO_FILTER,
+            ChannelLayout::Stereo => AUDIO_FILTER,
+        };
+        let cleanup = match &mix.voice {
+            Some(voice) if track.role == TrackRole::Voice => format!("{voice},"),
+            _ => String::new(),
+        };
+        let label = format!("[a{}]", track.stream);
+        let volume = format!("volume={}", track.weight);
+        if ducking.is_some() && track.role.is_speech() {
+            // Speech feeds both the mix and the ducking key, which is keyed
+            // off the level before the track's weight
+            let key = format!("[k{}]", track.stream);
+            let split = format!("[s{}]", track.stream);
+            sections.push(format!("[{input}:a:{}]{cleanup}{filter},asplit=2{split}{key}", track.stream));
+            sections.push(format!("{split}{volume}{label}"));
+            keys.push(key);
+        } else {
+            sections.push(format!("[{input}:a:{}]{cleanup}{filter},{volume}{label}", track.stream));
         }
+        inputs.push(label);
     }
-}
 
-fn build_filter_chain(channels: &[String], limiter_linear: f64, weights: &AudioWeights) -> String {
-    const AUDIO_FILTER: &str = "aformat=sample_fmts=fltp:sample_rates=48000:channel_layouts=stereo";
-    // Mic is mono - convert to stereo by duplicating the channel to both L+R
-    const MIC_FILTER: &str = "aformat=sample_fmts=fltp:sample_rates=48000,pan=stereo|FL<c0|FR<c0";
+    if let Some(ducking) = ducking {
+        let key = if keys.len() == 1 {
+            keys.remove(0)
+        } else {
+            sections.push(format!("{}amix=inputs={}:normalize=0[key]", keys.join(""), keys.len()));
+            "[key]".to_string()
+        };
 
-    let mut sections = Vec::new();
-    let mut inputs = Vec::new();
-    let mut mix_weights = Vec::new();
-
-    for channel in channels {
-        match channel.as_str() {
-            "voice" => {
-                sections.push(format!("[0:a:0]{MIC_FILTER}[mic]"));
-                inputs.push("[mic]".to_string());
-                mix_weights.push(weights.weight_for("voice").to_string());
-            }
-            "discord" => {
-                sections.push(format!("[0:a:1]{AUDIO_FILTER}[vc]"));
-                inputs.push("[vc]".to_string());
-                mix_weights.push(weights.weight_for("discord").to_string());
-            }
-            "game" => {
-                sections.push(format!("[0:a:2]{AUDIO_FILTER}[game]"));
-                inputs.push("[game]".to_string());
-                mix_weights.push(weights.weight_for("game").to_string());
-            }
-            _ => {}
+        let ducked: Vec<usize> = tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| track.role == TrackRole::Game)
+            .map(|(index, _)| index)
+            .collect();
+        let key_copies = if ducked.len() == 1 {
+            vec![key]
+        } else {
+            let copies = (0..ducked.len()).map(|n| format!("[key{n}]")).collect::<Vec<_>>();
+            sections.push(format!("{key}asplit={}{}", ducked.len(), copies.join("")));
+            copies
+        };
+
+        for (index, key) in ducked.into_iter().zip(key_copies) {
+            let output = format!("[d{}]", tracks[index].stream);
+            sections.push(format!("{}{key}{ducking}{output}", inputs[index]));
+            inputs[index] = output;
         }
     }
 
     if inputs.is_empty() {
-        sections.push(format!("[0:a:0]{AUDIO_FILTER}[mix]"));
+        sections.push(format!("[{input}:a:0]{AUDIO_FILTER}[mix]"));
         inputs.push("[mix]".to_string());
-        mix_weights.push("1.0".into());
     }
 
-    let limiter = format!("{:.6}", limiter_linear);
+    let limiter = format!("{:.6}", mix.limiter_linear);
+    let post = post.map(|filter| format!("{filter},")).unwrap_or_default();
 
     if inputs.len() == 1 {
         let input = &inputs[0];
         sections.push(format!(
-            "{input}alimiter=limit={limiter},{AUDIO_FILTER}[aout]",
+            "{input}alimiter=limit={limiter},{post}{AUDIO_FILTER}[aout]",
             input = input,
             limiter = limiter,
         ));
     } else {
+        // Tracks are summed at their own volume so weights above 100% stay
+        // louder; the limiter catches the peaks instead of amix renormalising
         let input_concat = inputs.join("");
-        let weights_expr = mix_weights.join("|");
         sections.push(format!(
-            "{inputs}amix=inputs={count}:duration=longest:normalize=1:weights={weights},alimiter=limit={limiter},{AUDIO_FILTER}[aout]",
+            "{inputs}amix=inputs={count}:duration=longest:normalize=0,alimiter=limit={limiter},{post}{AUDIO_FILTER}[aout]",
             inputs = input_concat,
             count = inputs.len(),
-            weights = weights_expr,
             limiter = limiter,
         ));
     }
@@ -184,8 +402,59 @@ fn build_filter_chain(channels: &[String], limiter_linear: f64, weights: &AudioW
     sections.join(";")
 }
 
-fn linear_from_db(db: &str) -> f64 {
-    db.parse::<f64>()
-        .map(|db| 10f64.powf(db / 20.0))
-        .unwrap_or(1.0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(stream: usize, role: TrackRole, weight: f32) -> AudioTrack {
+        AudioTrack {
+            name: format!("track{stream}"),
+            stream,
+            layout: ChannelLayout::Stereo,
+            weight,
+            enabled: true,
+            role,
+            title: None,
+        }
+    }
+
+    fn filters(ducking: Option<&str>) -> MixFilters {
+        MixFilters {
+            limiter_linear: 0.9,
+            voice: None,
+            ducking: ducking.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn single_track_keeps_its_weight() {
+        let game = track(0, TrackRole::Game, 1.5);
+        let chain = build_filter_chain(&[&game], &filters(None), None, 0);
+        assert!(chain.starts_with("[0:a:0]aformat="));
+        assert!(chain.contains(",volume=1.5[a0];[a0]alimiter=limit=0.900000,"));
+        assert!(!chain.contains("amix"));
+    }
+
+    #[test]
+    fn mix_sums_tracks_at_their_weights() {
+        let game = track(0, TrackRole::Game, 2.0);
+        let voice = track(1, TrackRole::Voice, 2.0);
+        let chain = build_filter_chain(&[&game, &voice], &filters(None), Some("loudnorm"), 1);
+        assert!(chain.contains("[1:a:0]aformat=sample_fmts=fltp:sample_rates=48000:channel_layouts=stereo,volume=2[a0]"));
+        assert!(chain.contains("volume=2[a1]"));
+        assert!(chain.contains(
+            "[a0][a1]amix=inputs=2:duration=longest:normalize=0,alimiter=limit=0.900000,loudnorm,"
+        ));
+        assert!(!chain.contains("weights="));
+    }
+
+    #[test]
+    fn ducking_key_ignores_the_voice_weight() {
+        let game = track(0, TrackRole::Game, 1.0);
+        let voice = track(1, TrackRole::Voice, 0.5);
+        let chain = build_filter_chain(&[&game, &voice], &filters(Some("sidechaincompress")), None, 0);
+        assert!(chain.contains("asplit=2[s1][k1];[s1]volume=0.5[a1]"));
+        assert!(chain.contains("[a0][k1]sidechaincompress[d0]"));
+        assert!(chain.contains("[d0][a1]amix=inputs=2"));
+    }
 }
@@ -0,0 +1,880 @@
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::convert::TryFrom;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::capture::{ReplaySettings, ReplayStorage};
+use crate::ffmpeg;
+use crate::metadata;
+use crate::sessions;
+use crate::storage;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Recorder stderr lines kept for the status view.
+const LOG_TAIL_LINES: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecorderState {
+    Stopped,
+    Running,
+    /// The recorder died on its own; reported once, then `Stopped`.
+    Exited(String),
+}
+
+/// Something that keeps a rolling replay buffer and can write out its tail.
+pub trait RecorderBackend: Send {
+    fn name(&self) -> &'static str;
+
+    /// Starts recording. Diagnostic output should be appended to `log`.
+    fn spawn(&mut self, settings: &ReplaySettings, log: RecorderLog) -> Result<()>;
+
+    /// Writes out at least the last `duration_secs` of the buffer (the whole
+    /// buffer for `None`) and returns the finished file. The caller trims
+    /// anything longer than requested.
+    fn save_window(&mut self, duration_secs: Option<u32>) -> BoxFuture<'_, Result<PathBuf>>;
+
+    /// Starts a full-length recording alongside the replay buffer.
+    fn start_recording(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    /// Finishes the recording started by `start_recording` and returns the file.
+    fn stop_recording(&mut self) -> BoxFuture<'_, Result<PathBuf>>;
+
+    /// Forgets a recording cut short by the recorder exiting and returns
+    /// whatever was written of it. Called before the recorder is respawned.
+    fn recover_recording(&mut self) -> BoxFuture<'_, Option<PathBuf>>;
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    fn status(&mut self) -> Result<RecorderState>;
+}
+
+/// Which backend capture mode records with.
+#[derive(Debug, Clone)]
+pub enum RecorderKind {
+    GpuScreenRecorder(PathBuf),
+    Ffmpeg,
+}
+
+impl RecorderKind {
+    pub fn build(&self) -> Box<dyn RecorderBackend> {
+        match self {
+            RecorderKind::GpuScreenRecorder(binary) => Box::new(GpuScreenRecorder::new(binary.clone())),
+            RecorderKind::Ffmpeg => Box::new(FfmpegRingBuffer::new()),
+        }
+    }
+}
+
+/// The last few lines a recorder wrote to stderr.
+#[derive(Debug, Clone, Default)]
+pub struct RecorderLog {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl RecorderLog {
+    pub fn push(&self, line: String) {
+        if let Ok(mut lines) = self.lines.lock() {
+            if lines.len() == LOG_TAIL_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines
+            .lock()
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Forwards a child's stdout/stderr to our stderr, tagging each line.
+fn pipe_output<F>(child: &mut Child, tag: &'static str, log: RecorderLog, mut on_stdout: F)
+where
+    F: FnMut(&str) + Send + 'static,
+{
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                eprintln!("[{}][stdout] {}", tag, line);
+                on_stdout(&line);
+            }
+        });
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                eprintln!("[{}][stderr] {}", tag, line);
+                log.push(line);
+            }
+        });
+    }
+}
+
+fn pass_display_env(cmd: &mut Command) {
+    for var in ["WAYLAND_DISPLAY", "DISPLAY", "XDG_RUNTIME_DIR"] {
+        if let Ok(val) = std::env::var(var) {
+            cmd.env(var, val);
+        }
+    }
+}
+
+fn child_status(child: &mut Option<Child>) -> Result<RecorderState> {
+    let Some(running) = child.as_mut() else {
+        return Ok(RecorderState::Stopped);
+    };
+    match running.try_wait()? {
+        Some(status) => {
+            *child = None;
+            Ok(RecorderState::Exited(status.to_string()))
+        }
+        None => Ok(RecorderState::Running),
+    }
+}
+
+/// Sends SIGINT (both recorders finalise their output on it) and reaps.
+async fn interrupt_and_wait(child: &mut Option<Child>) {
+    if let Some(mut child) = child.take() {
+        if let Some(pid) = child.id() {
+            if let Ok(signal) = Signal::try_from(libc::SIGINT) {
+                let _ = signal::kill(Pid::from_raw(pid as i32), signal);
+            }
+        }
+        let _ = child.wait().await;
+    }
+}
+
+/// gpu-screen-recorder's fixed save windows (seconds) and the SIGRTMIN offset
+/// that triggers each.
+const SAVE_WINDOWS: &[(u32, i32)] = &[
+    (10, 1),
+    (30, 2),
+    (60, 3),
+    (300, 4),
+    (600, 5),
+    (1800, 6),
+];
+
+/// How long to trust the recorder to report a save on stdout before also
+/// looking for new files in the output directory.
+const SAVE_POLL_GRACE: Duration = Duration::from_secs(3);
+
+/// Whether `path` looks like something gpu-screen-recorder wrote: a replay
+/// (`Replay_<time>.mp4`) or a recording (`Video_<time>.mp4`).
+fn is_recorder_output(path: &Path) -> bool {
+    let named = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("Replay_") || name.starts_with("Video_"));
+    named && path.extension().is_some_and(|ext| ext == "mp4")
+}
+
+pub struct GpuScreenRecorder {
+    binary: PathBuf,
+    child: Option<Child>,
+    output_dir: PathBuf,
+    save_timeout_secs: u32,
+    /// Paths the recorder reports on stdout after a save.
+    saved_rx: Option<mpsc::UnboundedReceiver<PathBuf>>,
+    /// Files that can't be the recording in progress: everything present
+    /// when it started plus replays saved since.
+    recording_existing: Option<HashSet<PathBuf>>,
+}
+
+impl GpuScreenRecorder {
+    pub fn new(binary: PathBuf) -> Self {
+        Self {
+            binary,
+            child: None,
+            output_dir: PathBuf::new(),
+            save_timeout_secs: 30,
+            saved_rx: None,
+            recording_existing: None,
+        }
+    }
+
+    fn pid(&self) -> Result<i32> {
+        self.child
+            .as_ref()
+            .and_then(|child| child.id())
+            .map(|pid| pid as i32)
+            .context("replay recorder is not running")
+    }
+
+    fn send_signal(&self, raw_signal: i32) -> Result<()> {
+        // Use libc::kill directly for real-time signals since nix::Signal doesn't support them
+        let result = unsafe { libc::kill(self.pid()?, raw_signal) };
+        if result != 0 {
+            return Err(anyhow!("failed to signal gpu-screen-recorder: {}",
+                std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn drain_saved(&mut self) {
+        if let Some(rx) = self.saved_rx.as_mut() {
+            while rx.try_recv().is_ok() {}
+        }
+    }
+
+    fn build_command(&self, settings: &ReplaySettings) -> Command {
+        let mut cmd = Command::new(&self.binary);
+        cmd.kill_on_drop(true);
+        cmd.stdin(std::process::Stdio::null());
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::piped());
+
+        cmd.arg("-w").arg(&settings.target);
+        cmd.arg("-c").arg("mp4");
+        cmd.arg("-f").arg(settings.fps.to_string());
+        cmd.arg("-bm").arg("cbr");
+        cmd.arg("-q").arg(settings.bitrate.to_string());
+        cmd.arg("-r").arg(settings.buffer_seconds.to_string());
+        cmd.arg("-o").arg(settings.output_dir.to_string_lossy().to_string());
+        cmd.arg("-ro").arg(settings.output_dir.to_string_lossy().to_string());
+        cmd.arg("-replay-storage").arg(settings.replay_storage.as_str());
+        cmd.arg("-v").arg("no");
+
+        if settings.restore_portal_session {
+            cmd.arg("-restore-portal-session").arg("yes");
+        }
+
+        for track in &settings.audio_tracks {
+            if !track.trim().is_empty() {
+                cmd.arg("-a").arg(track);
+            }
+        }
+
+        pass_display_env(&mut cmd);
+        cmd
+    }
+
+    /// Waits for the recorder to report a new file on stdout (or, after a
+    /// grace period, for one to appear), then until that file stops growing
+    /// and ffprobe can read it.
+    async fn wait_for_saved_file(&mut self, existing: &HashSet<PathBuf>) -> Result<PathBuf> {
+        let timeout_secs = self.save_timeout_secs;
+        let deadline = Instant::now() + Duration::from_secs(timeout_secs as u64);
+        let output_dir = self.output_dir.clone();
+        let saved_rx = &mut self.saved_rx;
+
+        let path = tokio::time::timeout_at(deadline, async {
+            // Polling races the reported path (another file may appear first),
+            // so it's only a fallback for when stdout stays quiet
+            let mut poll = tokio::time::interval_at(
+                Instant::now() + SAVE_POLL_GRACE,
+                Duration::from_millis(250),
+            );
+            loop {
+                tokio::select! {
+                    Some(path) = recv_saved(saved_rx) => return Ok::<_, anyhow::Error>(path),
+                    _ = poll.tick() => {
+                        let current = list_files(&output_dir)?;
+                        if let Some(path) = current
+                            .into_iter()
+                            .find(|path| is_recorder_output(path) && !existing.contains(path))
+                        {
+                            return Ok(path);
+                        }
+                    }
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow!(
+            "gpu-screen-recorder did not write a replay within {}s (check its output above)",
+            timeout_secs
+        ))??;
+
+        eprintln!("[CLIPS_APP] Replay file detected: {}", path.display());
+        tokio::time::timeout_at(deadline, wait_until_complete(&path))
+            .await
+            .map_err(|_| anyhow!(
+                "replay {} was still being written after {}s",
+                path.display(),
+                timeout_secs
+            ))?;
+        Ok(path)
+    }
+}
+
+impl RecorderBackend for GpuScreenRecorder {
+    fn name(&self) -> &'static str {
+        "gpu-screen-recorder"
+    }
+
+    fn spawn(&mut self, settings: &ReplaySettings, log: RecorderLog) -> Result<()> {
+        let mut cmd = self.build_command(settings);
+        eprintln!("[CLIPS_APP] Spawning gpu-screen-recorder: {:?}", cmd);
+        let mut child = cmd.spawn().context("failed to spawn gpu-screen-recorder")?;
+
+        let (saved_tx, saved_rx) = mpsc::unbounded_channel();
+        let output_dir = settings.output_dir.clone();
+        pipe_output(&mut child, "GPU-SR", log, move |line| {
+            // The recorder prints the path of each replay it saves
+            let path = Path::new(line.trim());
+            if path.is_absolute() && path.starts_with(&output_dir) {
+                let _ = saved_tx.send(path.to_path_buf());
+            }
+        });
+
+        self.output_dir = settings.output_dir.clone();
+        self.save_timeout_secs = settings.save_timeout_secs;
+        self.saved_rx = Some(saved_rx);
+        // A recording doesn't survive the process that was writing it
+        self.recording_existing = None;
+        self.child = Some(child);
+        Ok(())
+    }
+
+    fn save_window(&mut self, duration_secs: Option<u32>) -> BoxFuture<'_, Result<PathBuf>> {
+        Box::pin(async move {
+            self.pid()?;
+
+            // The recorder only knows a few fixed windows: save the nearest
+            // larger one (or the whole buffer).
+            let window = duration_secs.and_then(|secs| {
+                SAVE_WINDOWS
+                    .iter()
+                    .find(|(window, _)| *window >= secs)
+            });
+            let raw_signal = match window {
+                Some((_, offset)) => libc::SIGRTMIN() + offset,
+                None => libc::SIGUSR1,
+            };
+
+            // Anything already present (including a recording in progress) or
+            // already reported can't be this save
+            self.drain_saved();
+            let existing = list_files(&self.output_dir)?;
+
+            self.send_signal(raw_signal)?;
+            let path = self.wait_for_saved_file(&existing).await?;
+            if let Some(recording_existing) = self.recording_existing.as_mut() {
+                recording_existing.insert(path.clone());
+            }
+            Ok(path)
+        })
+    }
+
+    fn start_recording(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            if self.recording_existing.is_some() {
+                bail!("a recording is already in progress");
+            }
+            self.drain_saved();
+            let existing = list_files(&self.output_dir)?;
+            // SIGRTMIN toggles a regular recording (written to -ro) in replay mode
+            self.send_signal(libc::SIGRTMIN())?;
+            self.recording_existing = Some(existing);
+            Ok(())
+        })
+    }
+
+    fn stop_recording(&mut self) -> BoxFuture<'_, Result<PathBuf>> {
+        Box::pin(async move {
+            let existing = self
+                .recording_existing
+                .take()
+                .context("no recording in progress")?;
+            self.send_signal(libc::SIGRTMIN())?;
+            self.wait_for_saved_file(&existing).await
+        })
+    }
+
+    fn recover_recording(&mut self) -> BoxFuture<'_, Option<PathBuf>> {
+        Box::pin(async move {
+            let existing = self.recording_existing.take()?;
+            // Names carry the start time, so the newest new file is the recording
+            list_files(&self.output_dir)
+                .ok()?
+                .into_iter()
+                .filter(|path| is_recorder_output(path) && !existing.contains(path))
+                .max()
+        })
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            interrupt_and_wait(&mut self.child).await;
+            self.saved_rx = None;
+            self.recording_existing = None;
+            Ok(())
+        })
+    }
+
+    fn status(&mut self) -> Result<RecorderState> {
+        child_status(&mut self.child)
+    }
+}
+
+async fn recv_saved(rx: &mut Option<mpsc::UnboundedReceiver<PathBuf>>) -> Option<PathBuf> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Polls until the file size is unchanged between checks and the container
+/// probes as valid.
+async fn wait_until_complete(path: &Path) {
+    let mut last_size = None;
+    loop {
+        let size = std::fs::metadata(path).map(|m| m.len()).ok();
+        if size.is_some_and(|size| size > 0)
+            && size == last_size
+            && ffmpeg::probe_duration(path).await.is_ok()
+        {
+            return;
+        }
+        last_size = size;
+        sleep(Duration::from_millis(500)).await;
+    }
+}
+
+fn list_files(dir: &Path) -> Result<HashSet<PathBuf>> {
+    let mut files = HashSet::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("reading replay output directory {:?}", dir))?
+    {
+        let path = entry?.path();
+        // Sidecars are written next to saved clips and aren't saves themselves
+        if path.is_file() && !metadata::is_sidecar(&path) {
+            files.insert(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Length of each ring buffer segment; saves are accurate to one segment
+/// before the final cut.
+const SEGMENT_SECS: u32 = 2;
+
+/// Keeps the replay buffer as a ring of short MPEG-TS segments written by
+/// ffmpeg's segment muxer, for machines without gpu-screen-recorder. Video
+/// comes from x11grab (XWayland on Wayland sessions), audio from PulseAudio.
+pub struct FfmpegRingBuffer {
+    child: Option<Child>,
+    ring_dir: PathBuf,
+    settings: Option<ReplaySettings>,
+    /// A second ffmpeg writing a full-length recording, and its output.
+    recording: Option<(Child, PathBuf)>,
+}
+
+impl FfmpegRingBuffer {
+    pub fn new() -> Self {
+        Self {
+            child: None,
+            ring_dir: PathBuf::new(),
+            settings: None,
+            recording: None,
+        }
+    }
+
+    fn ring_dir_for(settings: &ReplaySettings) -> PathBuf {
+        match settings.replay_storage {
+            ReplayStorage::Ram => {
+                let base = std::env::var_os("XDG_RUNTIME_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("/dev/shm"));
+                base.join(format!("clips-replay-ring-{}", std::process::id()))
+            }
+            ReplayStorage::Disk => settings.output_dir.join(storage::RING_DIR_NAME),
+        }
+    }
+
+    /// Maps a gpu-screen-recorder style track name onto a PulseAudio source.
+    fn pulse_source(track: &str) -> Option<String> {
+        match track {
+            "default_input" => Some("default".to_string()),
+            "default_output" => Some("@DEFAULT_MONITOR@".to_string()),
+            other if other.starts_with("app:") || other.starts_with("app-inverse:") => {
+                eprintln!(
+                    "[CLIPS_APP] ffmpeg recorder cannot capture per-application audio, skipping track '{}'",
+                    other
+                );
+                None
+            }
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// An ffmpeg command with the capture inputs and encoders set up; the
+    /// caller adds the output.
+    fn capture_command(settings: &ReplaySettings) -> Command {
+        let display = if settings.target.starts_with(':') {
+            settings.target.clone()
+        } else {
+            std::env::var("DISPLAY").unwrap_or_else(|_| ":0".to_string())
+        };
+
+        let mut cmd = Command::new("ffmpeg");
+        cmd.kill_on_drop(true);
+        cmd.stdin(std::process::Stdio::null());
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::piped());
+        cmd.args(["-hide_banner", "-loglevel", "warning", "-nostats"]);
+
+        cmd.args(["-f", "x11grab", "-framerate"])
+            .arg(settings.fps.to_string())
+            .arg("-i")
+            .arg(&display);
+        let sources: Vec<String> = settings
+            .audio_tracks
+            .iter()
+            .filter_map(|track| Self::pulse_source(track.trim()))
+            .collect();
+        for source in &sources {
+            cmd.args(["-f", "pulse", "-i", source]);
+        }
+
+        cmd.args(["-map", "0:v"]);
+        for index in 1..=sources.len() {
+            cmd.arg("-map").arg(format!("{index}:a"));
+        }
+
+        // A keyframe at every segment boundary keeps each segment decodable alone
+        cmd.args(["-c:v", "libx264", "-preset", "veryfast", "-pix_fmt", "yuv420p"])
+            .arg("-b:v")
+            .arg(format!("{}k", settings.bitrate))
+            .arg("-force_key_frames")
+            .arg(format!("expr:gte(t,n_forced*{SEGMENT_SECS})"));
+        cmd.args(["-c:a", "aac", "-b:a", "160k"]);
+
+        pass_display_env(&mut cmd);
+        cmd
+    }
+
+    fn build_command(&self, settings: &ReplaySettings) -> Command {
+        let segments = settings.buffer_seconds.div_ceil(SEGMENT_SECS) + 1;
+        let mut cmd = Self::capture_command(settings);
+        cmd.args(["-f", "segment", "-segment_format", "mpegts", "-reset_timestamps", "1"])
+            .arg("-segment_time")
+            .arg(SEGMENT_SECS.to_string())
+            .arg("-segment_wrap")
+            .arg(segments.to_string())
+            .arg(self.ring_dir.join("segment_%05d.ts"));
+        cmd
+    }
+
+    /// Completed segments, oldest first. The newest one is still being
+    /// written and is left out.
+    fn completed_segments(&self) -> Result<Vec<PathBuf>> {
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(&self.ring_dir)
+            .with_context(|| format!("reading replay ring directory {:?}", self.ring_dir))?
+        {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("ts") {
+                continue;
+            }
+            let modified = entry.metadata()?.modified()?;
+            segments.push((modified, path));
+        }
+        segments.sort();
+        segments.pop();
+        Ok(segments.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+impl Default for FfmpegRingBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecorderBackend for FfmpegRingBuffer {
+    fn name(&self) -> &'static str {
+        "ffmpeg"
+    }
+
+    fn spawn(&mut self, settings: &ReplaySettings, log: RecorderLog) -> Result<()> {
+        self.ring_dir = Self::ring_dir_for(settings);
+        self.settings = Some(settings.clone());
+        // Segments from a previous run would be spliced into the next save
+        let _ = std::fs::remove_dir_all(&self.ring_dir);
+        std::fs::create_dir_all(&self.ring_dir)
+            .with_context(|| format!("creating replay ring directory {:?}", self.ring_dir))?;
+
+        let mut cmd = self.build_command(settings);
+        eprintln!("[CLIPS_APP] Spawning ffmpeg ring buffer: {:?}", cmd);
+        let mut child = cmd.spawn().context("failed to spawn ffmpeg")?;
+        pipe_output(&mut child, "FFMPEG-RB", log, |_| {});
+        self.child = Some(child);
+        // Normally finished by `recover_recording`; dropping kills the process
+        self.recording = None;
+        Ok(())
+    }
+
+    fn save_window(&mut self, duration_secs: Option<u32>) -> BoxFuture<'_, Result<PathBuf>> {
+        Box::pin(async move {
+            if self.child.is_none() {
+                bail!("replay recorder is not running");
+            }
+
+            let mut segments = self.completed_segments()?;
+            if let Some(secs) = duration_secs {
+                let wanted = secs.div_ceil(SEGMENT_SECS) as usize + 1;
+                let skip = segments.len().saturating_sub(wanted);
+                segments.drain(..skip);
+            }
+            if segments.is_empty() {
+                bail!("replay buffer is still empty");
+            }
+
+            let output_dir = &self
+                .settings
+                .as_ref()
+                .context("replay recorder is not running")?
+                .output_dir;
+            let output = output_dir.join(format!("Replay_{}.mp4", local_timestamp()));
+            eprintln!(
+                "[CLIPS_APP] Joining {} ring segments into {}",
+                segments.len(),
+                output.display()
+            );
+            ffmpeg::concat_files(&segments, &self.ring_dir.join("save.txt"), &output).await?;
+            Ok(output)
+        })
+    }
+
+    fn start_recording(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            if self.recording.is_some() {
+                bail!("a recording is already in progress");
+            }
+            let settings = self
+                .settings
+                .as_ref()
+                .filter(|_| self.child.is_some())
+                .context("replay recorder is not running")?;
+
+            let output = settings
+                .output_dir
+                .join(format!("Recording_{}.mp4", local_timestamp()));
+            let mut cmd = Self::capture_command(settings);
+            // Fragmented so a crash still leaves a playable file
+            cmd.args(["-movflags", "+frag_keyframe+empty_moov", "-y"])
+                .arg(&output);
+            eprintln!("[CLIPS_APP] Spawning ffmpeg recording: {:?}", cmd);
+            let mut child = cmd.spawn().context("failed to spawn ffmpeg recording")?;
+            pipe_output(&mut child, "FFMPEG-REC", RecorderLog::default(), |_| {});
+            self.recording = Some((child, output));
+            Ok(())
+        })
+    }
+
+    fn stop_recording(&mut self) -> BoxFuture<'_, Result<PathBuf>> {
+        Box::pin(async move {
+            let (child, output) = self.recording.take().context("no recording in progress")?;
+            interrupt_and_wait(&mut Some(child)).await;
+            if !output.is_file() {
+                bail!("ffmpeg did not write {}", output.display());
+            }
+            Ok(output)
+        })
+    }
+
+    fn recover_recording(&mut self) -> BoxFuture<'_, Option<PathBuf>> {
+        Box::pin(async move {
+            // The recording runs in its own ffmpeg, which may outlive the ring buffer
+            let (child, output) = self.recording.take()?;
+            interrupt_and_wait(&mut Some(child)).await;
+            output.is_file().then_some(output)
+        })
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            if let Some((child, _)) = self.recording.take() {
+                interrupt_and_wait(&mut Some(child)).await;
+            }
+            interrupt_and_wait(&mut self.child).await;
+            if !self.ring_dir.as_os_str().is_empty() {
+                let _ = std::fs::remove_dir_all(&self.ring_dir);
+            }
+            Ok(())
+        })
+    }
+
+    fn status(&mut self) -> Result<RecorderState> {
+        child_status(&mut self.child)
+    }
+}
+
+/// Local time as `YYYY-MM-DD_HH-MM-SS`, matching gpu-screen-recorder's names.
+fn local_timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    sessions::format_local_time_as(now, "%Y-%m-%d_%H-%M-%S")
+}
+
+/// What the scripted recorder does on its next spawn.
+#[derive(Debug, Clone)]
+pub enum ScriptedRun {
+    /// Keep running until stopped.
+    Run,
+    /// Report `status` as the exit reason after this many status polls.
+    ExitAfter { polls: u32, status: String },
+    /// Fail to start with this error.
+    FailSpawn(String),
+}
+
+/// A recorder that follows a script instead of running a process, for
+/// exercising the controller's supervision and save paths.
+#[derive(Default)]
+pub struct ScriptedBackend {
+    runs: VecDeque<ScriptedRun>,
+    saves: VecDeque<std::result::Result<PathBuf, String>>,
+    running: Option<ScriptedRun>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl ScriptedBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the behaviour of the next spawn. Once the queue is empty every
+    /// spawn behaves like `ScriptedRun::Run`.
+    pub fn with_run(mut self, run: ScriptedRun) -> Self {
+        self.runs.push_back(run);
+        self
+    }
+
+    /// Queues the result of the next save or finished recording.
+    pub fn with_save(mut self, result: std::result::Result<PathBuf, String>) -> Self {
+        self.saves.push_back(result);
+        self
+    }
+
+    /// Every call made on the backend, e.g. `spawn`, `save 30`, `stop`.
+    pub fn calls(&self) -> Arc<Mutex<Vec<String>>> {
+        self.calls.clone()
+    }
+
+    fn record(&self, call: String) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(call);
+        }
+    }
+}
+
+impl RecorderBackend for ScriptedBackend {
+    fn name(&self) -> &'static str {
+        "scripted"
+    }
+
+    fn spawn(&mut self, _settings: &ReplaySettings, log: RecorderLog) -> Result<()> {
+        self.record("spawn".to_string());
+        match self.runs.pop_front().unwrap_or(ScriptedRun::Run) {
+            ScriptedRun::FailSpawn(err) => {
+                log.push(err.clone());
+                Err(anyhow!(err))
+            }
+            run => {
+                self.running = Some(run);
+                Ok(())
+            }
+        }
+    }
+
+    fn save_window(&mut self, duration_secs: Option<u32>) -> BoxFuture<'_, Result<PathBuf>> {
+        Box::pin(async move {
+            self.record(match duration_secs {
+                Some(secs) => format!("save {secs}"),
+                None => "save full".to_string(),
+            });
+            if self.running.is_none() {
+                bail!("replay recorder is not running");
+            }
+            match self.saves.pop_front() {
+                Some(result) => result.map_err(|err| anyhow!(err)),
+                None => bail!("scripted backend has no save queued"),
+            }
+        })
+    }
+
+    fn start_recording(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            self.record("start recording".to_string());
+            if self.running.is_none() {
+                bail!("replay recorder is not running");
+            }
+            Ok(())
+        })
+    }
+
+    fn stop_recording(&mut self) -> BoxFuture<'_, Result<PathBuf>> {
+        Box::pin(async move {
+            self.record("stop recording".to_string());
+            match self.saves.pop_front() {
+                Some(result) => result.map_err(|err| anyhow!(err)),
+                None => bail!("scripted backend has no save queued"),
+            }
+        })
+    }
+
+    fn recover_recording(&mut self) -> BoxFuture<'_, Option<PathBuf>> {
+        Box::pin(async move {
+            self.record("recover recording".to_string());
+            self.saves.pop_front().and_then(Result::ok)
+        })
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            self.record("stop".to_string());
+            self.running = None;
+            Ok(())
+        })
+    }
+
+    fn status(&mut self) -> Result<RecorderState> {
+        match self.running.as_mut() {
+            None => Ok(RecorderState::Stopped),
+            Some(ScriptedRun::ExitAfter { polls, status }) => {
+                if *polls == 0 {
+                    let status = status.clone();
+                    self.running = None;
+                    return Ok(RecorderState::Exited(status));
+                }
+                *polls -= 1;
+                Ok(RecorderState::Running)
+            }
+            Some(_) => Ok(RecorderState::Running),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_recorder_output() {
+        assert!(is_recorder_output(Path::new("/clips/Replay_2024-05-01_20-15-02.mp4")));
+        assert!(is_recorder_output(Path::new("/clips/Video_2024-05-01_20-15-02.mp4")));
+        assert!(!is_recorder_output(Path::new("/clips/Replay_2024-05-01_20-15-02.mp4.json")));
+        assert!(!is_recorder_output(Path::new("/clips/Replay_2024-05-01_20-15-02.mkv")));
+        assert!(!is_recorder_output(Path::new("/clips/my_edit.mp4")));
+    }
+}
@@ -0,0 +1,267 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::capture::format_duration;
+use crate::games::DetectedGame;
+
+/// A replay or recording saved while a session was running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionClip {
+    pub path: PathBuf,
+    pub saved_at: u64,  // Unix timestamp
+    /// Where the clip ended up after the picker, if it was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processed: Option<PathBuf>,
+}
+
+/// One stretch of play, from the game being detected to it exiting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSession {
+    pub id: String,
+    pub game: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    pub started_at: u64,  // Unix timestamp
+    /// Unset while the session is running, or if the app quit before it ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+    #[serde(default)]
+    pub clips: Vec<SessionClip>,
+    /// Moments marked with the bookmark hotkey (Unix timestamps).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<u64>,
+}
+
+impl GameSession {
+    pub fn display_name(&self) -> String {
+        let start = format_local_time(self.started_at);
+        match self.ended_at {
+            Some(end) => format!(
+                "{} [{} - {}, {}]",
+                self.game,
+                start,
+                format_local_time(end),
+                format_duration(self.duration_secs.unwrap_or_default())
+            ),
+            None => format!("{} [{}, unfinished]", self.game, start),
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SessionLog {
+    pub sessions: Vec<GameSession>,
+}
+
+impl SessionLog {
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context("failed to read session log")?;
+        let log: SessionLog = serde_json::from_str(&contents)
+            .context("failed to parse session log")?;
+        Ok(log)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::config_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context("failed to create config directory")?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)
+            .context("failed to serialize session log")?;
+        fs::write(&path, contents)
+            .context("failed to write session log")?;
+        Ok(())
+    }
+
+    /// Opens a session for `game` and returns its ID.
+    pub fn start(&mut self, game: &DetectedGame, at: SystemTime) -> String {
+        let started_at = unix_secs(at);
+        let slug = game
+            .name
+            .chars()
+            .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("-")
+            .to_lowercase();
+        let id = format!("{started_at}-{}", slug.chars().take(20).collect::<String>());
+
+        self.sessions.push(GameSession {
+            id: id.clone(),
+            game: game.name.clone(),
+            appid: game.appid.clone(),
+            started_at,
+            ended_at: None,
+            duration_secs: None,
+            clips: Vec::new(),
+            bookmarks: Vec::new(),
+        });
+        id
+    }
+
+    pub fn end(&mut self, id: &str, at: SystemTime) {
+        if let Some(session) = self.get_mut(id) {
+            let ended_at = unix_secs(at);
+            session.ended_at = Some(ended_at);
+            session.duration_secs = Some(ended_at.saturating_sub(session.started_at));
+        }
+    }
+
+    pub fn add_clip(&mut self, id: &str, path: &Path) {
+        if let Some(session) = self.get_mut(id) {
+            session.clips.push(SessionClip {
+                path: path.to_path_buf(),
+                saved_at: unix_secs(SystemTime::now()),
+                processed: None,
+            });
+        }
+    }
+
+    pub fn add_bookmark(&mut self, id: &str, at: SystemTime) {
+        if let Some(session) = self.get_mut(id) {
+            session.bookmarks.push(unix_secs(at));
+        }
+    }
+
+    /// Records where a clip was filed after processing.
+    pub fn set_processed(&mut self, clip: &Path, processed: &Path) -> bool {
+        let Some(entry) = self
+            .sessions
+            .iter_mut()
+            .flat_map(|session| session.clips.iter_mut())
+            .find(|entry| entry.path == clip)
+        else {
+            return false;
+        };
+        entry.processed = Some(processed.to_path_buf());
+        true
+    }
+
+    pub fn get(&self, id: &str) -> Option<&GameSession> {
+        self.sessions.iter().find(|s| s.id == id)
+    }
+
+    fn get_mut(&mut self, id: &str) -> Option<&mut GameSession> {
+        self.sessions.iter_mut().find(|s| s.id == id)
+    }
+
+    /// The session a clip was saved in.
+    pub fn find_by_clip(&self, clip: &Path) -> Option<&GameSession> {
+        self.sessions
+            .iter()
+            .find(|session| session.clips.iter().any(|entry| entry.path == clip))
+    }
+
+    fn config_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".config/clips-app/sessions.json"))
+    }
+}
+
+/// Local time as `YYYY-MM-DD HH:MM`.
+pub fn format_local_time(unix_secs: u64) -> String {
+    format_local_time_as(unix_secs, "%Y-%m-%d %H:%M")
+}
+
+/// Local time formatted with a `strftime` pattern.
+pub fn format_local_time_as(unix_secs: u64, pattern: &str) -> String {
+    let Ok(pattern) = std::ffi::CString::new(pattern) else {
+        return String::new();
+    };
+    let time = unix_secs as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&time, &mut tm) };
+    let mut buffer = [0u8; 64];
+    let len = unsafe {
+        libc::strftime(buffer.as_mut_ptr().cast(), buffer.len(), pattern.as_ptr(), &tm)
+    };
+    String::from_utf8_lossy(&buffer[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn game(name: &str) -> DetectedGame {
+        DetectedGame {
+            name: name.to_string(),
+            appid: Some("1145360".to_string()),
+            pid: 42,
+            source: "steam",
+        }
+    }
+
+    #[test]
+    fn start_and_end_record_the_session_length() {
+        let mut log = SessionLog::default();
+        let id = log.start(&game("Hades: Battle Out of Hell!"), at(1_700_000_000));
+        assert_eq!(id, "1700000000-hades-battle-out-of-");
+
+        let session = log.get(&id).unwrap();
+        assert_eq!(session.appid.as_deref(), Some("1145360"));
+        assert_eq!(session.ended_at, None);
+        assert!(session.display_name().ends_with(", unfinished]"));
+
+        log.end(&id, at(1_700_005_400));
+        let session = log.get(&id).unwrap();
+        assert_eq!(session.ended_at, Some(1_700_005_400));
+        assert_eq!(session.duration_secs, Some(5400));
+        assert!(session.display_name().ends_with(", 1h30m]"));
+
+        // Unknown IDs are ignored
+        log.end("missing", at(1_700_009_000));
+        assert_eq!(log.sessions.len(), 1);
+    }
+
+    #[test]
+    fn clips_are_found_by_path() {
+        let mut log = SessionLog::default();
+        let first = log.start(&game("Hades"), at(1_700_000_000));
+        let second = log.start(&game("Celeste"), at(1_700_010_000));
+        log.add_clip(&first, Path::new("/clips/Replay_a.mp4"));
+        log.add_clip(&second, Path::new("/clips/Replay_b.mp4"));
+
+        let found = log.find_by_clip(Path::new("/clips/Replay_b.mp4")).unwrap();
+        assert_eq!(found.id, second);
+        assert!(log.find_by_clip(Path::new("/clips/Replay_c.mp4")).is_none());
+
+        assert!(log.set_processed(Path::new("/clips/Replay_a.mp4"), Path::new("/processed/a.mp4")));
+        assert!(!log.set_processed(Path::new("/clips/Replay_c.mp4"), Path::new("/processed/c.mp4")));
+        let clip = &log.get(&first).unwrap().clips[0];
+        assert_eq!(clip.processed.as_deref(), Some(Path::new("/processed/a.mp4")));
+    }
+
+    #[test]
+    fn formats_local_time_with_a_pattern() {
+        let formatted = format_local_time(1_700_000_000);
+        assert_eq!(formatted.len(), "2023-11-14 22:13".len());
+        assert_eq!(format_local_time_as(1_700_000_000, "%Y"), formatted[..4]);
+        assert_eq!(format_local_time_as(1_700_000_000, "%Y-%m-%d_%H-%M-%S").len(), 19);
+    }
+}
@@ -1,10 +1,11 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::capture::ReplaySettings;
+use crate::capture::{ReplaySettings, DEFAULT_SAVE_DURATIONS};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ReplayMode {
@@ -19,10 +20,16 @@ pub struct PersistedSettings {
     pub fps: u32,
     pub target: String,
     pub audio_tracks: Vec<String>,
+    #[serde(default = "default_save_durations")]
+    pub save_durations: Vec<u32>,
     pub replay_mode: ReplayMode,
     pub replay_enabled: bool,
 }
 
+fn default_save_durations() -> Vec<u32> {
+    DEFAULT_SAVE_DURATIONS.to_vec()
+}
+
 impl PersistedSettings {
     pub fn load() -> Result<Option<Self>> {
         let path = Self::config_path()?;
@@ -63,6 +70,7 @@ impl PersistedSettings {
             fps: settings.fps,
             target: settings.target.clone(),
             audio_tracks: settings.audio_tracks.clone(),
+            save_durations: settings.save_durations.clone(),
             replay_mode,
             replay_enabled,
         }
@@ -74,6 +82,7 @@ impl PersistedSettings {
         settings.fps = self.fps;
         settings.target = self.target.clone();
         settings.audio_tracks = self.audio_tracks.clone();
+        settings.save_durations = self.save_durations.clone();
     }
     
     fn config_path() -> Result<PathBuf> {
@@ -83,3 +92,163 @@ impl PersistedSettings {
     }
 }
 
+/// Capture overrides for one game. Unset fields keep the base settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_seconds: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fps: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tracks: Option<Vec<String>>,
+}
+
+impl CaptureProfile {
+    pub fn apply(&self, settings: &mut ReplaySettings) {
+        if let Some(buffer_seconds) = self.buffer_seconds {
+            settings.buffer_seconds = buffer_seconds;
+        }
+        if let Some(bitrate) = self.bitrate {
+            settings.bitrate = bitrate;
+        }
+        if let Some(fps) = self.fps {
+            settings.fps = fps;
+        }
+        if let Some(target) = &self.target {
+            settings.target = target.clone();
+        }
+        if let Some(audio_tracks) = &self.audio_tracks {
+            settings.audio_tracks = audio_tracks.clone();
+        }
+    }
+}
+
+/// Per-game capture profiles, keyed by detected game name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameProfiles {
+    pub profiles: BTreeMap<String, CaptureProfile>,
+}
+
+impl GameProfiles {
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context("failed to read game profiles file")?;
+        let profiles: GameProfiles = serde_json::from_str(&contents)
+            .context("failed to parse game profiles file")?;
+        Ok(profiles)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::config_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context("failed to create config directory")?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)
+            .context("failed to serialize game profiles")?;
+        fs::write(&path, contents)
+            .context("failed to write game profiles file")?;
+
+        eprintln!("[CLIPS_APP] Saved {} game profiles to {:?}", self.profiles.len(), path);
+        Ok(())
+    }
+
+    /// Looks up a profile by game name, ignoring case. Returns the stored name
+    /// along with the profile.
+    pub fn get(&self, game: &str) -> Option<(&str, &CaptureProfile)> {
+        self.profiles
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(game.trim()))
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+
+    pub fn set(&mut self, game: &str, profile: CaptureProfile) {
+        self.remove(game);
+        self.profiles.insert(game.trim().to_string(), profile);
+    }
+
+    pub fn remove(&mut self, game: &str) -> Option<CaptureProfile> {
+        let name = self.get(game)?.0.to_string();
+        self.profiles.remove(&name)
+    }
+
+    fn config_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".config/clips-app/profiles.json"))
+    }
+}
+
+/// Volume and mute state of one audio track as last set in the picker.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackLevel {
+    pub volume: f32,
+    pub enabled: bool,
+}
+
+/// Picker mix levels per game, keyed by game name and then track name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameMixes {
+    pub games: BTreeMap<String, BTreeMap<String, TrackLevel>>,
+}
+
+impl GameMixes {
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context("failed to read mix levels file")?;
+        let mixes: GameMixes = serde_json::from_str(&contents)
+            .context("failed to parse mix levels file")?;
+        Ok(mixes)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::config_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context("failed to create config directory")?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)
+            .context("failed to serialize mix levels")?;
+        fs::write(&path, contents)
+            .context("failed to write mix levels file")?;
+        Ok(())
+    }
+
+    /// Looks up a game's levels, ignoring case.
+    pub fn get(&self, game: &str) -> Option<&BTreeMap<String, TrackLevel>> {
+        self.games
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(game.trim()))
+            .map(|(_, levels)| levels)
+    }
+
+    pub fn set(&mut self, game: &str, levels: BTreeMap<String, TrackLevel>) {
+        self.games
+            .retain(|name, _| !name.eq_ignore_ascii_case(game.trim()));
+        self.games.insert(game.trim().to_string(), levels);
+    }
+
+    fn config_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".config/clips-app/mix_levels.json"))
+    }
+}
@@ -0,0 +1,356 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::process::Command;
+
+use crate::ffmpeg::{self, run_with_progress};
+use crate::process::AudioMix;
+
+/// Keyframes this close to a cut point count as on it.
+const KEYFRAME_EPSILON: f64 = 0.001;
+/// Copy seeks land on the keyframe before the seek point, so they aim just
+/// past the keyframe to survive rounding in the probed timestamps.
+const SEEK_NUDGE: f64 = 0.0005;
+/// Share of the progress taken by the video segments; mixing the audio in
+/// takes the rest.
+const SEGMENTS_SHARE: f64 = 0.9;
+/// Length of an AAC frame at 48 kHz, the granularity of the mixed audio.
+const AAC_FRAME_SECS: f64 = 1024.0 / 48_000.0;
+/// Allowed gap between the requested and the probed duration of a smart cut
+/// when the frame rate is unknown.
+const FALLBACK_TOLERANCE: f64 = 0.05;
+
+/// How stream-copied trims choose their cut points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimMode {
+    /// Cuts snap to the keyframe before each point.
+    Keyframe,
+    /// Re-encodes the partial GOPs at each end for exact cuts.
+    Smart,
+}
+
+impl FromStr for TrimMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "keyframe" => Ok(TrimMode::Keyframe),
+            "smart" => Ok(TrimMode::Smart),
+            other => Err(anyhow!("unknown trim mode '{}', expected 'smart' or 'keyframe'", other)),
+        }
+    }
+}
+
+/// A stretch of the trimmed video; only the ends are re-encoded.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: f64,
+    end: f64,
+    copy: bool,
+}
+
+impl Segment {
+    fn length(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Splits `start..end` at the first and last keyframes inside it. Without a
+/// whole GOP in range the entire cut is re-encoded.
+fn plan_segments(keyframes: &[f64], start: f64, end: f64) -> Vec<Segment> {
+    let first = keyframes
+        .iter()
+        .copied()
+        .find(|&keyframe| keyframe >= start - KEYFRAME_EPSILON && keyframe < end);
+    let last = keyframes
+        .iter()
+        .copied()
+        .rev()
+        .find(|&keyframe| keyframe <= end + KEYFRAME_EPSILON);
+
+    match (first, last) {
+        (Some(first), Some(last)) if last - first > KEYFRAME_EPSILON => {
+            let mut segments = Vec::new();
+            if first - start > KEYFRAME_EPSILON {
+                segments.push(Segment { start, end: first, copy: false });
+            }
+            let copy_end = if end - last > KEYFRAME_EPSILON { last } else { end };
+            segments.push(Segment { start: first, end: copy_end, copy: true });
+            if copy_end < end {
+                segments.push(Segment { start: last, end, copy: false });
+            }
+            segments
+        }
+        _ => vec![Segment { start, end, copy: false }],
+    }
+}
+
+/// The software encoder producing streams that concatenate with `codec`.
+fn encoder_for(codec: &str) -> Option<&'static str> {
+    match codec {
+        "h264" => Some("libx264"),
+        "hevc" => Some("libx265"),
+        _ => None,
+    }
+}
+
+/// Trims `input` to exactly `start_time..end_time` while re-encoding as
+/// little as possible: the keyframe-aligned middle is stream-copied and only
+/// the partial GOPs at either end are encoded. The audio is mixed with
+/// `audio` over the same range. Falls back to [`ffmpeg::trim_with_mix`] for
+/// unsupported codecs or when the result's duration doesn't match the cut,
+/// and returns which of the two made the clip.
+pub async fn smart_trim<F>(
+    input: &Path,
+    output: &Path,
+    start_time: f64,
+    end_time: f64,
+    audio: &AudioMix,
+    mut on_progress: F,
+) -> Result<TrimMode>
+where
+    F: FnMut(f32),
+{
+    let duration = end_time - start_time;
+    if !duration.is_finite() || duration <= 0.0 {
+        bail!("Trim duration must be positive");
+    }
+
+    let stream = ffmpeg::probe_video_stream(input).await?;
+    let Some(encoder) = encoder_for(&stream.codec) else {
+        eprintln!(
+            "[CLIPS_APP] Smart cut doesn't support {} video, trimming at keyframes",
+            stream.codec
+        );
+        ffmpeg::trim_with_mix(input, output, start_time, end_time, audio, on_progress).await?;
+        return Ok(TrimMode::Keyframe);
+    };
+
+    let keyframes = ffmpeg::probe_keyframes(input, start_time, end_time).await?;
+    let segments = plan_segments(&keyframes, start_time, end_time);
+    eprintln!(
+        "[CLIPS_APP] Smart cut: {}",
+        segments
+            .iter()
+            .map(|segment| format!(
+                "{} {:.3}-{:.3}",
+                if segment.copy { "copy" } else { "encode" },
+                segment.start,
+                segment.end
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let parts: Vec<PathBuf> = (0..segments.len())
+        .map(|index| output.with_extension(format!("part{index}.ts")))
+        .collect();
+    let video = output.with_extension("video.mp4");
+
+    let result = async {
+        let mut done = 0.0;
+        for (segment, part) in segments.iter().zip(&parts) {
+            let command = segment_command(input, part, segment, encoder, &stream.pix_fmt)?;
+            let length = segment.length();
+            run_with_progress(command, Some(Duration::from_secs_f64(length)), |fraction| {
+                let fraction = (done + f64::from(fraction) * length) / duration;
+                on_progress((fraction * SEGMENTS_SHARE) as f32);
+            })
+            .await
+            .with_context(|| format!("smart cut segment {:.3}-{:.3}", segment.start, segment.end))?;
+            done += length;
+        }
+        ffmpeg::concat_files(&parts, &output.with_extension("concat.txt"), &video).await?;
+        mux_audio(&video, input, output, start_time, duration, audio, |fraction| {
+            on_progress((SEGMENTS_SHARE + f64::from(fraction) * (1.0 - SEGMENTS_SHARE)) as f32);
+        })
+        .await
+    }
+    .await;
+
+    for path in parts.iter().chain([&video]) {
+        let _ = std::fs::remove_file(path);
+    }
+    result?;
+
+    // Within a frame, or the audio frame the mix may end on
+    let tolerance = stream
+        .frame_rate
+        .map_or(FALLBACK_TOLERANCE, |fps| (1.0 / fps).max(AAC_FRAME_SECS));
+    let probed = ffmpeg::probe_duration(output).await?;
+    if (probed - duration).abs() > tolerance {
+        eprintln!(
+            "[CLIPS_APP] Smart cut is {probed:.3}s instead of {duration:.3}s, trimming at keyframes"
+        );
+        ffmpeg::trim_with_mix(input, output, start_time, end_time, audio, on_progress).await?;
+        return Ok(TrimMode::Keyframe);
+    }
+    Ok(TrimMode::Smart)
+}
+
+/// Writes one segment's video to an MPEG-TS file, which carries the codec
+/// headers in-band so differently encoded parts join cleanly.
+fn segment_command(
+    input: &Path,
+    output: &Path,
+    segment: &Segment,
+    encoder: &str,
+    pix_fmt: &str,
+) -> Result<Command> {
+    let input_str = input
+        .to_str()
+        .context("input path is not valid UTF-8")?;
+    let output_str = output
+        .to_str()
+        .context("output path is not valid UTF-8")?;
+    let seek = if segment.copy { segment.start + SEEK_NUDGE } else { segment.start };
+
+    let mut command = Command::new("ffmpeg");
+    command.args([
+        "-hide_banner",
+        "-loglevel",
+        "warning",
+        "-y",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-ss",
+        &seek.to_string(),
+        "-i",
+        input_str,
+        "-t",
+        &segment.length().to_string(),
+        "-map",
+        "0:v:0",
+        "-an",
+    ]);
+    if segment.copy {
+        command.args(["-c:v", "copy"]);
+    } else {
+        // Short and visually lossless, so it doesn't stand out from the copy
+        command.args([
+            "-c:v", encoder, "-preset", "fast", "-crf", "14", "-pix_fmt", pix_fmt,
+        ]);
+    }
+    command.args(["-f", "mpegts", output_str]);
+    Ok(command)
+}
+
+/// Combines the cut video with the audio mixed over the same range.
+async fn mux_audio<F>(
+    video: &Path,
+    input: &Path,
+    output: &Path,
+    start_time: f64,
+    duration: f64,
+    audio: &AudioMix,
+    on_progress: F,
+) -> Result<()>
+where
+    F: FnMut(f32),
+{
+    let video_str = video
+        .to_str()
+        .context("video path is not valid UTF-8")?;
+    let input_str = input
+        .to_str()
+        .context("input path is not valid UTF-8")?;
+    let output_str = output
+        .to_str()
+        .context("output path is not valid UTF-8")?;
+
+    let mut command = Command::new("ffmpeg");
+    command.args([
+        "-hide_banner",
+        "-loglevel",
+        "warning",
+        "-y",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-i",
+        video_str,
+        "-ss",
+        &start_time.to_string(),
+        "-t",
+        &duration.to_string(),
+        "-i",
+        input_str,
+        "-map",
+        "0:v:0",
+    ]);
+    command.args(audio.args(1));
+    command.args(["-c:v", "copy", "-movflags", "+faststart", output_str]);
+    run_with_progress(command, Some(Duration::from_secs_f64(duration)), on_progress).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::testing;
+
+    const KEYFRAMES: [f64; 5] = [0.0, 2.0, 4.0, 6.0, 8.0];
+
+    fn plan(start: f64, end: f64) -> Vec<(f64, f64, bool)> {
+        plan_segments(&KEYFRAMES, start, end)
+            .iter()
+            .map(|segment| (segment.start, segment.end, segment.copy))
+            .collect()
+    }
+
+    #[test]
+    fn cut_on_a_keyframe_is_copied() {
+        assert_eq!(plan(2.0, 7.0), [(2.0, 6.0, true), (6.0, 7.0, false)]);
+        // Probed timestamps are rarely exact
+        assert_eq!(plan(2.0004, 7.0), [(2.0, 6.0, true), (6.0, 7.0, false)]);
+    }
+
+    #[test]
+    fn cut_inside_a_gop_encodes_the_ends() {
+        assert_eq!(
+            plan(1.0, 7.0),
+            [(1.0, 2.0, false), (2.0, 6.0, true), (6.0, 7.0, false)]
+        );
+    }
+
+    #[test]
+    fn range_without_a_whole_gop_is_encoded() {
+        assert_eq!(plan(3.0, 5.0), [(3.0, 5.0, false)]);
+        assert_eq!(plan(0.5, 3.0), [(0.5, 3.0, false)]);
+        assert_eq!(plan(1.0, 1.5), [(1.0, 1.5, false)]);
+    }
+
+    #[test]
+    fn end_on_a_keyframe_needs_no_tail() {
+        assert_eq!(plan(1.0, 6.0), [(1.0, 2.0, false), (2.0, 6.0, true)]);
+        assert_eq!(plan(2.0, 6.0), [(2.0, 6.0, true)]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs ffmpeg"]
+    async fn smart_trim_is_frame_accurate() {
+        let dir = testing::temp_dir("smart-trim");
+        let input = dir.join("input.mp4");
+        // A keyframe every second
+        testing::write_clip(&input, 10, testing::FPS).await.unwrap();
+        let frame = 1.0 / f64::from(testing::FPS);
+
+        for (index, (start, end)) in [(1.5, 6.25), (2.0, 4.0), (3.2, 3.7)].into_iter().enumerate() {
+            let output = dir.join(format!("cut{index}.mp4"));
+            let used = smart_trim(&input, &output, start, end, &AudioMix::first_stream(), |_| {})
+                .await
+                .unwrap();
+            // A keyframe cut would be off by up to a second, but it mustn't pass as smart
+            assert_eq!(used, TrimMode::Smart, "{start}-{end} fell back to a keyframe cut");
+            let duration = ffmpeg::probe_duration(&output).await.unwrap();
+            assert!(
+                (duration - (end - start)).abs() <= frame,
+                "{start}-{end} came out {duration}s long"
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
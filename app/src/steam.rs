@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::vdf;
+
+/// An installed Steam app, read from its `appmanifest_<appid>.acf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SteamApp {
+    pub appid: u32,
+    pub name: String,
+    /// Absolute path of the game's files under `steamapps/common`.
+    pub install_dir: PathBuf,
+    pub library: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryFolder {
+    pub path: PathBuf,
+    /// App IDs Steam lists as installed here. Empty for the old file format.
+    pub apps: HashSet<u32>,
+}
+
+/// Every Steam library on this machine, including ones on other drives.
+#[derive(Debug, Clone, Default)]
+pub struct SteamLibraries {
+    libraries: Vec<LibraryFolder>,
+}
+
+impl SteamLibraries {
+    /// Reads `libraryfolders.vdf` from each Steam install found under `$HOME`
+    /// (native and Flatpak).
+    pub fn discover() -> Self {
+        let mut libraries = Self::default();
+        for root in steam_roots() {
+            if let Err(err) = libraries.add_root(&root) {
+                eprintln!("[CLIPS_APP] Failed to read Steam libraries in {}: {err:#}", root.display());
+            }
+        }
+        libraries
+    }
+
+    pub fn from_root(root: &Path) -> Result<Self> {
+        let mut libraries = Self::default();
+        libraries.add_root(root)?;
+        Ok(libraries)
+    }
+
+    fn add_root(&mut self, root: &Path) -> Result<()> {
+        // The Steam install is always a library, even if the file is missing
+        self.push(LibraryFolder {
+            path: root.to_path_buf(),
+            apps: HashSet::new(),
+        });
+
+        let Some(path) = ["steamapps/libraryfolders.vdf", "config/libraryfolders.vdf"]
+            .iter()
+            .map(|relative| root.join(relative))
+            .find(|path| path.exists())
+        else {
+            return Ok(());
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        for library in parse_library_folders(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?
+        {
+            self.push(library);
+        }
+        Ok(())
+    }
+
+    /// Adds a library, merging with any entry for the same directory.
+    fn push(&mut self, library: LibraryFolder) {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let key = canonical(&library.path);
+        match self.libraries.iter_mut().find(|existing| canonical(&existing.path) == key) {
+            Some(existing) => existing.apps.extend(library.apps),
+            None => self.libraries.push(library),
+        }
+    }
+
+    pub fn libraries(&self) -> &[LibraryFolder] {
+        &self.libraries
+    }
+
+    /// Finds an installed app by ID, trying the library Steam lists it in
+    /// first and then every other library.
+    pub fn app(&self, appid: u32) -> Option<SteamApp> {
+        let (listed, others): (Vec<&LibraryFolder>, Vec<&LibraryFolder>) = self
+            .libraries
+            .iter()
+            .partition(|library| library.apps.contains(&appid));
+        listed.into_iter().chain(others).find_map(|library| {
+            let manifest = library
+                .path
+                .join(format!("steamapps/appmanifest_{appid}.acf"));
+            if !manifest.exists() {
+                return None;
+            }
+            read_manifest(&manifest, &library.path)
+                .map_err(|err| eprintln!("[CLIPS_APP] {err:#}"))
+                .ok()
+        })
+    }
+}
+
+fn steam_roots() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+    [
+        ".steam/steam",
+        ".local/share/Steam",
+        ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+    ]
+    .iter()
+    .map(|relative| home.join(relative))
+    .filter(|root| root.join("steamapps").is_dir())
+    .collect()
+}
+
+/// Parses `libraryfolders.vdf`, in both the current layout (one object per
+/// library with `path` and `apps`) and the old one (`"1" "/path"`).
+pub fn parse_library_folders(contents: &str) -> Result<Vec<LibraryFolder>> {
+    let document = vdf::parse(contents)?;
+    let folders = document
+        .get_object("libraryfolders")
+        .ok_or_else(|| anyhow!("missing \"libraryfolders\" section"))?;
+
+    let libraries = folders
+        .iter()
+        .filter(|(key, _)| key.parse::<u32>().is_ok())
+        .filter_map(|(_, value)| match value {
+            vdf::Value::String(path) => Some(LibraryFolder {
+                path: PathBuf::from(path),
+                apps: HashSet::new(),
+            }),
+            vdf::Value::Object(folder) => Some(LibraryFolder {
+                path: PathBuf::from(folder.get_str("path")?),
+                apps: folder
+                    .get_object("apps")
+                    .map(|apps| apps.iter().filter_map(|(appid, _)| appid.parse().ok()).collect())
+                    .unwrap_or_default(),
+            }),
+        })
+        .collect();
+    Ok(libraries)
+}
+
+/// Parses an `appmanifest_<appid>.acf` belonging to `library`.
+pub fn parse_manifest(contents: &str, library: &Path) -> Result<SteamApp> {
+    let document = vdf::parse(contents)?;
+    let state = document
+        .get_object("AppState")
+        .ok_or_else(|| anyhow!("missing \"AppState\" section"))?;
+    let field = |key: &str| {
+        state
+            .get_str(key)
+            .ok_or_else(|| anyhow!("missing \"{key}\" field"))
+    };
+
+    Ok(SteamApp {
+        appid: field("appid")?
+            .parse()
+            .context("invalid appid")?,
+        name: field("name")?.to_string(),
+        install_dir: library.join("steamapps/common").join(field("installdir")?),
+        library: library.to_path_buf(),
+    })
+}
+
+pub fn read_manifest(path: &Path, library: &Path) -> Result<SteamApp> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_manifest(&contents, library)
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = include_str!("../tests/fixtures/steam/appmanifest_1091500.acf");
+    const LIBRARY_FOLDERS: &str = include_str!("../tests/fixtures/steam/libraryfolders.vdf");
+    const LIBRARY_FOLDERS_OLD: &str = include_str!("../tests/fixtures/steam/libraryfolders_old.vdf");
+
+    #[test]
+    fn parses_manifest() {
+        let library = Path::new("/mnt/games/SteamLibrary");
+        let app = parse_manifest(MANIFEST, library).unwrap();
+        assert_eq!(app, SteamApp {
+            appid: 1091500,
+            name: "Cyberpunk 2077: \"Phantom Liberty\" Edition".to_string(),
+            install_dir: PathBuf::from("/mnt/games/SteamLibrary/steamapps/common/Cyberpunk 2077"),
+            library: library.to_path_buf(),
+        });
+    }
+
+    #[test]
+    fn manifest_needs_app_state() {
+        let err = parse_manifest(LIBRARY_FOLDERS, Path::new("/")).unwrap_err();
+        assert!(err.to_string().contains("AppState"));
+    }
+
+    #[test]
+    fn parses_library_folders() {
+        let libraries = parse_library_folders(LIBRARY_FOLDERS).unwrap();
+        assert_eq!(libraries, [
+            LibraryFolder {
+                path: PathBuf::from("/home/player/.local/share/Steam"),
+                apps: HashSet::from([228980, 1091500]),
+            },
+            LibraryFolder {
+                path: PathBuf::from("/mnt/games/SteamLibrary"),
+                apps: HashSet::from([730]),
+            },
+        ]);
+    }
+
+    #[test]
+    fn parses_old_library_folders() {
+        let libraries = parse_library_folders(LIBRARY_FOLDERS_OLD).unwrap();
+        assert_eq!(libraries, [
+            LibraryFolder {
+                path: PathBuf::from("/mnt/games/SteamLibrary"),
+                apps: HashSet::new(),
+            },
+            LibraryFolder {
+                path: PathBuf::from("/media/player/Backup \"Old\" Games"),
+                apps: HashSet::new(),
+            },
+        ]);
+    }
+}
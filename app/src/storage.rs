@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use walkdir::WalkDir;
+
+use crate::metadata as clip_metadata;
+
+/// Files touched more recently than this may still be written to.
+const MIN_PRUNE_AGE: Duration = Duration::from_secs(120);
+/// Where the ffmpeg recorder keeps an on-disk replay buffer, inside the
+/// output directory. It belongs to the recorder and is never pruned.
+pub const RING_DIR_NAME: &str = ".replay-ring";
+
+/// Limits on how much space replays and leftover raw files may take up.
+#[derive(Debug, Clone, Default)]
+pub struct StoragePolicy {
+    pub processed_dir: Option<PathBuf>,
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+}
+
+impl StoragePolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes.is_some() || self.max_age.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskUsage {
+    /// Space taken by the files the policy may prune.
+    pub used_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    /// Space taken by an on-disk replay buffer, outside the quota.
+    pub ring_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+    pub usage: DiskUsage,
+}
+
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Parses sizes like `500M`, `50G`, `1.5TiB` or a plain byte count. Units are
+/// binary (1K = 1024 bytes).
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid size '{}'", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        other => return Err(anyhow!("invalid size unit '{}' in '{}'", other, value)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Counts the files the quota applies to, i.e. the prune candidates, and the
+/// replay buffer separately. Finished clips aren't counted.
+pub fn usage(output_dir: &Path, policy: &StoragePolicy) -> DiskUsage {
+    DiskUsage {
+        used_bytes: candidates(output_dir, policy.processed_dir.as_deref())
+            .iter()
+            .map(|candidate| candidate.size)
+            .sum(),
+        quota_bytes: policy.max_bytes,
+        ring_bytes: dir_size(&output_dir.join(RING_DIR_NAME)),
+    }
+}
+
+/// Files the policy may delete: unsaved replays in `output_dir`, `_full.mp4`
+/// originals stranded in `processed_dir` by an interrupted run, and the
+/// `_raw.mp4` originals kept next to finished clips. Finished clips
+/// themselves are never candidates.
+fn candidates(output_dir: &Path, processed_dir: Option<&Path>) -> Vec<Candidate> {
+    let mut paths = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(output_dir) {
+        paths.extend(entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()));
+    }
+    if let Some(processed_dir) = processed_dir {
+        paths.extend(
+            WalkDir::new(processed_dir)
+                .max_depth(2)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.into_path())
+                .filter(|path| {
+                    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                    let top_level = path.parent() == Some(processed_dir);
+                    (top_level && name.ends_with("_full.mp4"))
+                        || (!top_level && name.ends_with("_raw.mp4"))
+                }),
+        );
+    }
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            // Sidecars go along with their clip
+            if !metadata.is_file() || clip_metadata::is_sidecar(&path) {
+                return None;
+            }
+            Some(Candidate {
+                path,
+                size: metadata.len(),
+                modified: metadata.modified().ok()?,
+            })
+        })
+        .collect()
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Deletes candidates older than the age limit, then the oldest remaining
+/// ones until usage fits the quota. Paths in `protected` are never removed,
+/// and nothing is removed for the quota when the files that may be can't
+/// bring usage under it.
+pub fn prune(output_dir: &Path, policy: &StoragePolicy, protected: &[PathBuf]) -> Result<PruneReport> {
+    let mut report = PruneReport {
+        usage: usage(output_dir, policy),
+        ..PruneReport::default()
+    };
+    if !policy.is_enabled() {
+        return Ok(report);
+    }
+
+    let protected: HashSet<PathBuf> = protected.iter().map(|path| canonical(path)).collect();
+    let now = SystemTime::now();
+    let age = |candidate: &Candidate| now.duration_since(candidate.modified).unwrap_or_default();
+
+    let mut candidates: Vec<Candidate> = candidates(output_dir, policy.processed_dir.as_deref())
+        .into_iter()
+        .filter(|candidate| age(candidate) >= MIN_PRUNE_AGE)
+        .filter(|candidate| !protected.contains(&canonical(&candidate.path)))
+        .collect();
+    candidates.sort_by_key(|candidate| candidate.modified);
+
+    let prunable_bytes: u64 = candidates.iter().map(|candidate| candidate.size).sum();
+    let quota_reachable = match policy.max_bytes {
+        Some(max_bytes) if report.usage.used_bytes.saturating_sub(prunable_bytes) > max_bytes => {
+            eprintln!(
+                "[CLIPS_APP] Storage quota can't be met: {} of {} bytes are recent or in use",
+                report.usage.used_bytes - prunable_bytes,
+                report.usage.used_bytes
+            );
+            false
+        }
+        _ => true,
+    };
+
+    for candidate in candidates {
+        let too_old = policy.max_age.is_some_and(|max_age| age(&candidate) > max_age);
+        let over_quota = quota_reachable
+            && policy
+                .max_bytes
+                .is_some_and(|max_bytes| report.usage.used_bytes > max_bytes);
+        if !too_old && !over_quota {
+            continue;
+        }
+
+        match std::fs::remove_file(&candidate.path) {
+            Ok(()) => {
+                eprintln!(
+                    "[CLIPS_APP] Pruned {} ({} bytes, {})",
+                    candidate.path.display(),
+                    candidate.size,
+                    if too_old { "too old" } else { "over quota" }
+                );
+                clip_metadata::remove_sidecar(&candidate.path);
+                report.usage.used_bytes = report.usage.used_bytes.saturating_sub(candidate.size);
+                report.freed_bytes += candidate.size;
+                report.removed.push(candidate.path);
+            }
+            Err(err) => {
+                eprintln!("[CLIPS_APP] Failed to prune {}: {}", candidate.path.display(), err);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::testing;
+
+    fn write_file(path: &Path, size: usize, age: Duration) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0u8; size]).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn usage_counts_only_prunable_files() {
+        let dir = testing::temp_dir("storage-usage");
+        let output_dir = dir.join("raw");
+        let processed_dir = dir.join("processed");
+        let old = MIN_PRUNE_AGE * 2;
+        write_file(&output_dir.join("replay.mp4"), 100, old);
+        write_file(&output_dir.join(RING_DIR_NAME).join("segment.ts"), 1000, old);
+        write_file(&processed_dir.join("stranded_full.mp4"), 50, old);
+        write_file(&processed_dir.join("Game").join("clip.mp4"), 500, old);
+        write_file(&processed_dir.join("Game").join("clip_raw.mp4"), 200, old);
+
+        let policy = StoragePolicy {
+            processed_dir: Some(processed_dir),
+            max_bytes: Some(1 << 20),
+            max_age: None,
+        };
+        let usage = usage(&output_dir, &policy);
+        assert_eq!(usage.used_bytes, 350);
+        assert_eq!(usage.ring_bytes, 1000);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prune_skips_an_unreachable_quota() {
+        let dir = testing::temp_dir("storage-quota");
+        let old = dir.join("old.mp4");
+        let older = dir.join("older.mp4");
+        write_file(&old, 300, MIN_PRUNE_AGE * 2);
+        write_file(&older, 300, MIN_PRUNE_AGE * 3);
+        write_file(&dir.join("recent.mp4"), 300, Duration::ZERO);
+        let protected = vec![old.clone()];
+
+        // Only `older` may go, which still leaves 600 bytes
+        let mut policy = StoragePolicy {
+            processed_dir: None,
+            max_bytes: Some(500),
+            max_age: None,
+        };
+        let report = prune(&dir, &policy, &protected).unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(report.usage.used_bytes, 900);
+
+        policy.max_bytes = Some(600);
+        let report = prune(&dir, &policy, &protected).unwrap();
+        assert_eq!(report.removed, [older]);
+        assert_eq!(report.usage.used_bytes, 600);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
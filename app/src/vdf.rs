@@ -0,0 +1,226 @@
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Object(Object),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            Value::Object(_) => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Value::Object(object) => Some(object),
+            Value::String(_) => None,
+        }
+    }
+}
+
+/// Keys in file order. Lookups are case-insensitive, as in Steam itself, and
+/// return the first match when a key repeats.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Object {
+    entries: Vec<(String, Value)>,
+}
+
+impl Object {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    pub fn get_object(&self, key: &str) -> Option<&Object> {
+        self.get(key).and_then(Value::as_object)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Parses a Valve KeyValues document (`.vdf`, `.acf`). The result holds the
+/// top-level keys, usually a single one such as `"AppState"`.
+pub fn parse(input: &str) -> Result<Object> {
+    let mut tokens = Tokenizer {
+        chars: input.chars().peekable(),
+        line: 1,
+    };
+    parse_object(&mut tokens, false)
+}
+
+#[derive(Debug)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+struct Tokenizer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl Tokenizer<'_> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        loop {
+            let Some(&c) = self.chars.peek() else {
+                return Ok(None);
+            };
+            match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                '/' if self.chars.clone().nth(1) == Some('/') => {
+                    while self.chars.peek().is_some_and(|&c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                // Platform conditionals like [$WIN32] are ignored
+                '[' => while self.bump().is_some_and(|c| c != ']') {},
+                '{' => {
+                    self.bump();
+                    return Ok(Some(Token::Open));
+                }
+                '}' => {
+                    self.bump();
+                    return Ok(Some(Token::Close));
+                }
+                '"' => return self.quoted().map(Some),
+                _ => return Ok(Some(self.unquoted())),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<Token> {
+        let line = self.line;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(Token::Str(value)),
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('\\' | '"')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => break,
+                },
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        bail!("unterminated string starting on line {}", line)
+    }
+
+    fn unquoted(&mut self) -> Token {
+        let mut value = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || matches!(c, '{' | '}' | '"') {
+                break;
+            }
+            value.push(c);
+            self.bump();
+        }
+        Token::Str(value)
+    }
+}
+
+fn parse_object(tokens: &mut Tokenizer, nested: bool) -> Result<Object> {
+    let mut object = Object::default();
+    loop {
+        let key = match tokens.next_token()? {
+            Some(Token::Str(key)) => key,
+            Some(Token::Close) if nested => return Ok(object),
+            None if !nested => return Ok(object),
+            Some(Token::Close) => bail!("unexpected '}}' on line {}", tokens.line),
+            Some(Token::Open) => bail!("expected a key but found '{{' on line {}", tokens.line),
+            None => bail!("unexpected end of input, missing '}}'"),
+        };
+        let value = match tokens.next_token()? {
+            Some(Token::Str(value)) => Value::String(value),
+            Some(Token::Open) => Value::Object(parse_object(tokens, true)?),
+            _ => bail!("key '{}' on line {} has no value", key, tokens.line),
+        };
+        object.entries.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_objects() {
+        let document = parse(include_str!("../tests/fixtures/steam/appmanifest_1091500.acf")).unwrap();
+        let state = document.get_object("appstate").unwrap();
+        assert_eq!(state.get_str("appid"), Some("1091500"));
+        assert_eq!(state.get_str("INSTALLDIR"), Some("Cyberpunk 2077"));
+        assert_eq!(
+            state.get_str("LauncherPath"),
+            Some(r"C:\Program Files (x86)\Steam\steam.exe")
+        );
+        let depot = state
+            .get_object("InstalledDepots")
+            .and_then(|depots| depots.get_object("1091501"))
+            .unwrap();
+        assert_eq!(depot.get_str("size"), Some("70000000000"));
+        assert_eq!(state.get_object("name"), None);
+    }
+
+    #[test]
+    fn handles_escapes_comments_and_conditionals() {
+        let document = parse(concat!(
+            "// header comment\n",
+            "root {\n",
+            "  \"quoted\" \"say \\\"hi\\\"\\n\" // trailing comment\n",
+            "  \"unknown\" \"C:\\x\"\n",
+            "  bare value [$WIN32]\n",
+            "  \"bare\" \"second\"\n",
+            "}\n",
+        ))
+        .unwrap();
+        let root = document.get_object("root").unwrap();
+        assert_eq!(root.get_str("quoted"), Some("say \"hi\"\n"));
+        assert_eq!(root.get_str("unknown"), Some("C:\\x"));
+        // The first of a repeated key wins
+        assert_eq!(root.get_str("BARE"), Some("value"));
+        assert_eq!(root.iter().count(), 4);
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        let err = parse("\"a\" {\n  \"b\" \"c\n}").unwrap_err();
+        assert!(err.to_string().contains("unterminated string starting on line 2"));
+        assert!(parse("\"a\" { \"b\" \"c\"").is_err());
+        assert!(parse("\"a\" }").is_err());
+        assert!(parse("\"a\"").is_err());
+        assert!(parse("").unwrap().is_empty());
+    }
+}
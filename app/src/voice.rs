@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, ensure, Context, Result};
+
+/// Cleanup applied to tracks marked as voice before they're mixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoicePreset {
+    Off,
+    /// High-pass and gentle FFT denoise; safe for any mic.
+    Light,
+    /// Stronger denoise plus a gate to silence the room between phrases.
+    Standard,
+    /// Adds non-local means denoise for noisy rooms and fans.
+    Strong,
+    /// RNNoise neural denoise; needs `--rnnoise-model`.
+    Rnnoise,
+}
+
+impl VoicePreset {
+    pub const ALL: [VoicePreset; 5] = [
+        VoicePreset::Off,
+        VoicePreset::Light,
+        VoicePreset::Standard,
+        VoicePreset::Strong,
+        VoicePreset::Rnnoise,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VoicePreset::Off => "off",
+            VoicePreset::Light => "light",
+            VoicePreset::Standard => "standard",
+            VoicePreset::Strong => "strong",
+            VoicePreset::Rnnoise => "rnnoise",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VoicePreset::Off => "Off",
+            VoicePreset::Light => "Light (high-pass, denoise)",
+            VoicePreset::Standard => "Standard (denoise, gate)",
+            VoicePreset::Strong => "Strong (heavy denoise, gate)",
+            VoicePreset::Rnnoise => "RNNoise (neural denoise, gate)",
+        }
+    }
+}
+
+impl FromStr for VoicePreset {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        VoicePreset::ALL
+            .into_iter()
+            .find(|preset| preset.as_str().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown voice preset '{}', expected one of: {}",
+                    value,
+                    VoicePreset::ALL.map(VoicePreset::as_str).join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoiceConfig {
+    /// Preset the picker starts on.
+    pub default_preset: VoicePreset,
+    pub rnnoise_model: Option<PathBuf>,
+}
+
+impl VoiceConfig {
+    pub fn new(default_preset: VoicePreset, rnnoise_model: Option<PathBuf>) -> Result<Self> {
+        let rnnoise_model = rnnoise_model
+            .map(|path| {
+                path.canonicalize()
+                    .with_context(|| format!("RNNoise model {} missing", path.display()))
+            })
+            .transpose()?;
+        ensure!(
+            default_preset != VoicePreset::Rnnoise || rnnoise_model.is_some(),
+            "the rnnoise voice preset needs --rnnoise-model"
+        );
+        Ok(Self {
+            default_preset,
+            rnnoise_model,
+        })
+    }
+
+    /// Presets usable with this configuration.
+    pub fn presets(&self) -> Vec<VoicePreset> {
+        VoicePreset::ALL
+            .into_iter()
+            .filter(|preset| *preset != VoicePreset::Rnnoise || self.rnnoise_model.is_some())
+            .collect()
+    }
+
+    /// The filters for `preset`, or `None` when it leaves the voice alone.
+    pub fn filter(&self, preset: VoicePreset) -> Option<String> {
+        let filter = match preset {
+            VoicePreset::Off => return None,
+            VoicePreset::Light => "highpass=f=80,afftdn=nr=10:nf=-50:tn=1".to_string(),
+            VoicePreset::Standard => {
+                "highpass=f=100,afftdn=nr=15:nf=-45:tn=1,agate=threshold=0.015:ratio=3:attack=5:release=200"
+                    .to_string()
+            }
+            VoicePreset::Strong => {
+                "highpass=f=120,afftdn=nr=25:nf=-40:tn=1,anlmdn=s=0.0003,agate=threshold=0.03:ratio=4:attack=5:release=150"
+                    .to_string()
+            }
+            VoicePreset::Rnnoise => {
+                let Some(model) = &self.rnnoise_model else {
+                    eprintln!("[CLIPS_APP] No RNNoise model configured, leaving voice untouched");
+                    return None;
+                };
+                format!(
+                    "highpass=f=80,arnndn=m={},agate=threshold=0.01:ratio=2:attack=5:release=250",
+                    escape_filter_path(model)
+                )
+            }
+        };
+        Some(filter)
+    }
+}
+
+/// Escapes a path for use as a filter option inside `-filter_complex`: once
+/// for the option parser and again for the graph parser.
+fn escape_filter_path(path: &Path) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let option = escape(&path.to_string_lossy(), &['\\', '\'', ':']);
+    escape(&option, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_paths_for_the_option_and_graph_parsers() {
+        let escaped = |path: &str| escape_filter_path(Path::new(path));
+        assert_eq!(escaped("/home/me/models/bd.rnnn"), "/home/me/models/bd.rnnn");
+        assert_eq!(escaped("/mnt/c:/bd.rnnn"), r"/mnt/c\\:/bd.rnnn");
+        assert_eq!(escaped("/home/me/it's.rnnn"), r"/home/me/it\\\'s.rnnn");
+        assert_eq!(escaped("/models/[v2],final;.rnnn"), r"/models/\[v2\]\,final\;.rnnn");
+        assert_eq!(escaped(r"/models/back\slash"), r"/models/back\\\\slash");
+    }
+
+    #[test]
+    fn rnnoise_needs_a_model() {
+        let config = VoiceConfig {
+            default_preset: VoicePreset::Light,
+            rnnoise_model: None,
+        };
+        assert!(!config.presets().contains(&VoicePreset::Rnnoise));
+        assert_eq!(config.filter(VoicePreset::Rnnoise), None);
+        assert_eq!(config.filter(VoicePreset::Off), None);
+
+        let config = VoiceConfig {
+            rnnoise_model: Some(PathBuf::from("/models/bd:v1.rnnn")),
+            ..config
+        };
+        assert!(config.presets().contains(&VoicePreset::Rnnoise));
+        let filter = config.filter(VoicePreset::Rnnoise).unwrap();
+        assert!(filter.contains(r"arnndn=m=/models/bd\\:v1.rnnn,"), "{filter}");
+    }
+}
@@ -1,7 +1,7 @@
 use gtk::{Adjustment, Box, Button, ComboBoxText, Entry, Label, Notebook, Orientation, Separator, SpinButton, Switch};
 use gtk::prelude::*;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,12 +18,22 @@ pub struct CaptureStatus {
     pub fps: u32,
     pub target: String,
     pub audio_tracks: Vec<String>,
+    pub save_durations: Vec<u32>,
     pub last_saved: Option<String>,
     pub hotkey: String,
     pub message: Option<String>,
     pub is_saving: bool,
     pub failed_uploads: Vec<FailedUploadEntry>,
     pub replay_mode: String,
+    pub hotkey_devices: usize,
+    pub restart_count: u32,
+    pub last_exit: Option<String>,
+    pub recent_stderr: Vec<String>,
+    pub recording_secs: Option<u64>,
+    pub disk_used_bytes: Option<u64>,
+    pub disk_quota_bytes: Option<u64>,
+    pub disk_ring_bytes: Option<u64>,
+    pub active_profile: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,31 +43,46 @@ pub struct CaptureSettings {
     pub fps: u32,
     pub target: String,
     pub audio_tracks: Vec<String>,
+    pub save_durations: String,
 }
 
 type ToggleCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(bool) + 'static>>>>;
 type SaveCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(u32) + 'static>>>>;
 type SettingsCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(CaptureSettings) + 'static>>>>;
 type ModeCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(String) + 'static>>>>;
+type SaveProfileCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(String, CaptureSettings) + 'static>>>>;
+type DeleteProfileCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(String) + 'static>>>>;
 type FailedUploadCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(String, String) + 'static>>>>; // (action, id)
 
 pub struct CaptureView {
     container: Box,
     status_label: Label,
+    devices_label: Label,
+    disk_label: Label,
+    recorder_health_label: Label,
+    recorder_log_label: Label,
     running_switch: Switch,
     mode_switch: Switch,
-    save_1m_button: Button,
-    save_5m_button: Button,
+    save_buttons_box: Box,
+    save_buttons: Rc<RefCell<Vec<(u32, Button)>>>,
+    save_durations_entry: Entry,
+    record_button: Button,
+    recording: Rc<Cell<bool>>,
     buffer_spin: SpinButton,
     bitrate_spin: SpinButton,
     fps_spin: SpinButton,
     target_combo: ComboBoxText,
     audio_entries: Vec<Entry>,
+    profile_label: Label,
+    profile_entry: Entry,
     failed_uploads_list: Box,
     toggle_callback: ToggleCallback,
     save_callback: SaveCallback,
+    record_callback: ToggleCallback,
     settings_callback: SettingsCallback,
     mode_callback: ModeCallback,
+    save_profile_callback: SaveProfileCallback,
+    delete_profile_callback: DeleteProfileCallback,
     failed_upload_callback: FailedUploadCallback,
 }
 
@@ -101,6 +126,11 @@ impl CaptureView {
                 \n  font-size: 16px;\
                 \n  font-family: monospace;\
                 \n}\
+                \n.recorder-log {\
+                \n  color: rgba(255, 180, 180, 0.85);\
+                \n  font-size: 11px;\
+                \n  font-family: monospace;\
+                \n}\
                 \n.save-button {\
                 \n  min-height: 50px;\
                 \n  font-size: 16px;\
@@ -204,6 +234,33 @@ impl CaptureView {
         running_box.append(&running_switch);
         main_tab.append(&running_box);
 
+        // Hotkey device count (follows keyboards being plugged/unplugged)
+        let devices_label = Label::new(Some("Hotkeys: no input devices"));
+        devices_label.set_halign(gtk::Align::Start);
+        devices_label.add_css_class("capture-label");
+        main_tab.append(&devices_label);
+
+        // Space used by replays and processed clips against the quota
+        let disk_label = Label::new(None);
+        disk_label.set_halign(gtk::Align::Start);
+        disk_label.add_css_class("capture-label");
+        disk_label.set_visible(false);
+        main_tab.append(&disk_label);
+
+        // Recorder restarts and its last few stderr lines, shown once it has crashed
+        let recorder_health_label = Label::new(None);
+        recorder_health_label.set_halign(gtk::Align::Start);
+        recorder_health_label.add_css_class("capture-label");
+        recorder_health_label.set_visible(false);
+        main_tab.append(&recorder_health_label);
+        let recorder_log_label = Label::new(None);
+        recorder_log_label.set_halign(gtk::Align::Start);
+        recorder_log_label.set_wrap(true);
+        recorder_log_label.set_selectable(true);
+        recorder_log_label.add_css_class("recorder-log");
+        recorder_log_label.set_visible(false);
+        main_tab.append(&recorder_log_label);
+
         // Separator
         let separator = Separator::new(Orientation::Horizontal);
         separator.set_margin_top(8);
@@ -219,14 +276,15 @@ impl CaptureView {
             .hexpand(false) // Don't expand horizontally
             .build();
 
-        let save_1m_button = Button::with_label("Save 1m");
-        save_1m_button.add_css_class("save-button");
-        let save_5m_button = Button::with_label("Save 5m");
-        save_5m_button.add_css_class("save-button");
-        buttons_box.append(&save_1m_button);
-        buttons_box.append(&save_5m_button);
+        // Filled in from the configured save durations on each status update
+        let save_buttons: Rc<RefCell<Vec<(u32, Button)>>> = Rc::new(RefCell::new(Vec::new()));
         main_tab.append(&buttons_box);
 
+        // Full-length recording alongside the replay buffer
+        let record_button = Button::with_label("Start recording");
+        record_button.set_margin_top(8);
+        main_tab.append(&record_button);
+
         let main_label = Label::new(Some("Main"));
         notebook.append_page(&main_tab, Some(&main_label));
         
@@ -358,10 +416,43 @@ impl CaptureView {
             settings_box.append(entry);
         }
 
+        // Save buttons
+        let save_durations_label = Label::new(Some("Save buttons (e.g. 30s, 1m, 2m)"));
+        save_durations_label.set_halign(gtk::Align::Start);
+        save_durations_label.add_css_class("capture-label");
+        settings_box.append(&save_durations_label);
+
+        let save_durations_entry = Entry::builder().placeholder_text("1m, 5m").build();
+        settings_box.append(&save_durations_entry);
+
         // Apply button
         let apply_button = Button::with_label("Apply settings");
         settings_box.append(&apply_button);
 
+        // Per-game profile
+        let profile_separator = Separator::new(Orientation::Horizontal);
+        profile_separator.set_margin_top(8);
+        profile_separator.set_margin_bottom(8);
+        settings_box.append(&profile_separator);
+
+        let profile_label = Label::new(Some("Game profile"));
+        profile_label.set_halign(gtk::Align::Start);
+        profile_label.add_css_class("capture-label");
+        settings_box.append(&profile_label);
+
+        let profile_entry = Entry::builder().placeholder_text("Game name").build();
+        settings_box.append(&profile_entry);
+
+        let profile_buttons = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .build();
+        let save_profile_button = Button::with_label("Save as profile");
+        let delete_profile_button = Button::with_label("Delete profile");
+        profile_buttons.append(&save_profile_button);
+        profile_buttons.append(&delete_profile_button);
+        settings_box.append(&profile_buttons);
+
         let settings_label = Label::new(Some("Settings"));
         notebook.append_page(&settings_box, Some(&settings_label));
 
@@ -377,8 +468,11 @@ impl CaptureView {
 
         let toggle_callback: ToggleCallback = Rc::new(RefCell::new(None));
         let save_callback: SaveCallback = Rc::new(RefCell::new(None));
+        let record_callback: ToggleCallback = Rc::new(RefCell::new(None));
         let settings_callback: SettingsCallback = Rc::new(RefCell::new(None));
         let mode_callback: ModeCallback = Rc::new(RefCell::new(None));
+        let save_profile_callback: SaveProfileCallback = Rc::new(RefCell::new(None));
+        let delete_profile_callback: DeleteProfileCallback = Rc::new(RefCell::new(None));
         let failed_upload_callback: FailedUploadCallback = Rc::new(RefCell::new(None));
 
         {
@@ -391,37 +485,30 @@ impl CaptureView {
             });
         }
 
+        let recording = Rc::new(Cell::new(false));
         {
-            let save_callback = save_callback.clone();
-            save_1m_button.connect_clicked(move |_| {
-                if let Some(cb) = save_callback.borrow().as_ref() {
-                    cb(60);
-                }
-            });
-        }
-
-        {
-            let save_callback = save_callback.clone();
-            save_5m_button.connect_clicked(move |_| {
-                if let Some(cb) = save_callback.borrow().as_ref() {
-                    cb(300);
+            let record_callback = record_callback.clone();
+            let recording = recording.clone();
+            record_button.connect_clicked(move |_| {
+                if let Some(cb) = record_callback.borrow().as_ref() {
+                    cb(!recording.get());
                 }
             });
         }
 
-        {
-            let settings_callback = settings_callback.clone();
+        let read_settings: Rc<dyn Fn() -> CaptureSettings> = {
             let buffer_spin = buffer_spin.clone();
             let bitrate_spin = bitrate_spin.clone();
             let fps_spin = fps_spin.clone();
             let target_combo = target_combo.clone();
             let audio_entries = audio_entries.clone();
-            apply_button.connect_clicked(move |_| {
+            let save_durations_entry = save_durations_entry.clone();
+            Rc::new(move || {
                 let target = target_combo
                     .active_id()
                     .map(|id| id.to_string())
                     .unwrap_or_else(|| "screen".to_string());
-                let settings = CaptureSettings {
+                CaptureSettings {
                     buffer_seconds: buffer_spin.value() as u32,
                     bitrate: bitrate_spin.value() as u32,
                     fps: fps_spin.value() as u32,
@@ -431,9 +518,37 @@ impl CaptureView {
                         .map(|entry| entry.text().to_string())
                         .filter(|s| !s.trim().is_empty())
                         .collect(),
-                };
+                    save_durations: save_durations_entry.text().to_string(),
+                }
+            })
+        };
+
+        {
+            let settings_callback = settings_callback.clone();
+            let read_settings = read_settings.clone();
+            apply_button.connect_clicked(move |_| {
                 if let Some(cb) = settings_callback.borrow().as_ref() {
-                    cb(settings);
+                    cb(read_settings());
+                }
+            });
+        }
+
+        {
+            let save_profile_callback = save_profile_callback.clone();
+            let profile_entry = profile_entry.clone();
+            save_profile_button.connect_clicked(move |_| {
+                if let Some(cb) = save_profile_callback.borrow().as_ref() {
+                    cb(profile_entry.text().to_string(), read_settings());
+                }
+            });
+        }
+
+        {
+            let delete_profile_callback = delete_profile_callback.clone();
+            let profile_entry = profile_entry.clone();
+            delete_profile_button.connect_clicked(move |_| {
+                if let Some(cb) = delete_profile_callback.borrow().as_ref() {
+                    cb(profile_entry.text().to_string());
                 }
             });
         }
@@ -452,20 +567,32 @@ impl CaptureView {
         Self {
             container: outer,
             status_label,
+            devices_label,
+            disk_label,
+            recorder_health_label,
+            recorder_log_label,
             running_switch,
             mode_switch,
-            save_1m_button,
-            save_5m_button,
+            save_buttons_box: buttons_box,
+            save_buttons,
+            save_durations_entry,
+            record_button,
+            recording,
             buffer_spin,
             bitrate_spin,
             fps_spin,
             target_combo,
             audio_entries,
+            profile_label,
+            profile_entry,
             failed_uploads_list,
             toggle_callback,
             save_callback,
+            record_callback,
             settings_callback,
             mode_callback,
+            save_profile_callback,
+            delete_profile_callback,
             failed_upload_callback,
         }
     }
@@ -488,6 +615,13 @@ impl CaptureView {
         *self.save_callback.borrow_mut() = Some(std::boxed::Box::new(callback));
     }
 
+    pub fn on_record<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        *self.record_callback.borrow_mut() = Some(std::boxed::Box::new(callback));
+    }
+
     pub fn on_apply_settings<F>(&self, callback: F)
     where
         F: Fn(CaptureSettings) + 'static,
@@ -502,6 +636,20 @@ impl CaptureView {
         *self.mode_callback.borrow_mut() = Some(std::boxed::Box::new(callback));
     }
     
+    pub fn on_save_profile<F>(&self, callback: F)
+    where
+        F: Fn(String, CaptureSettings) + 'static, // (game, settings)
+    {
+        *self.save_profile_callback.borrow_mut() = Some(std::boxed::Box::new(callback));
+    }
+
+    pub fn on_delete_profile<F>(&self, callback: F)
+    where
+        F: Fn(String) + 'static,
+    {
+        *self.delete_profile_callback.borrow_mut() = Some(std::boxed::Box::new(callback));
+    }
+
     pub fn on_failed_upload_action<F>(&self, callback: F)
     where
         F: Fn(String, String) + 'static, // (action, id)
@@ -531,6 +679,14 @@ impl CaptureView {
             }
         }
 
+        match status.active_profile.as_deref() {
+            Some(game) => {
+                self.profile_label.set_text(&format!("Game profile (active: {game})"));
+                self.profile_entry.set_text(game);
+            }
+            None => self.profile_label.set_text("Game profile"),
+        }
+
         let message = status
             .message
             .as_deref()
@@ -540,16 +696,92 @@ impl CaptureView {
                 "Replay recorder stopped"
             });
         self.status_label.set_text(message);
+
+        let devices_text = match status.hotkey_devices {
+            0 => "Hotkeys: no input devices".to_string(),
+            1 => "Hotkeys: monitoring 1 device".to_string(),
+            n => format!("Hotkeys: monitoring {n} devices"),
+        };
+        self.devices_label.set_text(&devices_text);
+
+        self.disk_label.set_visible(status.disk_used_bytes.is_some());
+        if let Some(used) = status.disk_used_bytes {
+            let mut text = match status.disk_quota_bytes {
+                Some(quota) => format!("Disk: {} of {}", format_size(used), format_size(quota)),
+                None => format!("Disk: {} used", format_size(used)),
+            };
+            if let Some(ring) = status.disk_ring_bytes.filter(|ring| *ring > 0) {
+                text.push_str(&format!(" + {} replay buffer", format_size(ring)));
+            }
+            self.disk_label.set_text(&text);
+        }
+
+        let crashed = status.last_exit.is_some();
+        self.recorder_health_label.set_visible(crashed);
+        self.recorder_log_label.set_visible(crashed && !status.recent_stderr.is_empty());
+        if let Some(last_exit) = status.last_exit.as_deref() {
+            self.recorder_health_label.set_text(&format!(
+                "Recorder restarts: {} (last exit: {})",
+                status.restart_count, last_exit
+            ));
+            self.recorder_log_label.set_text(&status.recent_stderr.join("\n"));
+        }
         
+        let durations_text = status
+            .save_durations
+            .iter()
+            .map(|secs| format_duration(*secs))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.save_durations_entry.set_text(&durations_text);
+        self.rebuild_save_buttons(&status.save_durations);
+
         // Handle saving state - disable save buttons when not running or currently saving
         let can_save = status.running && !status.is_saving;
-        self.save_1m_button.set_sensitive(can_save);
-        self.save_5m_button.set_sensitive(can_save);
+        for (_, button) in self.save_buttons.borrow().iter() {
+            button.set_sensitive(can_save);
+        }
+
+        self.recording.set(status.recording_secs.is_some());
+        match status.recording_secs {
+            Some(secs) => self
+                .record_button
+                .set_label(&format!("Stop recording ({})", format_duration(secs as u32))),
+            None => self.record_button.set_label("Start recording"),
+        }
+        self.record_button
+            .set_sensitive(status.running || status.recording_secs.is_some());
         
         // Update failed uploads list
         self.update_failed_uploads_list(&status.failed_uploads);
     }
     
+    fn rebuild_save_buttons(&self, durations: &[u32]) {
+        let current: Vec<u32> = self.save_buttons.borrow().iter().map(|(secs, _)| *secs).collect();
+        if current == durations {
+            return;
+        }
+
+        while let Some(child) = self.save_buttons_box.first_child() {
+            self.save_buttons_box.remove(&child);
+        }
+
+        let mut buttons = self.save_buttons.borrow_mut();
+        buttons.clear();
+        for &secs in durations {
+            let button = Button::with_label(&format!("Save {}", format_duration(secs)));
+            button.add_css_class("save-button");
+            let save_callback = self.save_callback.clone();
+            button.connect_clicked(move |_| {
+                if let Some(cb) = save_callback.borrow().as_ref() {
+                    cb(secs);
+                }
+            });
+            self.save_buttons_box.append(&button);
+            buttons.push((secs, button));
+        }
+    }
+
     fn update_failed_uploads_list(&self, uploads: &[FailedUploadEntry]) {
         // Clear existing children
         while let Some(child) = self.failed_uploads_list.first_child() {
@@ -628,3 +860,29 @@ impl CaptureView {
         }
     }
 }
+
+fn format_duration(secs: u32) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{hours}h"));
+    }
+    if minutes > 0 {
+        out.push_str(&format!("{minutes}m"));
+    }
+    if seconds > 0 || out.is_empty() {
+        out.push_str(&format!("{seconds}s"));
+    }
+    out
+}
+
+fn format_size(bytes: u64) -> String {
+    const GIB: f64 = (1u64 << 30) as f64;
+    const MIB: f64 = (1u64 << 20) as f64;
+    let bytes = bytes as f64;
+    if bytes >= GIB {
+        format!("{:.1} GiB", bytes / GIB)
+    } else {
+        format!("{:.0} MiB", bytes / MIB)
+    }
+}
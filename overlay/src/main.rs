@@ -1,10 +1,11 @@
-use gtk::{gdk, glib, Window, DrawingArea};
+use gtk::{gdk, glib, Window, DrawingArea, Label, Orientation};
 use gtk::prelude::*;
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
 use std::io::{self, BufRead, Write};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::thread;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 
 mod progress_view;
@@ -13,7 +14,7 @@ mod trimmer_view;
 mod capture_view;
 
 use progress_view::ProgressView;
-use picker_view::PickerView;
+use picker_view::{PickerTrack, PickerView, ShowPicker};
 use trimmer_view::TrimmerView;
 use capture_view::{CaptureView, CaptureStatus as CaptureStatusPayload, CaptureSettings as CaptureSettingsPayload};
 
@@ -27,16 +28,15 @@ enum Command {
         detail: String,
     },
     #[serde(rename = "show_picker")]
-    ShowPicker {
-        preview_path: Option<String>,
-        default_title: String,
-        default_game: String,
-        available_channels: Vec<String>,
-    },
+    ShowPicker(ShowPicker),
     #[serde(rename = "show_trimmer")]
     ShowTrimmer {
         video_path: String,
+        #[serde(default)]
+        audio_path: Option<String>,
         duration: f64,
+        #[serde(default)]
+        bookmarks: Vec<f64>,
     },
     #[serde(rename = "show_capture")]
     ShowCapture {
@@ -62,7 +62,12 @@ enum Response {
         title: String,
         game: String,
         action: String,
-        channels: Vec<String>,
+        tracks: Vec<PickerTrack>,
+        voice_preset: String,
+        duck_audio: bool,
+        normalize_loudness: bool,
+        audio_export: String,
+        export_preset: String,
     },
     #[serde(rename = "trimmer_result")]
     TrimmerResult {
@@ -82,9 +87,12 @@ enum Response {
 enum CaptureActionPayload {
     Toggle { enable: bool },
     Save { duration_secs: u32 },
+    Record { enable: bool },
     UpdateSettings { settings: CaptureSettingsPayload },
     UpdateMode { mode: String },
     FailedUpload { upload_action: String, id: String },
+    SaveProfile { game: String, settings: CaptureSettingsPayload },
+    DeleteProfile { game: String },
 }
 
 #[derive(Clone)]
@@ -98,6 +106,9 @@ enum ViewMode {
 struct AppState {
     window: Window,
     recording_indicator: Window,
+    recording_label: Label,
+    /// When the current full-length recording started, for the counter.
+    recording_since: Rc<Cell<Option<Instant>>>,
     view_mode: Rc<RefCell<ViewMode>>,
     progress_view: ProgressView,
     picker_view: PickerView,
@@ -164,6 +175,14 @@ impl AppState {
             ".transparent-window {\
                 \n  background-color: transparent;\
                 \n}\
+                \n.recording-time {\
+                \n  color: white;\
+                \n  background-color: rgba(0, 0, 0, 0.6);\
+                \n  border-radius: 4px;\
+                \n  padding: 0 6px;\
+                \n  font-family: monospace;\
+                \n  font-weight: 700;\
+                \n}\
             ",
         );
         if let Some(display) = gdk::Display::default() {
@@ -234,12 +253,23 @@ impl AppState {
             cr.stroke().unwrap();
         });
         
-        recording_indicator.set_child(Some(&indicator_area));
+        // Elapsed time, only shown during a full-length recording
+        let recording_label = Label::new(None);
+        recording_label.add_css_class("recording-time");
+        recording_label.set_visible(false);
+
+        let indicator_box = gtk::Box::new(Orientation::Horizontal, 6);
+        indicator_box.append(&recording_label);
+        indicator_box.append(&indicator_area);
+
+        recording_indicator.set_child(Some(&indicator_box));
         recording_indicator.hide(); // Start hidden
 
         Self {
             window,
             recording_indicator,
+            recording_label,
+            recording_since: Rc::new(Cell::new(None)),
             view_mode: Rc::new(RefCell::new(ViewMode::Progress)),
             progress_view,
             picker_view,
@@ -322,31 +352,45 @@ impl AppState {
         }
     }
 
+    fn update_recording_indicator(&self, status: &CaptureStatusPayload) {
+        match status.recording_secs {
+            Some(secs) => {
+                self.recording_since
+                    .set(Some(Instant::now() - Duration::from_secs(secs)));
+                self.recording_label.set_text(&format_elapsed(secs));
+                self.recording_label.set_visible(true);
+                self.set_recording_indicator_visible(true);
+            }
+            None => {
+                self.recording_since.set(None);
+                self.recording_label.set_visible(false);
+                self.set_recording_indicator_visible(status.running);
+            }
+        }
+    }
+
     fn handle_command(&self, cmd: Command) {
         match cmd {
             Command::Progress { stage, fraction, detail } => {
                 self.switch_to_progress();
                 self.progress_view.update(&stage, fraction, &detail);
             }
-            Command::ShowPicker { preview_path, default_title, default_game, available_channels } => {
+            Command::ShowPicker(picker) => {
                 self.switch_to_picker();
-                self.picker_view.show(
-                    preview_path.as_deref(),
-                    &default_title,
-                    &default_game,
-                    &available_channels,
-                );
+                self.picker_view.show(&picker);
             }
-            Command::ShowTrimmer { video_path, duration } => {
+            Command::ShowTrimmer { video_path, audio_path, duration, bookmarks } => {
                 self.switch_to_trimmer();
-                self.trimmer_view.show(&video_path, duration);
+                self.trimmer_view.show(&video_path, audio_path.as_deref(), duration, &bookmarks);
             }
             Command::ShowCapture { status } => {
                 self.switch_to_capture();
                 self.capture_view.update_status(&status);
+                self.update_recording_indicator(&status);
             }
             Command::CaptureStatus { status } => {
                 self.capture_view.update_status(&status);
+                self.update_recording_indicator(&status);
             }
             Command::SetVisibility { visible } => {
                 if visible {
@@ -447,7 +491,12 @@ fn main() {
             title: result.title,
             game: result.game,
             action: result.action,
-            channels: result.channels,
+            tracks: result.tracks,
+            voice_preset: result.voice_preset,
+            duck_audio: result.duck_audio,
+            normalize_loudness: result.normalize_loudness,
+            audio_export: result.audio_export,
+            export_preset: result.export_preset,
         };
         if let Ok(json) = serde_json::to_string(&response) {
             println!("{}", json);
@@ -521,6 +570,26 @@ fn main() {
         }
     });
 
+    state_rc.capture_view.on_record(|enable| {
+        let response = Response::CaptureAction {
+            action: CaptureActionPayload::Record { enable },
+        };
+        if let Ok(json) = serde_json::to_string(&response) {
+            println!("{}", json);
+            let _ = io::stdout().flush();
+        }
+    });
+
+    // Tick the recording counter locally between status updates
+    let recording_label = state_rc.recording_label.clone();
+    let recording_since = state_rc.recording_since.clone();
+    glib::timeout_add_seconds_local(1, move || {
+        if let Some(since) = recording_since.get() {
+            recording_label.set_text(&format_elapsed(since.elapsed().as_secs()));
+        }
+        glib::ControlFlow::Continue
+    });
+
     state_rc.capture_view.on_apply_settings(|settings| {
         let response = Response::CaptureAction {
             action: CaptureActionPayload::UpdateSettings { settings },
@@ -541,6 +610,26 @@ fn main() {
         }
     });
     
+    state_rc.capture_view.on_save_profile(|game, settings| {
+        let response = Response::CaptureAction {
+            action: CaptureActionPayload::SaveProfile { game, settings },
+        };
+        if let Ok(json) = serde_json::to_string(&response) {
+            println!("{}", json);
+            let _ = io::stdout().flush();
+        }
+    });
+
+    state_rc.capture_view.on_delete_profile(|game| {
+        let response = Response::CaptureAction {
+            action: CaptureActionPayload::DeleteProfile { game },
+        };
+        if let Ok(json) = serde_json::to_string(&response) {
+            println!("{}", json);
+            let _ = io::stdout().flush();
+        }
+    });
+
     state_rc.capture_view.on_failed_upload_action(|upload_action, id| {
         let response = Response::CaptureAction {
             action: CaptureActionPayload::FailedUpload { upload_action, id },
@@ -568,3 +657,13 @@ fn main() {
     main_loop.run();
     eprintln!("[OVERLAY] GTK main loop exited");
 }
+
+/// Formats a recording length as `REC mm:ss` (or `h:mm:ss` past an hour).
+fn format_elapsed(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("REC {hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("REC {minutes:02}:{seconds:02}")
+    }
+}
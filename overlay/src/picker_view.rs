@@ -1,5 +1,6 @@
-use gtk::{Box, Button, CheckButton, Entry, Label, Orientation};
+use gtk::{Box, Button, CheckButton, ComboBoxText, Entry, Label, Orientation, Scale};
 use gtk::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -8,9 +9,51 @@ pub struct PickerResult {
     pub title: String,
     pub game: String,
     pub action: String,
-    pub channels: Vec<String>,
+    pub tracks: Vec<PickerTrack>,
+    pub voice_preset: String,
+    pub duck_audio: bool,
+    pub normalize_loudness: bool,
+    pub audio_export: String,
+    pub export_preset: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickerTrack {
+    pub name: String,
+    pub enabled: bool,
+    pub volume: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickerOption {
+    pub id: String,
+    pub label: String,
+}
+
+/// What the app sends to open the picker for a clip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShowPicker {
+    pub default_title: String,
+    pub default_game: String,
+    pub audio_tracks: Vec<PickerTrack>,
+    /// Empty when no track is marked as voice.
+    pub voice_presets: Vec<PickerOption>,
+    pub voice_preset: String,
+    /// `None` hides the option when the tracks have nothing to duck.
+    pub duck_audio: Option<bool>,
+    pub loudness_target: f64,
+    pub normalize_loudness: bool,
+    pub audio_exports: Vec<PickerOption>,
+    pub export_presets: Vec<PickerOption>,
+    pub export_preset: String,
+    /// Presets with a size target, which carry the mix but no stems.
+    #[serde(default)]
+    pub mixed_only_presets: Vec<String>,
+}
+
+/// Slider range; 100% leaves the track at its captured level.
+const MAX_VOLUME: f64 = 2.0;
+
 type SubmitCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(PickerResult) + 'static>>>>;
 type CancelCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn() + 'static>>>>;
 
@@ -19,7 +62,14 @@ pub struct PickerView {
     title_entry: Entry,
     game_entry: Entry,
     channels_box: Box,
-    channel_checkboxes: Rc<RefCell<Vec<(String, CheckButton)>>>,
+    track_controls: Rc<RefCell<Vec<(String, CheckButton, Scale)>>>,
+    voice_label: Label,
+    voice_combo: ComboBoxText,
+    duck_check: CheckButton,
+    loudness_check: CheckButton,
+    export_combo: ComboBoxText,
+    video_combo: ComboBoxText,
+    mixed_only_presets: Rc<RefCell<Vec<String>>>,
     action_radio_upload: CheckButton,
     submit_callback: SubmitCallback,
     cancel_callback: CancelCallback,
@@ -80,6 +130,11 @@ impl PickerView {
                 \n  font-family: monospace;\
                 \n  font-size: 13px;\
                 \n}\
+                \nscale value {\
+                \n  color: white;\
+                \n  font-family: monospace;\
+                \n  font-size: 12px;\
+                \n}\
                 \nbutton {\
                 \n  background-color: rgba(50, 50, 50, 0.9);\
                 \n  color: white;\
@@ -137,18 +192,65 @@ impl PickerView {
         container.append(&game_entry);
 
         // Audio channels
-        let channels_label = Label::new(Some("Audio Channels:"));
+        let channels_label = Label::new(Some("Audio Tracks:"));
         channels_label.set_halign(gtk::Align::Start);
         channels_label.add_css_class("picker-label");
         container.append(&channels_label);
 
-        let channel_checkboxes: Rc<RefCell<Vec<(String, CheckButton)>>> = Rc::new(RefCell::new(Vec::new()));
+        let track_controls: Rc<RefCell<Vec<(String, CheckButton, Scale)>>> = Rc::new(RefCell::new(Vec::new()));
         let channels_box = Box::builder()
-            .orientation(Orientation::Horizontal)
-            .spacing(16)
+            .orientation(Orientation::Vertical)
+            .spacing(4)
             .build();
         container.append(&channels_box);
 
+        let voice_label = Label::new(Some("Voice Cleanup:"));
+        voice_label.set_halign(gtk::Align::Start);
+        voice_label.add_css_class("picker-label");
+        container.append(&voice_label);
+
+        let voice_combo = ComboBoxText::new();
+        container.append(&voice_combo);
+
+        let duck_check = CheckButton::with_label("Duck game audio under voice");
+        container.append(&duck_check);
+
+        let loudness_check = CheckButton::with_label("Normalise loudness");
+        container.append(&loudness_check);
+
+        let export_label = Label::new(Some("Audio Export:"));
+        export_label.set_halign(gtk::Align::Start);
+        export_label.add_css_class("picker-label");
+        container.append(&export_label);
+
+        let export_combo = ComboBoxText::new();
+        container.append(&export_combo);
+
+        let video_label = Label::new(Some("Video Export:"));
+        video_label.set_halign(gtk::Align::Start);
+        video_label.add_css_class("picker-label");
+        container.append(&video_label);
+
+        let video_combo = ComboBoxText::new();
+        container.append(&video_combo);
+
+        // Size targets leave no room for stems, so those presets lock the mix
+        let mixed_only_presets: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+        let mixed_only_clone = mixed_only_presets.clone();
+        let export_combo_lock = export_combo.clone();
+        video_combo.connect_changed(move |combo| {
+            let mixed_only = combo
+                .active_id()
+                .is_some_and(|id| mixed_only_clone.borrow().iter().any(|preset| preset.as_str() == id.as_str()));
+            if mixed_only {
+                export_combo_lock.set_active_id(Some("mixed"));
+                export_combo_lock.set_tooltip_text(Some("Presets with a size target only keep the mix"));
+            } else {
+                export_combo_lock.set_tooltip_text(None);
+            }
+            export_combo_lock.set_sensitive(!mixed_only);
+        });
+
         // Action selection
         let action_label = Label::new(Some("Action:"));
         action_label.set_halign(gtk::Align::Start);
@@ -194,7 +296,12 @@ impl PickerView {
         // Wire up OK button
         let title_entry_clone = title_entry.clone();
         let game_entry_clone = game_entry.clone();
-        let channel_checkboxes_clone = channel_checkboxes.clone();
+        let track_controls_clone = track_controls.clone();
+        let voice_combo_clone = voice_combo.clone();
+        let duck_check_clone = duck_check.clone();
+        let loudness_check_clone = loudness_check.clone();
+        let export_combo_clone = export_combo.clone();
+        let video_combo_clone = video_combo.clone();
         let action_radio_upload_clone = action_radio_upload.clone();
         let action_radio_move_clone = action_radio_move.clone();
         let action_radio_discard_clone = action_radio_discard.clone();
@@ -204,11 +311,14 @@ impl PickerView {
             let title = title_entry_clone.text().to_string();
             let game = game_entry_clone.text().to_string();
             
-            let channels: Vec<String> = channel_checkboxes_clone
+            let tracks: Vec<PickerTrack> = track_controls_clone
                 .borrow()
                 .iter()
-                .filter(|(_, cb)| cb.is_active())
-                .map(|(name, _)| name.clone())
+                .map(|(name, checkbox, scale)| PickerTrack {
+                    name: name.clone(),
+                    enabled: checkbox.is_active(),
+                    volume: scale.value() as f32,
+                })
                 .collect();
 
             let action = if action_radio_upload_clone.is_active() {
@@ -225,7 +335,21 @@ impl PickerView {
                 title,
                 game,
                 action,
-                channels,
+                tracks,
+                voice_preset: voice_combo_clone
+                    .active_id()
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "off".to_string()),
+                duck_audio: duck_check_clone.is_visible() && duck_check_clone.is_active(),
+                normalize_loudness: loudness_check_clone.is_active(),
+                audio_export: export_combo_clone
+                    .active_id()
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "mixed".to_string()),
+                export_preset: video_combo_clone
+                    .active_id()
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
             };
 
             if let Some(callback) = submit_callback_clone.borrow().as_ref() {
@@ -246,7 +370,14 @@ impl PickerView {
             title_entry,
             game_entry,
             channels_box,
-            channel_checkboxes,
+            track_controls,
+            voice_label,
+            voice_combo,
+            duck_check,
+            loudness_check,
+            export_combo,
+            video_combo,
+            mixed_only_presets,
             action_radio_upload,
             submit_callback,
             cancel_callback,
@@ -257,37 +388,84 @@ impl PickerView {
         &self.container
     }
 
-    pub fn show(
-        &self,
-        _preview_path: Option<&str>,
-        default_title: &str,
-        default_game: &str,
-        available_channels: &[String],
-    ) {
+    pub fn show(&self, picker: &ShowPicker) {
         // Set default values
-        self.title_entry.set_text(default_title);
-        self.game_entry.set_text(default_game);
+        self.title_entry.set_text(&picker.default_title);
+        self.game_entry.set_text(&picker.default_game);
 
-        // Clear and rebuild channel checkboxes
-        self.channel_checkboxes.borrow_mut().clear();
+        // Clear and rebuild track controls
+        self.track_controls.borrow_mut().clear();
         
-        // Clear existing checkboxes
+        // Clear existing rows
         while let Some(child) = self.channels_box.first_child() {
             self.channels_box.remove(&child);
         }
         
         // Add new checkboxes
-        for channel in available_channels {
-            let label = match channel.as_str() {
-                "voice" => "Voice",
-                "discord" => "Discord",
-                "game" => "Game",
-                other => other,
-            };
-            let checkbox = CheckButton::with_label(label);
-            checkbox.set_active(true); // Default to all channels enabled
-            self.channels_box.append(&checkbox);
-            self.channel_checkboxes.borrow_mut().push((channel.clone(), checkbox));
+        for track in &picker.audio_tracks {
+            let row = Box::builder()
+                .orientation(Orientation::Horizontal)
+                .spacing(12)
+                .build();
+
+            let checkbox = CheckButton::with_label(&track_label(&track.name));
+            checkbox.set_size_request(120, -1);
+            checkbox.set_active(track.enabled);
+
+            let scale = Scale::with_range(Orientation::Horizontal, 0.0, MAX_VOLUME, 0.05);
+            scale.set_value(f64::from(track.volume).clamp(0.0, MAX_VOLUME));
+            scale.set_draw_value(true);
+            scale.set_format_value_func(|_, value| format!("{:.0}%", value * 100.0));
+            scale.set_size_request(200, -1);
+            scale.set_hexpand(true);
+            scale.set_sensitive(track.enabled);
+
+            // Muted tracks keep their volume for next time
+            let scale_clone = scale.clone();
+            checkbox.connect_toggled(move |checkbox| {
+                scale_clone.set_sensitive(checkbox.is_active());
+            });
+
+            row.append(&checkbox);
+            row.append(&scale);
+            self.channels_box.append(&row);
+            self.track_controls.borrow_mut().push((track.name.clone(), checkbox, scale));
+        }
+
+        // Hidden when no track is marked as voice
+        self.voice_combo.remove_all();
+        for preset in &picker.voice_presets {
+            self.voice_combo.append(Some(&preset.id), &preset.label);
+        }
+        if !self.voice_combo.set_active_id(Some(&picker.voice_preset)) {
+            self.voice_combo.set_active(Some(0));
+        }
+        self.voice_label.set_visible(!picker.voice_presets.is_empty());
+        self.voice_combo.set_visible(!picker.voice_presets.is_empty());
+
+        self.duck_check.set_active(picker.duck_audio.unwrap_or(false));
+        self.duck_check.set_visible(picker.duck_audio.is_some());
+
+        self.loudness_check.set_label(Some(&format!(
+            "Normalise loudness ({} LUFS)",
+            picker.loudness_target
+        )));
+        self.loudness_check.set_active(picker.normalize_loudness);
+
+        // Mixed only unless picked otherwise for this clip
+        self.export_combo.remove_all();
+        for export in &picker.audio_exports {
+            self.export_combo.append(Some(&export.id), &export.label);
+        }
+        self.export_combo.set_active(Some(0));
+
+        *self.mixed_only_presets.borrow_mut() = picker.mixed_only_presets.clone();
+        self.video_combo.remove_all();
+        for preset in &picker.export_presets {
+            self.video_combo.append(Some(&preset.id), &preset.label);
+        }
+        if !self.video_combo.set_active_id(Some(&picker.export_preset)) {
+            self.video_combo.set_active(Some(0));
         }
 
         // Set default action to upload
@@ -309,3 +487,11 @@ impl PickerView {
     }
 }
 
+/// Capitalises the track name for display, e.g. `voice` becomes `Voice`.
+fn track_label(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
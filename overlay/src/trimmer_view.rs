@@ -9,6 +9,10 @@ pub struct TrimmerResult {
     pub end_time: f64,
 }
 
+/// How far (in microseconds) a separate audio track may drift from the video
+/// before it's seeked back in line.
+const AUDIO_DRIFT_US: i64 = 100_000;
+
 type SubmitCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn(TrimmerResult) + 'static>>>>;
 type CancelCallback = Rc<RefCell<Option<std::boxed::Box<dyn Fn() + 'static>>>>;
 
@@ -23,6 +27,9 @@ pub struct TrimmerView {
     start_pos: Rc<RefCell<f64>>, // 0.0 to 1.0
     end_pos: Rc<RefCell<f64>>,   // 0.0 to 1.0
     current_pos: Rc<RefCell<f64>>, // Current playback position 0.0 to 1.0
+    bookmarks: Rc<RefCell<Vec<f64>>>, // 0.0 to 1.0
+    /// Played instead of the video's own audio and kept in step with it
+    audio: Rc<RefCell<Option<gtk::MediaFile>>>,
     #[allow(dead_code)]
     dragging: Rc<RefCell<Option<DragTarget>>>,
     submit_callback: SubmitCallback,
@@ -216,6 +223,7 @@ impl TrimmerView {
         let start_pos = Rc::new(RefCell::new(0.0));
         let end_pos = Rc::new(RefCell::new(1.0));
         let current_pos = Rc::new(RefCell::new(0.0));
+        let bookmarks: Rc<RefCell<Vec<f64>>> = Rc::new(RefCell::new(Vec::new()));
         let dragging: Rc<RefCell<Option<DragTarget>>> = Rc::new(RefCell::new(None));
         let was_playing: Rc<RefCell<bool>> = Rc::new(RefCell::new(false));
         // Counter to skip multiple sync cycles after seeking (need ~3 cycles for 50ms delay + seek)
@@ -225,6 +233,7 @@ impl TrimmerView {
         let start_pos_draw = start_pos.clone();
         let end_pos_draw = end_pos.clone();
         let current_pos_draw = current_pos.clone();
+        let bookmarks_draw = bookmarks.clone();
         
         timeline.set_draw_func(move |_area, cr, width, height| {
             let start = *start_pos_draw.borrow();
@@ -241,6 +250,16 @@ impl TrimmerView {
             let end_x = end * width as f64;
             cr.rectangle(start_x, 0.0, end_x - start_x, height as f64);
             let _ = cr.fill();
+
+            // Bookmarked moments
+            cr.set_source_rgb(1.0, 0.8, 0.2);
+            cr.set_line_width(2.0);
+            for bookmark in bookmarks_draw.borrow().iter() {
+                let x = bookmark * width as f64;
+                cr.move_to(x, height as f64 * 0.5);
+                cr.line_to(x, height as f64);
+            }
+            let _ = cr.stroke();
             
             // Playhead (current position)
             cr.set_source_rgb(1.0, 1.0, 1.0);
@@ -500,9 +519,14 @@ impl TrimmerView {
         let video_sync = video.clone();
         let dragging_sync = dragging.clone();
         let seeking_skip_sync = seeking_skip_cycles.clone();
+        let audio: Rc<RefCell<Option<gtk::MediaFile>>> = Rc::new(RefCell::new(None));
+        let audio_sync = audio.clone();
         
         glib::timeout_add_local(std::time::Duration::from_millis(33), move || {
             if let Some(media_stream) = video_sync.media_stream() {
+                if let Some(audio) = audio_sync.borrow().as_ref() {
+                    follow_video(audio, &media_stream);
+                }
                 let timestamp = media_stream.timestamp(); // in microseconds
                 let dur = *duration_sync.borrow();
                 
@@ -663,6 +687,8 @@ impl TrimmerView {
             start_pos,
             end_pos,
             current_pos,
+            bookmarks,
+            audio,
             dragging,
             submit_callback,
             cancel_callback,
@@ -678,12 +704,25 @@ impl TrimmerView {
         if let Some(media) = self.video.media_stream() {
             media.pause();
         }
+        if let Some(audio) = self.audio.borrow().as_ref() {
+            audio.pause();
+        }
     }
 
-    pub fn show(&self, video_path: &str, duration: f64) {
+    /// `bookmarks` are offsets in seconds, marked on the timeline. With
+    /// `audio_path` that file plays in place of the video's own audio.
+    pub fn show(&self, video_path: &str, audio_path: Option<&str>, duration: f64, bookmarks: &[f64]) {
         // Set video file
         let file = gtk::gio::File::for_path(video_path);
         self.video.set_file(Some(&file));
+
+        let audio = audio_path.map(|path| gtk::MediaFile::for_file(&gtk::gio::File::for_path(path)));
+        if let Some(media_stream) = self.video.media_stream() {
+            media_stream.set_muted(audio.is_some());
+        }
+        if let Some(previous) = self.audio.replace(audio) {
+            previous.pause();
+        }
         
         // Store duration
         *self.duration.borrow_mut() = duration;
@@ -695,6 +734,11 @@ impl TrimmerView {
         *self.start_pos.borrow_mut() = 0.0;
         *self.end_pos.borrow_mut() = 1.0;
         *self.current_pos.borrow_mut() = 0.0;
+        *self.bookmarks.borrow_mut() = bookmarks
+            .iter()
+            .filter(|_| duration > 0.0)
+            .map(|offset| (offset / duration).clamp(0.0, 1.0))
+            .collect();
         
         // Update time labels
         self.start_label.set_text(&format!("Start: {}", format_time(0.0)));
@@ -719,6 +763,17 @@ impl TrimmerView {
     }
 }
 
+/// Mirrors the video's play state on `audio` and seeks it back in step when
+/// the video jumps or the two drift apart.
+fn follow_video(audio: &gtk::MediaFile, video: &gtk::MediaStream) {
+    if audio.is_playing() != video.is_playing() {
+        audio.set_playing(video.is_playing());
+    }
+    if (audio.timestamp() - video.timestamp()).abs() > AUDIO_DRIFT_US {
+        audio.seek(video.timestamp());
+    }
+}
+
 fn format_time(seconds: f64) -> String {
     let total_secs = seconds as i64;
     let mins = total_secs / 60;